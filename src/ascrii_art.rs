@@ -12,4 +12,4 @@ pub const PROCESSING_TEXT: &str = "
 ██████  ██████  ██    ██ ██      █████   ███████ ███████ ██ ██ ██  ██ ██   ███          
 ██      ██   ██ ██    ██ ██      ██           ██      ██ ██ ██  ██ ██ ██    ██          
 ██      ██   ██  ██████   ██████ ███████ ███████ ███████ ██ ██   ████  ██████  ██ ██ ██ 
-";
\ No newline at end of file
+";
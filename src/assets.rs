@@ -1,39 +1,288 @@
-pub mod templates {
-    pub const CONTAINER_XML: &[u8] = include_bytes!("../assets/templates/container.xml");
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
-    pub const IBOOKS_XML: &[u8] = include_bytes!("../assets/templates/ibooks.xml");
+use once_cell::sync::OnceCell;
 
-    pub const COVER_HTML: &str = include_str!("../assets/templates/cover.html");
+use crate::paths;
+use crate::workspace::Workspace;
 
-    pub const PAGE_HTML: &str = include_str!("../assets/templates/page.html");
+// ─── Asset Pack ──────────────────────────────────────────────────────────────
 
-    pub const TOC_NCX: &str = include_str!("../assets/templates/toc.ncx");
+/// A template or image used to build books.
+///
+/// Every asset is built into the binary and can be overridden by a file in
+/// the asset pack folder, see [`paths::asset_pack_folder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Asset {
+    ContainerXml,
+    IbooksXml,
+    CoverHtml,
+    PageHtml,
+    TocNcx,
+    V2ContentOpf,
+    V2NavXhtml,
+    V3ContentOpf,
+    V3NavXhtml,
+    EndOfChapter,
+    EndOfVolume,
+    VolumeCoverNotFound,
+}
+
+/// How the content of an asset is checked when the pack is loaded
+enum AssetKind {
+    Xml,
+    Handlebars,
+    Mustache,
+    Image,
+}
+
+impl Asset {
+    pub const ALL: [Asset; 12] = [
+        Asset::ContainerXml,
+        Asset::IbooksXml,
+        Asset::CoverHtml,
+        Asset::PageHtml,
+        Asset::TocNcx,
+        Asset::V2ContentOpf,
+        Asset::V2NavXhtml,
+        Asset::V3ContentOpf,
+        Asset::V3NavXhtml,
+        Asset::EndOfChapter,
+        Asset::EndOfVolume,
+        Asset::VolumeCoverNotFound,
+    ];
+
+    /// Path of the asset within a pack, always with forward slashes
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Asset::ContainerXml => "templates/container.xml",
+            Asset::IbooksXml => "templates/ibooks.xml",
+            Asset::CoverHtml => "templates/cover.html",
+            Asset::PageHtml => "templates/page.html",
+            Asset::TocNcx => "templates/toc.ncx",
+            Asset::V2ContentOpf => "templates/v2/content.opf",
+            Asset::V2NavXhtml => "templates/v2/nav.xhtml",
+            Asset::V3ContentOpf => "templates/v3/content.opf",
+            Asset::V3NavXhtml => "templates/v3/nav.xhtml",
+            Asset::EndOfChapter => "endofthischapter.png",
+            Asset::EndOfVolume => "endofthisvolume.png",
+            Asset::VolumeCoverNotFound => "volcovernotfound.png",
+        }
+    }
+
+    fn built_in(&self) -> &'static [u8] {
+        match self {
+            Asset::ContainerXml => include_bytes!("../assets/templates/container.xml"),
+            Asset::IbooksXml => include_bytes!("../assets/templates/ibooks.xml"),
+            Asset::CoverHtml => include_bytes!("../assets/templates/cover.html"),
+            Asset::PageHtml => include_bytes!("../assets/templates/page.html"),
+            Asset::TocNcx => include_bytes!("../assets/templates/toc.ncx"),
+            Asset::V2ContentOpf => include_bytes!("../assets/templates/v2/content.opf"),
+            Asset::V2NavXhtml => include_bytes!("../assets/templates/v2/nav.xhtml"),
+            Asset::V3ContentOpf => include_bytes!("../assets/templates/v3/content.opf"),
+            Asset::V3NavXhtml => include_bytes!("../assets/templates/v3/nav.xhtml"),
+            Asset::EndOfChapter => include_bytes!("../assets/endofthischapter.png"),
+            Asset::EndOfVolume => include_bytes!("../assets/endofthisvolume.png"),
+            Asset::VolumeCoverNotFound => include_bytes!("../assets/volcovernotfound.png"),
+        }
+    }
+
+    fn kind(&self) -> AssetKind {
+        match self {
+            Asset::ContainerXml | Asset::IbooksXml => AssetKind::Xml,
+            Asset::CoverHtml | Asset::PageHtml => AssetKind::Handlebars,
+            Asset::TocNcx
+            | Asset::V2ContentOpf
+            | Asset::V2NavXhtml
+            | Asset::V3ContentOpf
+            | Asset::V3NavXhtml => AssetKind::Mustache,
+            Asset::EndOfChapter | Asset::EndOfVolume | Asset::VolumeCoverNotFound => {
+                AssetKind::Image
+            }
+        }
+    }
+
+    /// Checks `content` can be used in place of this asset
+    fn check(&self, content: &[u8]) -> Result<(), String> {
+        if let AssetKind::Image = self.kind() {
+            return image::load_from_memory(content)
+                .map(|_| ())
+                .map_err(|error| format!("not a readable image: {}", error));
+        }
+
+        let text = std::str::from_utf8(content).map_err(|_| String::from("not UTF-8 text"))?;
+
+        match self.kind() {
+            AssetKind::Handlebars => handlebars::Handlebars::new()
+                .register_template_string(self.file_name(), text)
+                .map_err(|error| format!("not a valid handlebars template: {}", error)),
+            AssetKind::Mustache => mustache::compile_str(text)
+                .map(|_| ())
+                .map_err(|error| format!("not a valid mustache template: {}", error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The assets overridden by the user, by position in `Asset::ALL`
+struct AssetPack {
+    overrides: Vec<Option<&'static [u8]>>,
+}
+
+static ASSET_PACK: OnceCell<AssetPack> = OnceCell::new();
+
+/// Returned when files in the asset pack folder cannot be used
+#[derive(Debug, Clone)]
+pub struct AssetPackError {
+    /// One line per unusable file
+    pub problems: Vec<String>,
+}
 
-    pub mod v2 {
-        pub const CONTENT_OPF: &str = include_str!("../assets/templates/v2/content.opf");
+impl Error for AssetPackError {}
 
-        pub const NAV_XHTML: &str = include_str!("../assets/templates/v2/nav.xhtml");
+impl fmt::Display for AssetPackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "The asset pack in {} is invalid:",
+            paths::asset_pack_folder().display()
+        )?;
+        for problem in &self.problems {
+            writeln!(f, "    {}", problem)?;
+        }
+        Ok(())
     }
+}
+
+/// Loads and checks the asset pack in the asset pack folder, if there is one.
+///
+/// Call once at startup; until then, and if it fails, the built-in assets are used.
+pub fn load_asset_pack() -> Result<(), AssetPackError> {
+    let pack = read_asset_pack(&paths::asset_pack_folder())?;
+    let _ = ASSET_PACK.set(pack);
+    Ok(())
+}
+
+fn read_asset_pack(folder: &Path) -> Result<AssetPack, AssetPackError> {
+    let mut overrides: Vec<Option<&'static [u8]>> = vec![None; Asset::ALL.len()];
+    let mut problems: Vec<String> = Vec::new();
+
+    if !folder.is_dir() {
+        return Ok(AssetPack { overrides });
+    }
+
+    for file in files_in(folder) {
+        let file_name = file
+            .strip_prefix(folder)
+            .unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let position = match Asset::ALL
+            .iter()
+            .position(|asset| asset.file_name() == file_name)
+        {
+            Some(position) => position,
+            None => {
+                problems.push(format!("{}: not the name of any asset", file_name));
+                continue;
+            }
+        };
 
-    pub mod v3 {
-        pub const CONTENT_OPF: &str = include_str!("../assets/templates/v2/content.opf");
+        let content = match fs::read(&file) {
+            Ok(content) => content,
+            Err(error) => {
+                problems.push(format!("{}: could not be read: {}", file_name, error));
+                continue;
+            }
+        };
+
+        match Asset::ALL[position].check(&content) {
+            // Loaded once for the whole run, so leaking it is what keeps it `'static`
+            Ok(()) => overrides[position] = Some(Box::leak(content.into_boxed_slice())),
+            Err(problem) => problems.push(format!("{}: {}", file_name, problem)),
+        }
+    }
 
-        pub const NAV_XHTML: &str = include_str!("../assets/templates/v2/nav.xhtml");
+    if problems.is_empty() {
+        Ok(AssetPack { overrides })
+    } else {
+        Err(AssetPackError { problems })
     }
 }
 
-pub mod image_paths {
-    pub const END_OF_CHAPTER: &str = "assets\\endofthischapter.png";
+fn files_in(folder: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
 
-    pub const END_OF_VOLUME: &str = "assets\\endofthisvolume.png";
+    for entry in fs::read_dir(folder).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(files_in(&path));
+        } else {
+            files.push(path);
+        }
+    }
 
-    pub const VOLUME_COVER_NOT_FOUND: &str = "assets\\volcovernotfound.png";
+    files
 }
 
-pub mod que {
-    pub const QUE_DB: &str = "assets\\que\\que_db.json";
+/// The content of `asset`, from the asset pack if it overrides it
+pub fn bytes(asset: Asset) -> &'static [u8] {
+    let position = Asset::ALL.iter().position(|a| *a == asset).unwrap();
+
+    ASSET_PACK
+        .get()
+        .and_then(|pack| pack.overrides[position])
+        .unwrap_or_else(|| asset.built_in())
+}
+
+/// The content of a template asset as text
+pub fn text(asset: Asset) -> &'static str {
+    // Built-in templates are UTF-8 and overrides were checked when loaded
+    std::str::from_utf8(bytes(asset)).unwrap()
+}
 
-    pub const QUE_FOLDER: &str = "assets\\que";
+/// Writes an image asset to `workspace`, for the steps that need it as a file
+pub fn write_to(asset: Asset, workspace: &Workspace) -> PathBuf {
+    let path = workspace.file(asset.file_name().replace('/', "-"));
+    fs::write(&path, bytes(asset)).unwrap();
+    fs::canonicalize(path).unwrap()
 }
 
-pub const KINDLEGEN_PATH: &str = "assets\\kindlegen.exe";
+#[test]
+fn asset_pack_overrides_and_rejects() {
+    let folder = std::env::temp_dir().join(format!("asset-pack-{}", std::process::id()));
+    fs::create_dir_all(folder.join("templates")).unwrap();
+
+    fs::write(
+        folder.join("templates").join("page.html"),
+        "<p>{{ image }}</p>",
+    )
+    .unwrap();
+    let pack = read_asset_pack(&folder).unwrap();
+    let page = Asset::ALL
+        .iter()
+        .position(|a| *a == Asset::PageHtml)
+        .unwrap();
+    assert_eq!(pack.overrides[page], Some(b"<p>{{ image }}</p>".as_ref()));
+    assert!(pack.overrides.iter().filter(|o| o.is_some()).count() == 1);
+
+    fs::write(folder.join("templates").join("cover.html"), "{{#if}}").unwrap();
+    fs::write(folder.join("endofthisvolume.png"), "not a png").unwrap();
+    fs::write(folder.join("page.htm"), "").unwrap();
+    let error = read_asset_pack(&folder).err().unwrap();
+
+    fs::remove_dir_all(&folder).unwrap();
+
+    let mut problems = error.problems.clone();
+    problems.sort();
+    assert_eq!(problems.len(), 3, "{}", error);
+    assert!(problems[0].starts_with("endofthisvolume.png: not a readable image"));
+    assert!(problems[1].starts_with("page.htm: not the name of any asset"));
+    assert!(problems[2].starts_with("templates/cover.html: not a valid handlebars template"));
+}
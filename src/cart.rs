@@ -1,20 +1,22 @@
-use std::{fs, path::Path};
+use std::fs;
+
+use crate::paths;
 
 // ─── Private ─────────────────────────────────────────────────────────────────
 
 fn create_cart() {
-    fs::File::create("temp\\cart.txt").unwrap();
+    fs::File::create(paths::cart_file()).unwrap();
 }
 
 fn does_cart_exist() -> bool {
-    Path::new("temp\\cart.txt").exists()
+    paths::cart_file().exists()
 }
 
 // ─── Public ──────────────────────────────────────────────────────────────────
 
 pub fn delete_cart() {
     if does_cart_exist() {
-        fs::remove_file("temp\\cart.txt").unwrap();
+        fs::remove_file(paths::cart_file()).unwrap();
     }
 }
 
@@ -27,7 +29,7 @@ pub fn add_to_cart(item: &str) {
 
     lines.push(item.to_owned());
 
-    fs::write("temp\\cart.txt", lines.join("\n")).unwrap();
+    fs::write(paths::cart_file(), lines.join("\n")).unwrap();
 }
 
 pub fn remove_from_cart(item: &String) {
@@ -36,7 +38,7 @@ pub fn remove_from_cart(item: &String) {
     let index = lines.iter().position(|x| x.eq(item)).unwrap();
     lines.remove(index);
 
-    fs::write("temp\\cart.txt", lines.join("\n")).unwrap();
+    fs::write(paths::cart_file(), lines.join("\n")).unwrap();
 }
 
 pub fn get_cart() -> Vec<String> {
@@ -44,7 +46,7 @@ pub fn get_cart() -> Vec<String> {
         create_cart();
     }
 
-    let vector: Vec<String> = fs::read_to_string("temp\\cart.txt")
+    let vector: Vec<String> = fs::read_to_string(paths::cart_file())
         .unwrap()
         .lines()
         .map(|s| s.to_string())
@@ -0,0 +1,356 @@
+//! Settings that apply to every book, read from `config.toml` in the config
+//! folder at startup and changed from the Settings tab.
+//!
+//! Settings the file leaves out keep their defaults, so no file at all builds
+//! books as earlier versions did. Settings that are invalid are reported and
+//! replaced by their defaults until they are fixed.
+
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, read_to_string},
+    sync::RwLock,
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::manga::{
+    naming, BuildSettings, ConverterSettings, DeviceProfile, Enhancement, LongStrip, MangaSeries,
+    OutputFormat, SourceQuality,
+};
+use crate::paths;
+use crate::series_settings::SeriesSettings;
+
+/// Narrowest and widest pages can be resized to, in pixels
+const RESIZE_WIDTHS: (u32, u32) = (200, 5000);
+
+/// Most rows of a long strip repeated on the next page, well under the height
+/// of any profile's screen
+const MAX_LONG_STRIP_OVERLAP: u32 = 300;
+
+// ─── Errors ──────────────────────────────────────────────────────────────────
+
+/// Returned when settings cannot be used
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// One line per invalid setting
+    pub problems: Vec<String>,
+}
+
+impl Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "The settings in {} are invalid:",
+            paths::config_file().display()
+        )?;
+        for problem in &self.problems {
+            writeln!(f, "    {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+// ─── Serde Structs ───────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    /// Label of the Kindle's disk, or name of the folder it is mounted in
+    pub device_name: String,
+    /// Name of the device profile books are built for, see [`DeviceProfile::all`]
+    pub profile: String,
+    /// Width in pixels pages are resized to, instead of the profile's
+    pub resize_width: Option<u32>,
+    /// Enhancement for series that do not set their own, instead of the profile's
+    pub enhancement: Option<Enhancement>,
+    /// Rows repeated at the top of the next page when a long strip is cut
+    /// through artwork
+    pub long_strip_overlap: u32,
+    /// MangaDex code of the language chapters are translated to, e.g. `pt-br`
+    pub language: String,
+    pub source_quality: SourceQuality,
+    pub output_format: OutputFormat,
+    /// Template the file names of books are made from, see [`naming`]
+    pub file_name_template: String,
+    /// Largest book made, in megabytes
+    pub max_book_megabytes: Option<u64>,
+    pub converter: ConverterSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            device_name: String::from("Kindle"),
+            profile: DeviceProfile::default().name,
+            resize_width: None,
+            enhancement: None,
+            long_strip_overlap: LongStrip::default().overlap,
+            language: String::from("en"),
+            source_quality: SourceQuality::default(),
+            output_format: OutputFormat::default(),
+            file_name_template: String::from(naming::DEFAULT_TEMPLATE),
+            max_book_megabytes: None,
+            converter: ConverterSettings::default(),
+        }
+    }
+}
+
+static CONFIG: Lazy<RwLock<Config>> = Lazy::new(|| RwLock::new(Config::default()));
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// The settings in use, the defaults until [`load`] is called
+pub fn get() -> Config {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Reads and checks the settings file, if there is one.
+///
+/// Call once at startup. Invalid settings are replaced by their defaults, and
+/// the rest are used even when an error is returned.
+pub fn load() -> Result<(), ConfigError> {
+    let path = paths::config_file();
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let parsed = read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|serialized| {
+            toml::from_str::<Config>(&serialized).map_err(|error| error.to_string())
+        });
+
+    let (config, problems) = match parsed {
+        Ok(config) => config.validated(),
+        Err(error) => (Config::default(), vec![error]),
+    };
+
+    *CONFIG.write().unwrap() = config;
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError { problems })
+    }
+}
+
+/// Writes `config` to the settings file and uses it from now on, unless any
+/// of it is invalid
+pub fn save(config: Config) -> Result<(), ConfigError> {
+    let (config, problems) = config.validated();
+    if !problems.is_empty() {
+        return Err(ConfigError { problems });
+    }
+
+    let serialized = toml::to_string_pretty(&config).unwrap();
+
+    fs::write(paths::config_file(), serialized).map_err(|error| ConfigError {
+        problems: vec![error.to_string()],
+    })?;
+
+    *CONFIG.write().unwrap() = config;
+
+    Ok(())
+}
+
+impl Config {
+    /// The config with invalid settings replaced by their defaults, and what
+    /// was wrong with each
+    pub fn validated(mut self) -> (Config, Vec<String>) {
+        let defaults = Config::default();
+        let mut problems = Vec::new();
+
+        if self.device_name.trim().is_empty() {
+            problems.push(String::from("device_name: must not be empty"));
+            self.device_name = defaults.device_name;
+        }
+
+        if DeviceProfile::by_name(&self.profile).is_none() {
+            let names: Vec<String> = DeviceProfile::all()
+                .into_iter()
+                .map(|profile| profile.name)
+                .collect();
+            problems.push(format!(
+                "profile: {} is not one of {}",
+                self.profile,
+                names.join(", ")
+            ));
+            self.profile = defaults.profile;
+        }
+
+        let (narrowest, widest) = RESIZE_WIDTHS;
+        if let Some(width) = self
+            .resize_width
+            .filter(|width| !(narrowest..=widest).contains(width))
+        {
+            problems.push(format!(
+                "resize_width: {} is not between {} and {}",
+                width, narrowest, widest
+            ));
+            self.resize_width = defaults.resize_width;
+        }
+
+        if let Some(enhancement) = self.enhancement.as_mut() {
+            let default_enhancement = Enhancement::default();
+            if !(0.0..50.0).contains(&enhancement.clip_percent) {
+                problems.push(format!(
+                    "enhancement.clip_percent: {} is not at least 0 and under 50",
+                    enhancement.clip_percent
+                ));
+                enhancement.clip_percent = default_enhancement.clip_percent;
+            }
+            if !(0.0..=10.0).contains(&enhancement.sharpen_sigma) {
+                problems.push(format!(
+                    "enhancement.sharpen_sigma: {} is not between 0 and 10",
+                    enhancement.sharpen_sigma
+                ));
+                enhancement.sharpen_sigma = default_enhancement.sharpen_sigma;
+            }
+            if !(0..=255).contains(&enhancement.sharpen_threshold) {
+                problems.push(format!(
+                    "enhancement.sharpen_threshold: {} is not between 0 and 255",
+                    enhancement.sharpen_threshold
+                ));
+                enhancement.sharpen_threshold = default_enhancement.sharpen_threshold;
+            }
+        }
+
+        if self.long_strip_overlap > MAX_LONG_STRIP_OVERLAP {
+            problems.push(format!(
+                "long_strip_overlap: {} is more than {}",
+                self.long_strip_overlap, MAX_LONG_STRIP_OVERLAP
+            ));
+            self.long_strip_overlap = defaults.long_strip_overlap;
+        }
+
+        // MangaDex codes are ISO 639 ones, with a region for some, like `es-la`
+        let language_code = Regex::new(r"^[a-z]{2,3}(-[a-z]{2,4})?$").unwrap();
+        if !language_code.is_match(&self.language) {
+            problems.push(format!(
+                "language: {:?} is not a language code like en or pt-br",
+                self.language
+            ));
+            self.language = defaults.language;
+        }
+
+        if let Err(problem) = naming::check_template(&self.file_name_template) {
+            problems.push(format!("file_name_template: {}", problem));
+            self.file_name_template = defaults.file_name_template;
+        }
+
+        if self.max_book_megabytes == Some(0) {
+            problems.push(String::from("max_book_megabytes: must be more than 0"));
+            self.max_book_megabytes = defaults.max_book_megabytes;
+        }
+
+        if self.converter.timeout_secs == 0 {
+            problems.push(String::from("converter.timeout_secs: must be more than 0"));
+            self.converter.timeout_secs = defaults.converter.timeout_secs;
+        }
+
+        if let Some(tool_path) = self
+            .converter
+            .tool_path
+            .as_ref()
+            .filter(|tool_path| !tool_path.is_file())
+        {
+            problems.push(format!(
+                "converter.tool_path: {} is not a file",
+                tool_path.display()
+            ));
+            self.converter.tool_path = defaults.converter.tool_path;
+        }
+
+        (self, problems)
+    }
+
+    /// The profile books are built for, with its width replaced by `resize_width`
+    /// and its enhancement by `enhancement`
+    pub fn device_profile(&self) -> DeviceProfile {
+        let mut profile = DeviceProfile::by_name(&self.profile).unwrap_or_default();
+        if let Some(width) = self.resize_width {
+            profile.width = width;
+        }
+        if let Some(enhancement) = &self.enhancement {
+            profile.enhancement = enhancement.clone();
+        }
+        profile
+    }
+
+    /// Settings for building `manga`, with the series' own tuning taking precedence
+    pub fn build_settings(&self, series: &SeriesSettings, manga: &MangaSeries) -> BuildSettings {
+        let settings = BuildSettings::for_series(self.device_profile(), series, manga);
+
+        BuildSettings {
+            long_strip: LongStrip {
+                overlap: self.long_strip_overlap,
+                ..settings.long_strip.clone()
+            },
+            source_quality: self.source_quality,
+            output_format: self.output_format,
+            converter: self.converter.clone(),
+            file_name_template: self.file_name_template.clone(),
+            max_book_bytes: self
+                .max_book_megabytes
+                .map(|megabytes| megabytes * 1024 * 1024),
+            ..settings
+        }
+    }
+}
+
+#[test]
+fn missing_settings_default_and_invalid_ones_are_replaced() {
+    let config: Config = toml::from_str("language = \"pt-br\"").unwrap();
+    assert_eq!(config.language, "pt-br");
+    assert_eq!(config.device_name, "Kindle");
+    assert!(config.clone().validated().1.is_empty());
+
+    let saved = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(toml::from_str::<Config>(&saved).unwrap(), config);
+
+    let (config, problems) = Config {
+        device_name: String::new(),
+        profile: String::from("Nook"),
+        resize_width: Some(20),
+        language: String::from("English"),
+        file_name_template: String::from("{title}"),
+        max_book_megabytes: Some(0),
+        long_strip_overlap: 1000,
+        ..Config::default()
+    }
+    .validated();
+    assert_eq!(problems.len(), 7);
+    assert_eq!(config, Config::default());
+
+    let profile = Config {
+        profile: String::from("Kindle Paperwhite"),
+        resize_width: Some(1000),
+        ..Config::default()
+    }
+    .device_profile();
+    assert_eq!((profile.width, profile.height), (1000, 1648));
+
+    let (config, problems) = Config {
+        enhancement: Some(Enhancement {
+            clip_percent: 60.0,
+            sharpen_threshold: 2,
+            ..Enhancement::disabled()
+        }),
+        ..Config::default()
+    }
+    .validated();
+    assert_eq!(problems.len(), 1);
+    let saved = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(toml::from_str::<Config>(&saved).unwrap(), config);
+    let enhancement = config.device_profile().enhancement;
+    assert!(!enhancement.enabled);
+    assert_eq!(
+        enhancement.clip_percent,
+        Enhancement::default().clip_percent
+    );
+}
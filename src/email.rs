@@ -0,0 +1,306 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, read_to_string},
+    path::Path,
+    time::Duration,
+};
+
+use lettre::{
+    message::{header::ContentType, Attachment, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::paths;
+
+/// Largest email Send to Kindle takes, attachments included
+pub const SEND_TO_KINDLE_LIMIT: u64 = 50 * 1024 * 1024;
+
+/// How long the SMTP server gets to answer each command
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// ─── Errors ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub enum EmailError {
+    /// Send to Kindle only converts documents, it rejects AZW3, MOBI and CBZ
+    UnsupportedFormat { file_name: String },
+    /// The email would be larger than the service takes
+    TooLarge {
+        file_name: String,
+        size: u64,
+        limit: u64,
+    },
+    /// The sender or Kindle address cannot be parsed
+    Address(String),
+    /// The book could not be read from the queue
+    Read(std::io::Error),
+    /// The message could not be put together
+    Message(lettre::error::Error),
+    /// Connecting, logging in or sending failed
+    Smtp(lettre::transport::smtp::Error),
+}
+
+impl Error for EmailError {}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmailError::UnsupportedFormat { file_name } => write!(
+                f,
+                "Send to Kindle does not take {}, build the series as EPUB or PDF to email it",
+                file_name
+            ),
+            EmailError::TooLarge {
+                file_name,
+                size,
+                limit,
+            } => write!(
+                f,
+                "{} is {} MB once attached, over the {} MB Send to Kindle takes",
+                file_name,
+                size / 1024 / 1024,
+                limit / 1024 / 1024
+            ),
+            EmailError::Address(address) => write!(f, "{} is not an email address", address),
+            EmailError::Read(error) => write!(f, "The book could not be read: {}", error),
+            EmailError::Message(error) => write!(f, "The email could not be written: {}", error),
+            EmailError::Smtp(error) => write!(f, "The email could not be sent: {}", error),
+        }
+    }
+}
+
+// ─── Serde Structs ───────────────────────────────────────────────────────────
+
+/// How the connection to the SMTP server is secured
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually on port 465
+    #[default]
+    Tls,
+    /// Upgraded to TLS after connecting, usually on port 587
+    StartTls,
+    /// Unencrypted and usually without login, for a local SMTP stand-in
+    None,
+}
+
+fn default_limit() -> u64 {
+    SEND_TO_KINDLE_LIMIT
+}
+
+/// Where books are emailed to when the Kindle is not plugged in
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailSettings {
+    pub smtp_host: String,
+    /// Port of the server, the usual one for `security` if not set
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sender, which must be on the Kindle's approved email list
+    pub from: String,
+    /// The Kindle's `@kindle.com` address
+    pub to: String,
+    /// Largest email sent, attachment included
+    #[serde(default = "default_limit")]
+    pub max_email_bytes: u64,
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// The email settings, or `None` if emailing books is not set up
+pub fn settings() -> Option<EmailSettings> {
+    let path = paths::email_settings();
+    if !path.exists() {
+        return None;
+    }
+
+    let serialized = read_to_string(&path).unwrap();
+
+    serde_json::from_str(&serialized)
+        .map_err(|error| log::warn!("ignoring unreadable {}: {}", path.display(), error))
+        .ok()
+}
+
+/// Content type Send to Kindle needs to convert a book, or `None` if it does
+/// not take books of that kind
+fn content_type(book_path: &Path) -> Option<ContentType> {
+    let content_type = match book_path.extension()?.to_str()?.to_lowercase().as_str() {
+        "epub" => "application/epub+zip",
+        "pdf" => "application/pdf",
+        _ => return None,
+    };
+    ContentType::parse(content_type).ok()
+}
+
+/// Whether Send to Kindle takes the book at `book_path`, which it only does
+/// for EPUB and PDF
+pub fn can_send(book_path: &Path) -> bool {
+    content_type(book_path).is_some()
+}
+
+/// Emails the book at `book_path` to the Kindle address of `settings`
+pub fn send(settings: &EmailSettings, book_path: &Path, subject: &str) -> Result<(), EmailError> {
+    let file_name = book_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let content_type = content_type(book_path).ok_or_else(|| EmailError::UnsupportedFormat {
+        file_name: file_name.clone(),
+    })?;
+
+    // Attachments are base64 encoded, which makes them a third larger
+    let size = fs::metadata(book_path)
+        .map_err(EmailError::Read)?
+        .len()
+        .div_ceil(3)
+        * 4;
+    if size > settings.max_email_bytes {
+        return Err(EmailError::TooLarge {
+            file_name,
+            size,
+            limit: settings.max_email_bytes,
+        });
+    }
+
+    let book = fs::read(book_path).map_err(EmailError::Read)?;
+
+    let email = Message::builder()
+        .from(
+            settings
+                .from
+                .parse()
+                .map_err(|_| EmailError::Address(settings.from.clone()))?,
+        )
+        .to(settings
+            .to
+            .parse()
+            .map_err(|_| EmailError::Address(settings.to.clone()))?)
+        .subject(subject)
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(String::from("Sent by KindleMangaReader")))
+                .singlepart(Attachment::new(file_name).body(book, content_type)),
+        )
+        .map_err(EmailError::Message)?;
+
+    let builder = match settings.security {
+        SmtpSecurity::Tls => SmtpTransport::relay(&settings.smtp_host).map_err(EmailError::Smtp)?,
+        SmtpSecurity::StartTls => {
+            SmtpTransport::starttls_relay(&settings.smtp_host).map_err(EmailError::Smtp)?
+        }
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&settings.smtp_host),
+    };
+    let mut builder = builder.timeout(Some(SMTP_TIMEOUT));
+    if let Some(port) = settings.smtp_port {
+        builder = builder.port(port);
+    }
+    if let Some(username) = &settings.username {
+        builder = builder.credentials(Credentials::new(
+            username.clone(),
+            settings.password.clone().unwrap_or_default(),
+        ));
+    }
+
+    builder
+        .build()
+        .send(&email)
+        .map(|_| ())
+        .map_err(EmailError::Smtp)
+}
+
+#[test]
+fn books_are_emailed_through_the_configured_server() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    // A stand-in SMTP server that accepts one message and hands it over
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut received = String::new();
+
+        writer.write_all(b"220 stand-in ready\r\n").unwrap();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            received.push_str(&line);
+            let reply: &[u8] = if in_data {
+                if line == ".\r\n" {
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else {
+                    continue;
+                }
+            } else if line.starts_with("EHLO") {
+                b"250 stand-in\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                writer.write_all(b"221 bye\r\n").unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            writer.write_all(reply).unwrap();
+        }
+        received
+    });
+
+    let folder = std::env::temp_dir().join(format!("email-{}", std::process::id()));
+    fs::create_dir_all(&folder).unwrap();
+    let book = folder.join("Spy x Family volume 2.epub");
+    fs::write(&book, b"PK epub").unwrap();
+
+    let settings = EmailSettings {
+        smtp_host: String::from("127.0.0.1"),
+        smtp_port: Some(port),
+        security: SmtpSecurity::None,
+        username: None,
+        password: None,
+        from: String::from("reader@example.com"),
+        to: String::from("someone@kindle.com"),
+        max_email_bytes: SEND_TO_KINDLE_LIMIT,
+    };
+    send(&settings, &book, "Spy x Family volume 2").unwrap();
+
+    let received = server.join().unwrap();
+    assert!(received.contains("RCPT TO:<someone@kindle.com>"));
+    assert!(received.contains("Subject: Spy x Family volume 2"));
+    assert!(received.contains("Content-Type: application/epub+zip"));
+    assert!(received.contains("Spy x Family volume 2.epub"));
+
+    // Books the service would reject are not sent at all
+    let azw3 = folder.join("Spy x Family volume 2.azw3");
+    fs::write(&azw3, b"BOOKMOBI").unwrap();
+    assert!(matches!(
+        send(&settings, &azw3, "Spy x Family volume 2"),
+        Err(EmailError::UnsupportedFormat { .. })
+    ));
+    let small_limit = EmailSettings {
+        max_email_bytes: 4,
+        ..settings
+    };
+    assert!(matches!(
+        send(&small_limit, &book, "Spy x Family volume 2"),
+        Err(EmailError::TooLarge { .. })
+    ));
+
+    fs::remove_dir_all(&folder).unwrap();
+}
@@ -1,8 +1,9 @@
 use std::{
     error::Error,
+    ffi::OsStr,
     fmt,
     fs::{self, read_to_string},
-    io::Write,
+    io::{self, Write},
     path::Path,
     path::PathBuf,
 };
@@ -11,18 +12,39 @@ use serde::{Deserialize, Serialize};
 use serde_json;
 use sysinfo::{DiskExt, System, SystemExt};
 
-use crate::manga::Outputfile;
+use crate::config;
+use crate::manga::{naming, BuildSettings, Outputfile, Part};
+use crate::manifest;
 use crate::que;
 // ─── Errors ──────────────────────────────────────────────────────────────────
 
+/// Why a book could not be put on, or read from, the Kindle
 #[derive(Debug)]
-pub struct KindleNotFoundError;
+pub enum KindleError {
+    /// No Kindle is plugged in
+    NotFound,
+    /// The book is larger than the space left on the Kindle
+    NoSpace,
+    /// The Kindle or the queue could not be read or written, e.g. because the
+    /// Kindle was unplugged or filled up
+    Io(io::Error),
+}
 
-impl Error for KindleNotFoundError {}
+impl Error for KindleError {}
 
-impl fmt::Display for KindleNotFoundError {
+impl fmt::Display for KindleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Oh no, something bad went down")
+        match self {
+            KindleError::NotFound => write!(f, "the Kindle is not plugged in"),
+            KindleError::NoSpace => write!(f, "there is no space left on the Kindle"),
+            KindleError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for KindleError {
+    fn from(error: io::Error) -> Self {
+        KindleError::Io(error)
     }
 }
 
@@ -34,8 +56,15 @@ pub struct OnDeviceFile {
     pub manga_title: String,
     pub volume_title: String,
     pub chapter_title: Option<String>,
+    #[serde(default)]
+    pub part: Option<Part>,
     pub file_name: String,
     pub file_size: u64,
+    #[serde(default)]
+    pub build_settings: Option<BuildSettings>,
+    /// SHA-256 of the book, none for books copied before builds were recorded
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 impl OnDeviceFile {
@@ -45,8 +74,11 @@ impl OnDeviceFile {
             manga_title: String::new(),
             volume_title: String::new(),
             chapter_title: Some(String::new()),
+            part: None,
             file_name: String::new(),
             file_size: 0,
+            build_settings: None,
+            sha256: None,
         }
     }
 }
@@ -87,25 +119,54 @@ impl Default for Mount {
 
 // Private
 impl Mount {
-    fn create_kmr2_file(&self) {
+    fn create_kmr2_file(&self) -> io::Result<()> {
         let serialized = serde_json::to_string(&OnDeviceFiles::new()).unwrap();
 
         let kmr_data_file_path = Path::new(&self.mount_point).join("kmr2.json");
 
-        let mut kmr_data_file = fs::File::create(&kmr_data_file_path).unwrap();
+        let mut kmr_data_file = fs::File::create(&kmr_data_file_path)?;
 
-        kmr_data_file.write_all(serialized.as_bytes()).unwrap();
+        kmr_data_file.write_all(serialized.as_bytes())
     }
 
-    fn add_to_kmr2_file(&self, output_file: &Outputfile) {
+    fn read_kmr2_file(&self) -> io::Result<OnDeviceFiles> {
         let kmr_data_file_path = Path::new(&self.mount_point).join("kmr2.json");
 
-        let serialized = read_to_string(&kmr_data_file_path).unwrap();
+        let serialized = read_to_string(kmr_data_file_path)?;
 
-        let mut data: OnDeviceFiles = serde_json::from_str(&serialized).unwrap();
+        Ok(serde_json::from_str(&serialized)?)
+    }
+
+    /// Name `output_file` gets in the documents folder: its own, unless another
+    /// book or a file the user put there already has it
+    fn device_file_name(&self, output_file: &Outputfile) -> io::Result<String> {
+        let data = self.read_kmr2_file()?;
+        let documents_path = Path::new(&self.mount_point).join("documents");
+
+        Ok(naming::unique(
+            output_file.path.file_name().unwrap().to_str().unwrap(),
+            |name| match data.files.iter().find(|x| x.file_name == name) {
+                Some(on_device) => {
+                    on_device.r#type != output_file.content_type
+                        || on_device.manga_title != output_file.manga_title
+                        || on_device.volume_title != output_file.volume_title
+                        || on_device.chapter_title != output_file.chapter_title
+                        || on_device.part != output_file.part
+                }
+                None => documents_path.join(name).exists(),
+            },
+        ))
+    }
+
+    /// Records `output_file` as on the device under `file_name`, replacing the
+    /// entry of an earlier copy
+    fn add_to_kmr2_file(&self, output_file: &Outputfile, file_name: &str) -> io::Result<()> {
+        let kmr_data_file_path = Path::new(&self.mount_point).join("kmr2.json");
+
+        let mut data = self.read_kmr2_file()?;
 
         // Remove empty OnDeviceFile
-        let empty_data_index = data.files.iter().position(|x| x.r#type.eq(""));
+        let empty_data_index = data.files.iter().position(|x| x.r#type.is_empty());
         if let Some(index) = empty_data_index {
             data.files.remove(index);
         }
@@ -115,28 +176,19 @@ impl Mount {
             manga_title: output_file.manga_title.to_owned(),
             volume_title: output_file.volume_title.to_owned(),
             chapter_title: output_file.chapter_title.to_owned(),
-            file_name: output_file
-                .path
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string(),
+            part: output_file.part,
+            file_name: file_name.to_string(),
             file_size: output_file.size,
+            build_settings: output_file.build_settings.clone(),
+            sha256: manifest::file_sha256(&output_file.path).ok(),
         };
 
-        // check if the data is already on the kindle, and if there are no duplicates, add the file data
-        let duplicate_index = data
-            .files
-            .iter()
-            .position(|x| x.file_name.eq(&file_data.file_name));
-        if duplicate_index.is_none() {
-            data.files.push(file_data);
+        data.files.retain(|x| x.file_name != file_data.file_name);
+        data.files.push(file_data);
 
-            let serialized = serde_json::to_string(&data).unwrap();
+        let serialized = serde_json::to_string(&data).unwrap();
 
-            fs::write(kmr_data_file_path, serialized).unwrap();
-        }
+        fs::write(kmr_data_file_path, serialized)
     }
 
     fn remove_from_kmr2_file(&self, file: &OnDeviceFile) {
@@ -183,9 +235,14 @@ impl Mount {
 
     pub fn scan(&mut self) -> bool {
         self.sys.refresh_disks_list();
+        let device_name = config::get().device_name;
 
         for disk in self.sys.disks() {
-            if disk.name() == "Kindle" {
+            // Windows and macOS name the disk after its label, Linux mounts it
+            // in a folder of that name
+            if disk.name() == OsStr::new(&device_name)
+                || disk.mount_point().file_name() == Some(OsStr::new(&device_name))
+            {
                 self.mount_point = disk.mount_point().to_owned();
                 self.available_space = disk.available_space();
                 self.is_connected = true;
@@ -200,48 +257,44 @@ impl Mount {
         self.is_connected
     }
 
-    // , output_file: Outputfile
-    pub fn send_to_kindle(&self, output_file: &Outputfile) -> Result<(), KindleNotFoundError> {
+    /// Copies `output_file` to the Kindle, or queues it if the Kindle is too
+    /// full for it
+    pub fn send_to_kindle(&self, output_file: &Outputfile) -> Result<(), KindleError> {
         if !self.is_connected {
-            return Err(KindleNotFoundError);
+            return Err(KindleError::NotFound);
         }
 
         if !self.does_kmr2_exist() {
-            self.create_kmr2_file();
+            self.create_kmr2_file()?;
         }
 
         // check if there is available space on the kindle
         if (self.available_space + 100) > output_file.size {
-            self.add_to_kmr2_file(output_file);
+            let file_name = self.device_file_name(output_file)?;
+            self.add_to_kmr2_file(output_file, &file_name)?;
 
             let documents_path = Path::new(&self.mount_point)
                 .join("documents")
-                .join(output_file.path.file_name().unwrap());
+                .join(file_name);
 
-            fs::copy(&output_file.path, documents_path).unwrap();
+            fs::copy(&output_file.path, documents_path)?;
         } else {
-            que::add(output_file);
+            que::add(output_file)?;
         }
 
         Ok(())
     }
 
-    pub fn on_device_manga(&self) -> Result<Vec<OnDeviceFile>, KindleNotFoundError> {
+    pub fn on_device_manga(&self) -> Result<Vec<OnDeviceFile>, KindleError> {
         if !self.is_connected {
-            return Err(KindleNotFoundError);
+            return Err(KindleError::NotFound);
         }
 
         if !self.does_kmr2_exist() {
-            self.create_kmr2_file();
+            self.create_kmr2_file()?;
         }
 
-        let kmr_data_file_path = Path::new(&self.mount_point).join("kmr2.json");
-
-        let serialized = read_to_string(&kmr_data_file_path).unwrap();
-
-        let data: OnDeviceFiles = serde_json::from_str(&serialized).unwrap();
-
-        Ok(data.files)
+        Ok(self.read_kmr2_file()?.files)
     }
 
     pub fn remove_manga(&self, file: &OnDeviceFile) {
@@ -1,6 +1,12 @@
 pub mod ascrii_art;
 pub mod assets;
 pub mod cart;
+pub mod config;
+pub mod email;
 pub mod kindle;
 pub mod manga;
+pub mod manifest;
+pub mod paths;
 pub mod que;
+pub mod series_settings;
+pub mod workspace;
@@ -2,7 +2,10 @@
 
 use kindle_manga_reader_v2::kindle::OnDeviceFile;
 use kindle_manga_reader_v2::que::QueFile;
-use kindle_manga_reader_v2::{ascrii_art, cart, kindle, manga, que};
+use kindle_manga_reader_v2::workspace::{self, Workspace};
+use kindle_manga_reader_v2::{
+    ascrii_art, assets, cart, config, email, kindle, manga, manifest, paths, que, series_settings,
+};
 
 // ─── Ui Stuff ────────────────────────────────────────────────────────────────
 
@@ -26,6 +29,19 @@ use ansi_term::Colour;
 // ─────────────────────────────────────────────────────────────────────────────
 
 fn main() {
+    // Reclaim the job folders of runs that crashed or were killed mid-build
+    workspace::sweep();
+
+    // Overrides are checked before anything is built so a broken template is
+    // reported now rather than halfway through a download
+    if let Err(error) = assets::load_asset_pack() {
+        eprint!("{}", error);
+        std::process::exit(1);
+    }
+
+    // Invalid settings fall back to their defaults, so they are only reported
+    let config_loaded = config::load();
+
     let mut siv = cursive::default();
 
     // ─── Theme ───────────────────────────────────────────────────────────
@@ -71,6 +87,12 @@ fn main() {
         });
     });
 
+    siv.set_global_callback('s', |siv: &mut Cursive| {
+        siv.call_on_name("content_panel", |view: &mut TabPanel| {
+            view.set_active_tab("Settings").unwrap();
+        });
+    });
+
     siv.set_global_callback('a', |siv: &mut Cursive| {
         siv.pop_layer();
         display_get_manga_id(siv);
@@ -80,6 +102,10 @@ fn main() {
 
     display_get_manga_id(&mut siv);
 
+    if let Err(error) = config_loaded {
+        siv.add_layer(Dialog::info(error.to_string()).title("Invalid Settings"));
+    }
+
     // ─── Run Cursive ─────────────────────────────────────────────────────
 
     siv.run();
@@ -289,6 +315,317 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
         });
     }
 
+    /// Builds what is in the cart and delivers it, with the cart's chapters in
+    /// one book if `bundle_chapters`
+    fn checkout(siv: &mut Cursive, bundle_chapters: bool) {
+        /// Builds an item of the cart into its books
+        type ToMobi<'a> =
+            &'a dyn Fn(&Workspace) -> Result<Vec<manga::Outputfile>, manga::BuildError>;
+
+        siv.pop_layer();
+
+        siv.call_on_name("main_horizontal_stackview", |view: &mut StackView| {
+            view.move_to_front(LayerPosition::FromBack(0));
+        });
+
+        let mut volumes_to_get = vec![];
+        let mut chapters_to_get = vec![];
+
+        let cart = cart::get_cart();
+        for volume in siv
+            .user_data::<manga::MangaSeries>()
+            .unwrap()
+            .clone()
+            .volumes
+        {
+            for item in cart.iter() {
+                if item.contains('v') {
+                    if volume.title.eq(&item.replace('v', "")) {
+                        volumes_to_get.push(volume.clone());
+                    }
+                } else {
+                    let split_text: Vec<&str> = item.split('-').collect();
+
+                    if volume.title.eq(split_text[0]) {
+                        for chapter in volume.chapters.iter() {
+                            if chapter.title.eq(split_text[1]) {
+                                chapters_to_get.push(chapter.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Bundled chapters are built as one book instead of one each
+        let bundle_to_get = if bundle_chapters && !chapters_to_get.is_empty() {
+            Some(manga::ChapterBundle::new(std::mem::take(
+                &mut chapters_to_get,
+            )))
+        } else {
+            None
+        };
+
+        // The items are taken out of the cart now, so that anything
+        // added while this job is running is kept for the next one.
+        // Items that are not built are put back afterwards.
+        cart::delete_cart();
+
+        let manga = siv.user_data::<manga::MangaSeries>().unwrap().clone();
+        let build_settings = config::get().build_settings(&series_settings::get(&manga.id), &manga);
+
+        let cb_sink = siv.cb_sink().clone();
+
+        siv.call_on_name("manga_progress_bar", move |view: &mut ProgressBar| {
+            let mut max_counter = 0;
+            for _ in 0..(chapters_to_get.len() + volumes_to_get.len() + bundle_to_get.iter().len())
+            {
+                max_counter += 5;
+            }
+            max_counter += 1;
+            view.set_max(max_counter);
+            view.start(move |counter| {
+                let mut kindle = kindle::Mount::new();
+                kindle.scan();
+
+                // Books for an unplugged Kindle are also emailed to it,
+                // if that is set up
+                let email_settings = email::settings();
+
+                // Each item is built in its own workspace, which is
+                // removed once the output has been delivered, unless it was
+                // built before from the same inputs. Returns whether the item
+                // was delivered; what went wrong is added to the failures.
+                let mut failures: Vec<String> = Vec::new();
+                let mut warnings: Vec<String> = Vec::new();
+                let mut reused: Vec<String> = Vec::new();
+                // Books that were built and queued, but not sent on
+                let mut undelivered: Vec<String> = Vec::new();
+                let mut not_emailed = false;
+                let mut build = |inputs: manifest::BuildInputs,
+                                 title: String,
+                                 to_mobi: ToMobi|
+                 -> bool {
+                    let ticks_before = counter.get();
+                    // The steps the item did not get to, so the bar still fills up
+                    let not_built = |failures: &mut Vec<String>, error: String| {
+                        failures.push(error);
+                        counter.tick(5_usize.saturating_sub(counter.get() - ticks_before));
+                        false
+                    };
+
+                    let on_device = match kindle.is_connected {
+                        true => match kindle.on_device_manga() {
+                            Ok(on_device) => on_device,
+                            Err(error) => {
+                                return not_built(&mut failures, format!("{}: {}", title, error))
+                            }
+                        },
+                        false => Vec::new(),
+                    };
+                    match manifest::existing_build(&inputs, &on_device, &que::data()) {
+                        Some(manifest::ExistingBuild::OnKindle) => {
+                            reused.push(format!("{} is already on the Kindle", title));
+                            counter.tick(5);
+                            return true;
+                        }
+                        Some(manifest::ExistingBuild::Queued(que_files)) => {
+                            if kindle.is_connected {
+                                for que_file in que_files {
+                                    if let Err(error) = que::send_item_to_kindle(&que_file, &kindle)
+                                    {
+                                        undelivered
+                                            .push(format!("{}: {}", que_file.file_name, error));
+                                    }
+                                }
+                                reused.push(format!("{} was sent from the queue", title));
+                            } else {
+                                reused.push(format!("{} is already queued", title));
+                            }
+                            counter.tick(5);
+                            return true;
+                        }
+                        None => {}
+                    }
+
+                    let workspace = match Workspace::new() {
+                        Ok(workspace) => workspace,
+                        Err(error) => {
+                            return not_built(&mut failures, format!("{}: {}", title, error))
+                        }
+                    };
+                    let output_files = match to_mobi(&workspace) {
+                        Ok(output_files) => output_files,
+                        Err(error) => return not_built(&mut failures, error.to_string()),
+                    };
+                    // A book split for size is delivered part by part
+                    let mut delivered = true;
+                    for output_file in output_files {
+                        manifest::record(&inputs, &output_file);
+                        warnings.extend(output_file.warnings.iter().map(|warning| {
+                            format!(
+                                "{}: {}",
+                                output_file
+                                    .path
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy(),
+                                warning
+                            )
+                        }));
+                        let file_name = output_file
+                            .path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        if kindle.is_connected {
+                            if let Err(error) = kindle.send_to_kindle(&output_file) {
+                                failures.push(format!("{}: {}", file_name, error));
+                                delivered = false;
+                            }
+                            continue;
+                        }
+                        let que_file = match que::add(&output_file) {
+                            Ok(que_file) => que_file,
+                            Err(error) => {
+                                failures.push(format!("{} was not queued: {}", file_name, error));
+                                delivered = false;
+                                continue;
+                            }
+                        };
+                        if let Some(settings) = &email_settings {
+                            // Send to Kindle rejects the other formats, which
+                            // wait in the queue for USB instead
+                            if !email::can_send(&output_file.path) {
+                                not_emailed = true;
+                            } else if let Err(error) = que::email_item(&que_file, settings) {
+                                undelivered
+                                    .push(format!("{} was queued: {}", que_file.file_name, error));
+                            }
+                        }
+                    }
+                    delivered
+                };
+
+                // Cart items of what was not built, to be put back
+                let mut not_built: Vec<String> = Vec::new();
+                let chapter_item = |chapter: &manga::MangaChapter| {
+                    format!("{}-{}", chapter.volume_title, chapter.title)
+                };
+
+                let chapter_ids = |chapters: &[manga::MangaChapter]| {
+                    chapters.iter().map(|chapter| chapter.id.clone()).collect()
+                };
+
+                for volume in volumes_to_get {
+                    let built = build(
+                        manifest::BuildInputs::new(
+                            "volume",
+                            &manga.id,
+                            chapter_ids(&volume.chapters),
+                            &build_settings,
+                        ),
+                        format!("Volume {}", volume.title),
+                        &|workspace| volume.to_mobi(&manga, workspace, &build_settings, &counter),
+                    );
+                    if !built {
+                        not_built.push(format!("v{}", volume.title));
+                    }
+                }
+                for chapter in chapters_to_get {
+                    let built = build(
+                        manifest::BuildInputs::new(
+                            "chapter",
+                            &manga.id,
+                            chapter_ids(std::slice::from_ref(&chapter)),
+                            &build_settings,
+                        ),
+                        format!("Volume {} Chapter {}", chapter.volume_title, chapter.title),
+                        &|workspace| chapter.to_mobi(&manga, workspace, &build_settings, &counter),
+                    );
+                    if !built {
+                        not_built.push(chapter_item(&chapter));
+                    }
+                }
+                if let Some(bundle) = bundle_to_get {
+                    let built = build(
+                        manifest::BuildInputs::new(
+                            "bundle",
+                            &manga.id,
+                            chapter_ids(&bundle.chapters),
+                            &build_settings,
+                        ),
+                        bundle.title(),
+                        &|workspace| bundle.to_mobi(&manga, workspace, &build_settings, &counter),
+                    );
+                    if !built {
+                        not_built.extend(bundle.chapters.iter().map(chapter_item));
+                    }
+                }
+
+                let cart = cart::get_cart();
+                for item in not_built.iter().filter(|item| !cart.contains(item)) {
+                    cart::add_to_cart(item);
+                }
+                if !not_built.is_empty() {
+                    failures.push(String::from("What was not built is back in the cart."));
+                }
+
+                counter.tick(1);
+
+                if !warnings.is_empty() {
+                    cb_sink
+                        .send(Box::new(move |siv: &mut Cursive| {
+                            siv.add_layer(
+                                Dialog::info(warnings.join("\n")).title("Converter Warnings"),
+                            );
+                        }))
+                        .unwrap();
+                }
+
+                if !reused.is_empty() {
+                    cb_sink
+                        .send(Box::new(move |siv: &mut Cursive| {
+                            siv.add_layer(Dialog::info(reused.join("\n")).title("Already Built"));
+                        }))
+                        .unwrap();
+                }
+
+                if not_emailed {
+                    undelivered.push(String::from(
+                        "Send to Kindle only takes EPUB and PDF, so books in other formats \
+                         wait in the queue until the Kindle is plugged in.",
+                    ));
+                }
+
+                if !undelivered.is_empty() {
+                    cb_sink
+                        .send(Box::new(move |siv: &mut Cursive| {
+                            update_que_files_select_view_dailog(siv);
+                            siv.add_layer(
+                                Dialog::info(undelivered.join("\n"))
+                                    .title("Some Books Were Not Delivered"),
+                            );
+                        }))
+                        .unwrap();
+                }
+
+                if !failures.is_empty() {
+                    cb_sink
+                        .send(Box::new(move |siv: &mut Cursive| {
+                            update_cart_view(siv);
+                            siv.add_layer(
+                                Dialog::info(failures.join("\n"))
+                                    .title("Some Items Were Not Built"),
+                            );
+                        }))
+                        .unwrap();
+                }
+            });
+        });
+    }
+
     let cart_view = Dialog::around(
         LinearLayout::vertical()
             .child(
@@ -302,90 +639,28 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                 move |siv: &mut Cursive| {
                     let cart = cart::get_cart();
                     let number_of_volumes =
-                        { cart.iter().filter(|item| !item.contains('v')).count() };
-
-                    let number_of_chapters =
                         { cart.iter().filter(|item| item.contains('v')).count() };
 
-                    siv.add_layer(
-                        Dialog::text(format!(
-                            "Send {} volumes and {} chapters to kindle?",
-                            Colour::Cyan.bold().paint(number_of_volumes.to_string()),
-                            Colour::Cyan.bold().paint(number_of_chapters.to_string())
-                        ))
-                        .button("Send to Kindle", |siv: &mut Cursive| {
-                            siv.pop_layer();
-
-                            siv.call_on_name(
-                                "main_horizontal_stackview",
-                                |view: &mut StackView| {
-                                    view.move_to_front(LayerPosition::FromBack(0));
-                                },
-                            );
-
-                            let mut volumes_to_get = vec![];
-                            let mut chapters_to_get = vec![];
-
-                            let cart = cart::get_cart();
-                            for volume in siv
-                                .user_data::<manga::MangaSeries>()
-                                .unwrap()
-                                .clone()
-                                .volumes
-                            {
-                                for item in cart.iter() {
-                                    if item.contains('v') {
-                                        if volume.title.eq(&item.replace('v', "")) {
-                                            volumes_to_get.push(volume.clone());
-                                        }
-                                    } else {
-                                        let split_text: Vec<&str> = item.split('-').collect();
-
-                                        if volume.title.eq(split_text[0]) {
-                                            for chapter in volume.chapters.iter() {
-                                                if chapter.title.eq(split_text[1]) {
-                                                    chapters_to_get.push(chapter.clone());
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            siv.call_on_name("manga_progress_bar", |view: &mut ProgressBar| {
-                                let mut max_counter = 0;
-                                for _ in 0..(chapters_to_get.len() + volumes_to_get.len()) {
-                                    max_counter += 5;
-                                }
-                                max_counter += 1;
-                                view.set_max(max_counter);
-                                view.start(|counter| {
-                                    let mut files_to_send: Vec<manga::Outputfile> = Vec::new();
+                    let number_of_chapters =
+                        { cart.iter().filter(|item| !item.contains('v')).count() };
 
-                                    for volume in volumes_to_get {
-                                        files_to_send.push(volume.to_mobi(&counter));
-                                    }
-                                    for chapter in chapters_to_get {
-                                        files_to_send.push(chapter.to_mobi(&counter));
-                                    }
+                    let mut checkout_dialog = Dialog::text(format!(
+                        "Send {} volumes and {} chapters to kindle?",
+                        Colour::Cyan.bold().paint(number_of_volumes.to_string()),
+                        Colour::Cyan.bold().paint(number_of_chapters.to_string())
+                    ))
+                    .button("Send to Kindle", |siv: &mut Cursive| checkout(siv, false));
 
-                                    let mut kindle = kindle::Mount::new();
-                                    kindle.scan();
-                                    for output_file in files_to_send {
-                                        if kindle.is_connected {
-                                            kindle.send_to_kindle(&output_file).unwrap();
-                                        } else {
-                                            que::add(&output_file);
-                                        }
-                                    }
-                                    cart::delete_cart();
-                                    counter.tick(1);
-                                });
+                    if number_of_chapters > 1 {
+                        checkout_dialog
+                            .add_button("Send Chapters as One Book", |siv: &mut Cursive| {
+                                checkout(siv, true)
                             });
-                        })
-                        .button("Cancel", |siv: &mut Cursive| {
-                            siv.pop_layer();
-                        }),
-                    );
+                    }
+
+                    siv.add_layer(checkout_dialog.button("Cancel", |siv: &mut Cursive| {
+                        siv.pop_layer();
+                    }));
                 },
             ))),
     )
@@ -455,6 +730,141 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
 
     // ─── Manga Data ──────────────────────────────────────────────────────
 
+    fn reading_direction_label(manga: &manga::MangaSeries) -> String {
+        let series = series_settings::get(&manga.id);
+
+        let (direction, source) = match series.reading_direction {
+            Some(direction) => (direction, "set for this series"),
+            None => (
+                manga::ReadingDirection::from_language(&manga.original_language),
+                "from original language",
+            ),
+        };
+
+        format!("Reading Direction: {} ({})", direction.name(), source)
+    }
+
+    // Cycles the series through following its language, right to left and left to right
+    fn toggle_reading_direction(siv: &mut Cursive) {
+        let manga = siv.user_data::<manga::MangaSeries>().unwrap().clone();
+        let mut series = series_settings::get(&manga.id);
+
+        series.reading_direction = match series.reading_direction {
+            None => Some(manga::ReadingDirection::RightToLeft),
+            Some(manga::ReadingDirection::RightToLeft) => {
+                Some(manga::ReadingDirection::LeftToRight)
+            }
+            Some(manga::ReadingDirection::LeftToRight) => None,
+        };
+        series_settings::set(&manga.id, series);
+
+        siv.call_on_name("manga_data_dialog", |view: &mut Dialog| {
+            set_manga_data_buttons(view, &manga);
+        });
+    }
+
+    fn panel_view_label(manga: &manga::MangaSeries) -> String {
+        if series_settings::get(&manga.id).panel_view {
+            String::from("Panel View: On")
+        } else {
+            String::from("Panel View: Off")
+        }
+    }
+
+    fn toggle_panel_view(siv: &mut Cursive) {
+        let manga = siv.user_data::<manga::MangaSeries>().unwrap().clone();
+        let mut series = series_settings::get(&manga.id);
+
+        series.panel_view = !series.panel_view;
+        series_settings::set(&manga.id, series);
+
+        siv.call_on_name("manga_data_dialog", |view: &mut Dialog| {
+            set_manga_data_buttons(view, &manga);
+        });
+    }
+
+    fn on_off(enabled: bool) -> &'static str {
+        if enabled {
+            "On"
+        } else {
+            "Off"
+        }
+    }
+
+    fn enhancement_label(manga: &manga::MangaSeries) -> String {
+        match series_settings::get(&manga.id).enhancement {
+            Some(enhancement) => format!(
+                "Enhancement: {} (set for this series)",
+                on_off(enhancement.enabled)
+            ),
+            None => format!(
+                "Enhancement: {} (from settings)",
+                on_off(config::get().device_profile().enhancement.enabled)
+            ),
+        }
+    }
+
+    // Cycles the series through following the settings, on and off. When on it
+    // uses the tuning of the settings.
+    fn toggle_enhancement(siv: &mut Cursive) {
+        let manga = siv.user_data::<manga::MangaSeries>().unwrap().clone();
+        let mut series = series_settings::get(&manga.id);
+
+        series.enhancement = match series.enhancement {
+            None => Some(manga::Enhancement {
+                enabled: true,
+                ..config::get().device_profile().enhancement
+            }),
+            Some(enhancement) if enhancement.enabled => Some(manga::Enhancement::disabled()),
+            Some(_) => None,
+        };
+        series_settings::set(&manga.id, series);
+
+        siv.call_on_name("manga_data_dialog", |view: &mut Dialog| {
+            set_manga_data_buttons(view, &manga);
+        });
+    }
+
+    fn long_strip_label(manga: &manga::MangaSeries) -> String {
+        match series_settings::get(&manga.id).long_strip {
+            Some(long_strip) => format!("Long Strip: {} (set for this series)", on_off(long_strip)),
+            None => String::from("Long Strip: Auto (from page shapes)"),
+        }
+    }
+
+    // Cycles the series through detecting long strips, always and never
+    // slicing them
+    fn toggle_long_strip(siv: &mut Cursive) {
+        let manga = siv.user_data::<manga::MangaSeries>().unwrap().clone();
+        let mut series = series_settings::get(&manga.id);
+
+        series.long_strip = match series.long_strip {
+            None => Some(true),
+            Some(true) => Some(false),
+            Some(false) => None,
+        };
+        series_settings::set(&manga.id, series);
+
+        siv.call_on_name("manga_data_dialog", |view: &mut Dialog| {
+            set_manga_data_buttons(view, &manga);
+        });
+    }
+
+    /// Fills the button row with the series' settings, each labelled with
+    /// its current value
+    fn set_manga_data_buttons(dialog: &mut Dialog, manga: &manga::MangaSeries) {
+        // Rebuilding the row loses focus, which is kept on the same button
+        let focus = dialog.focus();
+
+        dialog.clear_buttons();
+        dialog.add_button(reading_direction_label(manga), toggle_reading_direction);
+        dialog.add_button(panel_view_label(manga), toggle_panel_view);
+        dialog.add_button(enhancement_label(manga), toggle_enhancement);
+        dialog.add_button(long_strip_label(manga), toggle_long_strip);
+
+        dialog.set_focus(focus);
+    }
+
     let manga_data = Dialog::around(
         LinearLayout::vertical()
             .child(TextView::new(format!(
@@ -519,10 +929,17 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                     .align_center(),
             ),
     )
+    .with(|dialog| set_manga_data_buttons(dialog, siv.user_data::<manga::MangaSeries>().unwrap()))
     .title("Manga Details")
+    .with_name("manga_data_dialog")
     .fixed_height(15);
 
     // ─── Kindle Panel ────────────────────────────────────────────────────
+    /// Tells the parts of a book split for size apart in the lists
+    fn part_label(part: Option<manga::Part>) -> String {
+        part.map(|part| format!(" {}", part)).unwrap_or_default()
+    }
+
     fn update_kindle_select_view_dialog(siv: &mut Cursive) {
         siv.call_on_name("kindle_select_view_dialog", |view: &mut Dialog| {
             view.set_content(display_kindle_select_view_dialog());
@@ -537,6 +954,11 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
             if kindle.is_connected {
                 if !kindle.on_device_manga().unwrap().is_empty() {
                     for manga in kindle.on_device_manga().unwrap() {
+                        // Copies a newer build has replaced are marked
+                        let stale_label = match manifest::is_stale(&manga) {
+                            true => " (outdated)",
+                            false => "",
+                        };
                         let manga_title_short = {
                             if manga.manga_title.len() > 90 {
                                 format!("{}...", &manga.manga_title[..90])
@@ -546,16 +968,35 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                         };
                         if manga.r#type.eq("volume") {
                             kindle_select_view.add_item(
-                                format!("{} Volume {}", manga_title_short, manga.volume_title),
+                                format!(
+                                    "{} Volume {}{}{}",
+                                    manga_title_short,
+                                    manga.volume_title,
+                                    part_label(manga.part),
+                                    stale_label
+                                ),
+                                manga,
+                            );
+                        } else if manga.r#type.eq("bundle") {
+                            kindle_select_view.add_item(
+                                format!(
+                                    "{} Chapters {}{}{}",
+                                    manga_title_short,
+                                    manga.chapter_title.clone().unwrap(),
+                                    part_label(manga.part),
+                                    stale_label
+                                ),
                                 manga,
                             );
                         } else {
                             kindle_select_view.add_item(
                                 format!(
-                                    "{} Volume {} Chapter {}",
+                                    "{} Volume {} Chapter {}{}{}",
                                     manga_title_short,
                                     manga.volume_title,
-                                    manga.chapter_title.clone().unwrap()
+                                    manga.chapter_title.clone().unwrap(),
+                                    part_label(manga.part),
+                                    stale_label
                                 ),
                                 manga,
                             );
@@ -666,7 +1107,7 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
 
     fn update_que_files_select_view_dailog(siv: &mut Cursive) {
         siv.call_on_name("que_files_select_view_dialog", |view: &mut Dialog| {
-            view.set_content(display_kindle_select_view_dialog());
+            view.set_content(display_que_files_select_view_dailog());
         });
     }
 
@@ -715,6 +1156,12 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                                 Colour::Cyan
                                     .paint((que_file.size as f32 / 1024.0).round().to_string()),
                                 Colour::Purple.paint("kilobytes")
+                            )))
+                            .child(DummyView)
+                            .child(TextView::new(format!(
+                                "{}\n{}",
+                                Colour::Purple.paint("Delivery:"),
+                                Colour::Cyan.paint(que_file.delivery.to_string())
                             ))),
                     );
                 });
@@ -724,6 +1171,20 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                 kindle.scan();
                 if kindle.is_connected {
                     que::send_item_to_kindle(que_file, &kindle).unwrap();
+                } else if let Some(settings) = email::settings() {
+                    match que::email_item(que_file, &settings) {
+                        Ok(()) => siv.add_layer(
+                            Dialog::info(format!(
+                                "{} was emailed to the Kindle",
+                                que_file.file_name
+                            ))
+                            .title("Sent"),
+                        ),
+                        Err(error) => {
+                            siv.add_layer(Dialog::info(error.to_string()).title("(╯°□°）╯︵ ┻━┻"))
+                        }
+                    }
+                    update_que_files_select_view_dailog(siv);
                 } else {
                     siv.add_layer(Dialog::info("KIndle is not Connected").title("(╯°□°）╯︵ ┻━┻"));
                 }
@@ -741,16 +1202,32 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
                 };
                 if que_file.r#type.eq("volume") {
                     in_que_manga_select_view.add_item(
-                        format!("{} Volume {}", manga_title_short, que_file.volume_title),
+                        format!(
+                            "{} Volume {}{}",
+                            manga_title_short,
+                            que_file.volume_title,
+                            part_label(que_file.part)
+                        ),
+                        que_file,
+                    );
+                } else if que_file.r#type.eq("bundle") {
+                    in_que_manga_select_view.add_item(
+                        format!(
+                            "{} Chapters {}{}",
+                            manga_title_short,
+                            que_file.chapter_title.clone().unwrap(),
+                            part_label(que_file.part)
+                        ),
                         que_file,
                     );
                 } else {
                     in_que_manga_select_view.add_item(
                         format!(
-                            "{} Volume {} Chapter {}",
+                            "{} Volume {} Chapter {}{}",
                             manga_title_short,
                             que_file.volume_title,
-                            que_file.chapter_title.clone().unwrap()
+                            que_file.chapter_title.clone().unwrap(),
+                            part_label(que_file.part)
                         ),
                         que_file,
                     );
@@ -793,9 +1270,358 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
         update_que_files_select_view_dailog(siv);
     });
 
+    // ─── Settings Panel ──────────────────────────────────────────────────
+
+    /// `view` with `label` in front of it
+    fn setting_row<V: View>(label: &str, view: V) -> LinearLayout {
+        LinearLayout::horizontal()
+            .child(TextView::new(label).fixed_width(26))
+            .child(view)
+    }
+
+    fn setting_edit(name: &str, content: String) -> ResizedView<NamedView<EditView>> {
+        EditView::new()
+            .content(content)
+            .with_name(name)
+            .fixed_width(60)
+    }
+
+    fn setting_select<T: PartialEq + 'static>(
+        name: &str,
+        items: Vec<(String, T)>,
+        selected: &T,
+    ) -> NamedView<SelectView<T>> {
+        let position = items
+            .iter()
+            .position(|(_, item)| item == selected)
+            .unwrap_or_default();
+
+        SelectView::<T>::new()
+            .popup()
+            .with_all(items)
+            .selected(position)
+            .with_name(name)
+    }
+
+    fn display_settings_form() -> LinearLayout {
+        let config = config::get();
+        // Empty fields stand for settings that are not set
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let enhancement = config.device_profile().enhancement;
+        let switch = || vec![(String::from("On"), true), (String::from("Off"), false)];
+
+        LinearLayout::vertical()
+            .child(setting_row(
+                "Kindle disk name",
+                setting_edit("settings_device_name", config.device_name.clone()),
+            ))
+            .child(setting_row(
+                "Device profile",
+                setting_select(
+                    "settings_profile",
+                    manga::DeviceProfile::all()
+                        .into_iter()
+                        .map(|profile| (profile.name.clone(), profile.name))
+                        .collect(),
+                    &config.profile,
+                ),
+            ))
+            .child(setting_row(
+                "Resize width (pixels)",
+                setting_edit(
+                    "settings_resize_width",
+                    optional(config.resize_width.map(|width| width.to_string())),
+                ),
+            ))
+            .child(setting_row(
+                "Long strip overlap (rows)",
+                setting_edit(
+                    "settings_long_strip_overlap",
+                    config.long_strip_overlap.to_string(),
+                ),
+            ))
+            .child(setting_row(
+                "Language",
+                setting_edit("settings_language", config.language.clone()),
+            ))
+            .child(setting_row(
+                "Page quality",
+                setting_select(
+                    "settings_source_quality",
+                    vec![
+                        (String::from("Data saver"), manga::SourceQuality::DataSaver),
+                        (String::from("Original"), manga::SourceQuality::Data),
+                    ],
+                    &config.source_quality,
+                ),
+            ))
+            .child(setting_row(
+                "Book format",
+                setting_select(
+                    "settings_output_format",
+                    vec![
+                        (String::from("Kindle (AZW3)"), manga::OutputFormat::Mobi),
+                        (String::from("EPUB"), manga::OutputFormat::Epub),
+                        (String::from("CBZ"), manga::OutputFormat::Cbz),
+                        (String::from("PDF"), manga::OutputFormat::Pdf),
+                    ],
+                    &config.output_format,
+                ),
+            ))
+            .child(setting_row(
+                "File name template",
+                setting_edit(
+                    "settings_file_name_template",
+                    config.file_name_template.clone(),
+                ),
+            ))
+            .child(setting_row(
+                "Largest book (MB)",
+                setting_edit(
+                    "settings_max_book_megabytes",
+                    optional(config.max_book_megabytes.map(|size| size.to_string())),
+                ),
+            ))
+            .child(DummyView)
+            .child(TextView::new(
+                "Enhancement of series that do not set their own, the profile's unless changed",
+            ))
+            .child(setting_row(
+                "Enhance pages",
+                setting_select(
+                    "settings_enhancement_enabled",
+                    switch(),
+                    &enhancement.enabled,
+                ),
+            ))
+            .child(setting_row(
+                "Auto levels",
+                setting_select(
+                    "settings_enhancement_auto_levels",
+                    switch(),
+                    &enhancement.auto_levels,
+                ),
+            ))
+            .child(setting_row(
+                "Levels clipped (%)",
+                setting_edit(
+                    "settings_enhancement_clip_percent",
+                    enhancement.clip_percent.to_string(),
+                ),
+            ))
+            .child(setting_row(
+                "Sharpen radius",
+                setting_edit(
+                    "settings_enhancement_sharpen_sigma",
+                    enhancement.sharpen_sigma.to_string(),
+                ),
+            ))
+            .child(setting_row(
+                "Sharpen threshold",
+                setting_edit(
+                    "settings_enhancement_sharpen_threshold",
+                    enhancement.sharpen_threshold.to_string(),
+                ),
+            ))
+            .child(DummyView)
+            .child(setting_row(
+                "Converter",
+                setting_select(
+                    "settings_converter_backend",
+                    vec![
+                        (String::from("Built in"), manga::ConverterBackend::Native),
+                        (
+                            String::from("kindlegen"),
+                            manga::ConverterBackend::Kindlegen,
+                        ),
+                        (
+                            String::from("Calibre ebook-convert"),
+                            manga::ConverterBackend::EbookConvert,
+                        ),
+                    ],
+                    &config.converter.backend,
+                ),
+            ))
+            .child(setting_row(
+                "Converter compression",
+                setting_select(
+                    "settings_converter_compression",
+                    vec![
+                        (String::from("None"), manga::Compression::None),
+                        (String::from("Standard"), manga::Compression::Standard),
+                        (String::from("Huffdic"), manga::Compression::Huffdic),
+                    ],
+                    &config.converter.compression,
+                ),
+            ))
+            .child(setting_row(
+                "Converter timeout (s)",
+                setting_edit(
+                    "settings_converter_timeout_secs",
+                    config.converter.timeout_secs.to_string(),
+                ),
+            ))
+            .child(setting_row(
+                "Converter path",
+                setting_edit(
+                    "settings_converter_tool_path",
+                    optional(
+                        config
+                            .converter
+                            .tool_path
+                            .map(|tool_path| tool_path.display().to_string()),
+                    ),
+                ),
+            ))
+    }
+
+    /// The settings in the form, or what could not be read as a number
+    fn read_settings_form(siv: &mut Cursive) -> Result<config::Config, config::ConfigError> {
+        let mut text = |name: &str| {
+            siv.call_on_name(name, |view: &mut EditView| {
+                view.get_content().trim().to_string()
+            })
+            .unwrap()
+        };
+
+        let device_name = text("settings_device_name");
+        let resize_width = text("settings_resize_width");
+        let long_strip_overlap = text("settings_long_strip_overlap");
+        let clip_percent = text("settings_enhancement_clip_percent");
+        let sharpen_sigma = text("settings_enhancement_sharpen_sigma");
+        let sharpen_threshold = text("settings_enhancement_sharpen_threshold");
+        let language = text("settings_language");
+        let file_name_template = text("settings_file_name_template");
+        let max_book_megabytes = text("settings_max_book_megabytes");
+        let timeout_secs = text("settings_converter_timeout_secs");
+        let tool_path = text("settings_converter_tool_path");
+
+        fn choice<T: Clone + 'static>(siv: &mut Cursive, name: &str) -> T {
+            siv.call_on_name(name, |view: &mut SelectView<T>| view.selection())
+                .flatten()
+                .map(|selection| (*selection).clone())
+                .unwrap()
+        }
+
+        fn number<T: std::str::FromStr>(
+            problems: &mut Vec<String>,
+            setting: &str,
+            value: &str,
+        ) -> Option<T> {
+            match value {
+                "" => None,
+                value => value.parse::<T>().map(Some).unwrap_or_else(|_| {
+                    problems.push(format!("{}: {:?} is not a number", setting, value));
+                    None
+                }),
+            }
+        }
+
+        let mut problems = Vec::new();
+        let resize_width: Option<u64> = number(&mut problems, "resize_width", &resize_width);
+        let long_strip_overlap: Option<u32> =
+            number(&mut problems, "long_strip_overlap", &long_strip_overlap);
+        let clip_percent: Option<f32> =
+            number(&mut problems, "enhancement.clip_percent", &clip_percent);
+        let sharpen_sigma: Option<f32> =
+            number(&mut problems, "enhancement.sharpen_sigma", &sharpen_sigma);
+        let sharpen_threshold: Option<i32> = number(
+            &mut problems,
+            "enhancement.sharpen_threshold",
+            &sharpen_threshold,
+        );
+        let max_book_megabytes = number(&mut problems, "max_book_megabytes", &max_book_megabytes);
+        let timeout_secs = number(&mut problems, "converter.timeout_secs", &timeout_secs);
+
+        if !problems.is_empty() {
+            return Err(config::ConfigError { problems });
+        }
+
+        let mut config = config::get();
+        config.device_name = device_name;
+        config.profile = choice(siv, "settings_profile");
+        // Widths too large for a u32 are out of range all the same
+        config.resize_width = resize_width.map(|width| u32::try_from(width).unwrap_or(u32::MAX));
+        // Empty fields keep the defaults
+        config.long_strip_overlap =
+            long_strip_overlap.unwrap_or(manga::LongStrip::default().overlap);
+
+        // Empty fields keep the profile's values, and so does an enhancement
+        // that is the profile's
+        let profile_enhancement = manga::DeviceProfile::by_name(&config.profile)
+            .unwrap_or_default()
+            .enhancement;
+        let enhancement = manga::Enhancement {
+            enabled: choice(siv, "settings_enhancement_enabled"),
+            auto_levels: choice(siv, "settings_enhancement_auto_levels"),
+            clip_percent: clip_percent.unwrap_or(profile_enhancement.clip_percent),
+            sharpen_sigma: sharpen_sigma.unwrap_or(profile_enhancement.sharpen_sigma),
+            sharpen_threshold: sharpen_threshold.unwrap_or(profile_enhancement.sharpen_threshold),
+        };
+        config.enhancement = (enhancement != profile_enhancement).then_some(enhancement);
+        config.language = language;
+        config.source_quality = choice(siv, "settings_source_quality");
+        config.output_format = choice(siv, "settings_output_format");
+        config.file_name_template = file_name_template;
+        config.max_book_megabytes = max_book_megabytes;
+        config.converter.backend = choice(siv, "settings_converter_backend");
+        config.converter.compression = choice(siv, "settings_converter_compression");
+        config.converter.timeout_secs =
+            timeout_secs.unwrap_or(manga::ConverterSettings::default().timeout_secs);
+        config.converter.tool_path = (!tool_path.is_empty()).then(|| tool_path.into());
+
+        Ok(config)
+    }
+
+    fn save_settings(siv: &mut Cursive) {
+        match read_settings_form(siv).and_then(config::save) {
+            Ok(()) => {
+                let mut message =
+                    String::from("Books checked out from now on are built with these settings.");
+
+                // The chapters listed are translated to the language the series was loaded in
+                let language = config::get().language;
+                let reload = siv
+                    .user_data::<manga::MangaSeries>()
+                    .filter(|manga| manga.language != language)
+                    .map(|manga| manga.id.clone());
+                if let Some(manga_id) = reload {
+                    siv.pop_layer();
+                    display_content(siv, &manga_id);
+                    message.push_str(&format!(
+                        "\n\nThe series was loaded again with its chapters in {}.",
+                        language
+                    ));
+                }
+
+                siv.add_layer(Dialog::info(message).title("Settings Saved"));
+            }
+            Err(error) => siv.add_layer(Dialog::info(error.to_string()).title("Invalid Settings")),
+        }
+    }
+
+    let settings_panel = Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new(format!(
+                "{}    {}",
+                Colour::Purple.paint("Saved in:"),
+                Colour::Cyan
+                    .bold()
+                    .paint(paths::config_file().display().to_string())
+            )))
+            .child(DummyView)
+            .child(display_settings_form())
+            .scrollable(),
+    )
+    .button("Save", save_settings)
+    .title("Settings")
+    .full_height()
+    .with_name("Settings");
+
     // ─────────────────────────────────────────────────────────────
 
     let content_panel = TabPanel::new()
+        .with_tab(settings_panel)
         .with_tab(kindle_panel)
         .with_tab(manga_panel)
         .with_name("content_panel");
@@ -804,12 +1630,13 @@ fn display_content(siv: &mut Cursive, manga_id: &str) {
         LinearLayout::vertical()
             .child(title)
             .child(Dialog::text(format!(
-                "Keyboard Shortcuts: ({})uit, ({})escan for kindle, lookup ({})nother manga, ({})anga tab, ({})indle tab, ({})ogger tab", 
+                "Keyboard Shortcuts: ({})uit, ({})escan for kindle, lookup ({})nother manga, ({})anga tab, ({})indle tab, ({})ettings tab, ({})ogger tab", 
                 Colour::Blue.bold().paint("q"),
                 Colour::Blue.bold().paint("r"),
                 Colour::Blue.bold().paint("a"),
                 Colour::Blue.bold().paint("m"),
                 Colour::Blue.bold().paint("k"),
+                Colour::Blue.bold().paint("s"),
                 Colour::Blue.bold().paint("l"),
                 )).align_center())
             .child(manga_data)
@@ -7,7 +7,27 @@ pub fn get_json(url: String) -> serde_json::Value {
         .unwrap()
 }
 
+use std::fmt;
 use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::manga::BuildSettings;
+
+/// Which of the books a volume or chapter too large for one was split into
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part {
+    /// Counted from 1
+    pub number: usize,
+    pub count: usize,
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Part {}/{}", self.number, self.count)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Outputfile {
     ///content type
@@ -27,4 +47,13 @@ pub struct Outputfile {
 
     /// file size
     pub size: u64,
+
+    /// part of its volume or chapter, none for books that were not split
+    pub part: Option<Part>,
+
+    /// settings the pages were processed with, none for books built before they were recorded
+    pub build_settings: Option<BuildSettings>,
+
+    /// what the converter warned about while converting the book
+    pub warnings: Vec<String>,
 }
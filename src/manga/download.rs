@@ -0,0 +1,356 @@
+use std::{error::Error, fmt, fs, path::Path, thread, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How many times a single page or cover is requested before the build is given up on
+const MAX_ATTEMPTS: usize = 4;
+
+// ─── Errors ──────────────────────────────────────────────────────────────────
+
+/// Why a single page could not be obtained
+#[derive(Debug, Clone)]
+pub enum PageError {
+    /// The at-home server or the page itself could not be requested
+    Request(String),
+    /// The bytes that arrived do not match the digest in the file name
+    HashMismatch { expected: String, actual: String },
+    /// The bytes that arrived are not an image we can open
+    Undecodable(String),
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageError::Request(error) => write!(f, "request failed: {}", error),
+            PageError::HashMismatch { expected, actual } => {
+                write!(f, "hash mismatch: expected {}, got {}", expected, actual)
+            }
+            PageError::Undecodable(error) => write!(f, "not a valid image: {}", error),
+        }
+    }
+}
+
+/// A page that still failed after every attempt
+#[derive(Debug, Clone)]
+pub struct PageFailure {
+    /// 1-based page number within the chapter
+    pub page: usize,
+    pub file_name: String,
+    pub error: PageError,
+}
+
+/// The pages of one chapter that could not be obtained
+#[derive(Debug, Clone)]
+pub struct ChapterFailure {
+    pub volume_title: String,
+    pub chapter_title: String,
+    /// Set when no at-home server could be obtained, so no page was attempted
+    pub server_error: Option<PageError>,
+    pub pages: Vec<PageFailure>,
+}
+
+/// Returned when one or more chapters of a build are missing pages
+#[derive(Debug, Clone)]
+pub struct DownloadError {
+    pub manga_title: String,
+    pub chapters: Vec<ChapterFailure>,
+    /// Set when the volume's cover could not be obtained
+    pub cover: Option<PageError>,
+}
+
+impl Error for DownloadError {}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Some pages of {} could not be downloaded:",
+            self.manga_title
+        )?;
+
+        if let Some(error) = &self.cover {
+            writeln!(f, "Cover: {}", error)?;
+        }
+
+        for chapter in &self.chapters {
+            if let Some(error) = &chapter.server_error {
+                writeln!(
+                    f,
+                    "Volume {} Chapter {}: no at-home server available, {}",
+                    chapter.volume_title, chapter.chapter_title, error
+                )?;
+                continue;
+            }
+
+            writeln!(
+                f,
+                "Volume {} Chapter {}: {} page(s) missing",
+                chapter.volume_title,
+                chapter.chapter_title,
+                chapter.pages.len()
+            )?;
+
+            for page in &chapter.pages {
+                writeln!(
+                    f,
+                    "    page {} ({}): {}",
+                    page.page, page.file_name, page.error
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ─── At-Home Server ──────────────────────────────────────────────────────────
+
+/// Which of MangaDex's renditions of the pages is downloaded
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceQuality {
+    /// Compressed pages, a fraction of the size and plenty for e-ink
+    #[default]
+    DataSaver,
+    /// The pages as the scanlators uploaded them
+    Data,
+}
+
+impl SourceQuality {
+    /// Name of the rendition in page URLs
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceQuality::DataSaver => "data-saver",
+            SourceQuality::Data => "data",
+        }
+    }
+
+    /// Key of the rendition's pages in at-home responses
+    fn pages_key(&self) -> &'static str {
+        match self {
+            SourceQuality::DataSaver => "dataSaver",
+            SourceQuality::Data => "data",
+        }
+    }
+}
+
+/// The MangaDex@Home server a chapter's pages are served from
+#[derive(Debug, Clone)]
+pub struct AtHomeServer {
+    pub base_url: String,
+    pub chapter_hash: String,
+    pub quality: SourceQuality,
+    /// Server file names of the pages in `quality`, in reading order
+    pub pages: Vec<String>,
+}
+
+impl AtHomeServer {
+    /// Asks MangaDex for a server to download `chapter_id` from in `quality`.
+    ///
+    /// Each call may hand out a different server, which is what `fetch_page`
+    /// relies on to get away from a server that serves broken pages.
+    pub fn request(chapter_id: &str, quality: SourceQuality) -> Result<AtHomeServer, PageError> {
+        let chapter_data = reqwest::blocking::get(format!(
+            "https://api.mangadex.org/at-home/server/{}",
+            chapter_id
+        ))
+        .and_then(|response| response.json::<serde_json::Value>())
+        .map_err(|error| PageError::Request(error.to_string()))?;
+
+        let base_url = chapter_data["baseUrl"].as_str();
+        let chapter_hash = chapter_data["chapter"]["hash"].as_str();
+        let pages = chapter_data["chapter"][quality.pages_key()].as_array();
+
+        match (base_url, chapter_hash, pages) {
+            (Some(base_url), Some(chapter_hash), Some(pages)) => Ok(AtHomeServer {
+                base_url: base_url.to_owned(),
+                chapter_hash: chapter_hash.to_owned(),
+                quality,
+                pages: pages
+                    .iter()
+                    .filter_map(|page| page.as_str().map(str::to_owned))
+                    .collect(),
+            }),
+            _ => Err(PageError::Request(format!(
+                "unexpected at-home response for chapter {}",
+                chapter_id
+            ))),
+        }
+    }
+
+    fn page_url(&self, file_name: &str) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.base_url,
+            self.quality.name(),
+            self.chapter_hash,
+            file_name
+        )
+    }
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// The SHA-256 digest MangaDex embeds in page file names, e.g. the part after
+/// `x1-` in `x1-b765e86d5ecbc932cf3f517a8604f6ac6d8a7f379b0277a117dc7c09c53d041e.jpg`
+pub fn expected_digest(file_name: &str) -> Option<String> {
+    let stem = Path::new(file_name).file_stem()?.to_str()?;
+    let digest = stem.rsplit('-').next()?;
+
+    if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(digest.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Checks downloaded page bytes against the digest in `file_name` (when it has
+/// one) and makes sure they decode as an image.
+pub fn verify_page(file_name: &str, bytes: &[u8]) -> Result<(), PageError> {
+    if let Some(expected) = expected_digest(file_name) {
+        let actual = format!("{:x}", Sha256::digest(bytes));
+
+        if actual != expected {
+            return Err(PageError::HashMismatch { expected, actual });
+        }
+    }
+
+    image::load_from_memory(bytes)
+        .map(|_| ())
+        .map_err(|error| PageError::Undecodable(error.to_string()))
+}
+
+/// Downloads the page `file_name` of `chapter_id` to `output_path`.
+///
+/// A page that fails verification is requested again, each time from a freshly
+/// requested at-home server. Nothing is written to `output_path` unless the
+/// page verified.
+pub fn fetch_page(
+    chapter_id: &str,
+    server: &AtHomeServer,
+    file_name: &str,
+    output_path: &Path,
+) -> Result<(), PageError> {
+    let mut server = server.clone();
+    let mut last_error = PageError::Request(String::from("no attempt was made"));
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        if attempt > 1 {
+            // Back off a little, then move to whichever server MangaDex hands out now
+            thread::sleep(Duration::from_millis(500 * attempt as u64));
+
+            match AtHomeServer::request(chapter_id, server.quality) {
+                Ok(new_server) => server = new_server,
+                Err(error) => {
+                    last_error = error;
+                    continue;
+                }
+            }
+        }
+
+        match download_verified(&server.page_url(file_name), file_name) {
+            Ok(bytes) => {
+                return fs::write(output_path, &bytes)
+                    .map_err(|error| PageError::Request(error.to_string()));
+            }
+            Err(error) => {
+                log::warn!(
+                    "page {} of chapter {} failed attempt {}/{}: {}",
+                    file_name,
+                    chapter_id,
+                    attempt,
+                    MAX_ATTEMPTS,
+                    error
+                );
+                last_error = error;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Downloads the cover at `url` to `output_path`, checking it decodes.
+///
+/// A cover that fails is requested again, and nothing is written to
+/// `output_path` unless it decoded.
+pub fn fetch_cover(url: &str, output_path: &Path) -> Result<(), PageError> {
+    let file_name = url.rsplit('/').next().unwrap_or(url);
+    let mut last_error = PageError::Request(String::from("no attempt was made"));
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        if attempt > 1 {
+            thread::sleep(Duration::from_millis(500 * attempt as u64));
+        }
+
+        match download_verified(url, file_name) {
+            Ok(bytes) => {
+                return fs::write(output_path, bytes)
+                    .map_err(|error| PageError::Request(error.to_string()));
+            }
+            Err(error) => {
+                log::warn!(
+                    "cover {} failed attempt {}/{}: {}",
+                    url,
+                    attempt,
+                    MAX_ATTEMPTS,
+                    error
+                );
+                last_error = error;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// The bytes at `url`, once they pass [`verify_page`] for `file_name`
+fn download_verified(url: &str, file_name: &str) -> Result<Vec<u8>, PageError> {
+    let bytes = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map_err(|error| PageError::Request(error.to_string()))?;
+
+    verify_page(file_name, &bytes)?;
+
+    Ok(bytes.to_vec())
+}
+
+#[test]
+fn digest_from_file_name() {
+    let digest = "b765e86d5ecbc932cf3f517a8604f6ac6d8a7f379b0277a117dc7c09c53d041e";
+
+    assert_eq!(
+        expected_digest(&format!("x1-{}.jpg", digest)).as_deref(),
+        Some(digest)
+    );
+    assert_eq!(expected_digest("x1-notahash.jpg"), None);
+    assert_eq!(expected_digest("cover.png"), None);
+}
+
+#[test]
+fn verify_rejects_bad_pages() {
+    let mut png = Vec::new();
+    image::DynamicImage::new_luma8(2, 2)
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+    let file_name = format!("x1-{:x}.png", Sha256::digest(&png));
+
+    assert!(verify_page(&file_name, &png).is_ok());
+
+    // Truncated download
+    assert!(matches!(
+        verify_page(&file_name, &png[..png.len() / 2]),
+        Err(PageError::HashMismatch { .. })
+    ));
+
+    // No digest to compare against, but still not an image
+    assert!(matches!(
+        verify_page("page.png", b"<html>502</html>"),
+        Err(PageError::Undecodable(_))
+    ));
+}
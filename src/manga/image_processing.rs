@@ -0,0 +1,219 @@
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use fast_image_resize as fr;
+use image::{imageops, io::Reader as ImageReader, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::manga::panels;
+use crate::manga::settings::BuildSettings;
+
+// ─── Enhancement ─────────────────────────────────────────────────────────────
+
+/// Contrast and sharpness tuning for e-ink screens
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Enhancement {
+    pub enabled: bool,
+    /// Stretch the levels so the darkest and lightest tones become black and white
+    pub auto_levels: bool,
+    /// Percentage of pixels at each end of the histogram that may be clipped
+    /// when stretching the levels
+    pub clip_percent: f32,
+    /// Blur radius of the unsharp mask, `0.0` disables sharpening
+    pub sharpen_sigma: f32,
+    /// Minimum brightness difference the unsharp mask acts on
+    pub sharpen_threshold: i32,
+}
+
+impl Enhancement {
+    pub fn disabled() -> Enhancement {
+        Enhancement {
+            enabled: false,
+            ..Enhancement::default()
+        }
+    }
+}
+
+impl Default for Enhancement {
+    fn default() -> Self {
+        Enhancement {
+            enabled: true,
+            auto_levels: true,
+            clip_percent: 0.5,
+            sharpen_sigma: 0.8,
+            sharpen_threshold: 2,
+        }
+    }
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// Resizes the page at `image_path` to the width of the device profile, applies
+/// the enhancement and writes it back in place. With Panel View on, the page's
+/// panels are found while it is decoded and kept next to it.
+pub fn process_page(image_path: &Path, settings: &BuildSettings) {
+    let opened_image = ImageReader::open(image_path).unwrap().decode().unwrap();
+
+    let mut page = resize_to_width(&opened_image, settings.profile.width);
+
+    enhance(&mut page, &settings.enhancement);
+
+    if settings.panel_view {
+        panels::save_regions(
+            &imageops::grayscale(&page),
+            image_path,
+            settings.reading_direction,
+        );
+    }
+
+    // Pages carry no meaningful transparency, and jpeg cannot store it
+    DynamicImage::ImageRgba8(page)
+        .into_rgb8()
+        .save(image_path)
+        .unwrap();
+}
+
+/// Applies `enhancement` to an already downscaled page
+pub fn enhance(page: &mut RgbaImage, enhancement: &Enhancement) {
+    if !enhancement.enabled {
+        return;
+    }
+
+    if enhancement.auto_levels {
+        stretch_levels(page, enhancement.clip_percent);
+    }
+
+    if enhancement.sharpen_sigma > 0.0 {
+        *page = image::imageops::unsharpen(
+            page,
+            enhancement.sharpen_sigma,
+            enhancement.sharpen_threshold,
+        );
+    }
+}
+
+fn resize_to_width(opened_image: &DynamicImage, target_width: u32) -> RgbaImage {
+    let width = NonZeroU32::new(opened_image.width()).unwrap();
+    let height = NonZeroU32::new(opened_image.height()).unwrap();
+
+    let mut src_image = fr::Image::from_vec_u8(
+        width,
+        height,
+        opened_image.to_rgba8().into_raw(),
+        fr::PixelType::U8x4,
+    )
+    .unwrap();
+
+    // Multiple RGB channels of source image by alpha channel
+    // (not required for the Nearest algorithm)
+    let alpha_mul_div = fr::MulDiv::default();
+    alpha_mul_div
+        .multiply_alpha_inplace(&mut src_image.view_mut())
+        .unwrap();
+
+    // height of the destination image
+    let hszize = ((opened_image.height() as f64)
+        * (target_width as f64 / (opened_image.width() as f64)))
+        .round()
+        .max(1.0) as u32;
+
+    // Create container for data of destination image
+    let dst_width = NonZeroU32::new(target_width).unwrap();
+    let dst_height = NonZeroU32::new(hszize).unwrap();
+    let mut dst_image = fr::Image::new(dst_width, dst_height, src_image.pixel_type());
+
+    // Get mutable view of destination image data
+    let mut dst_view = dst_image.view_mut();
+
+    // Create Resizer instance and resize source image
+    // into buffer of destination image
+    let mut resizer = fr::Resizer::new(fr::ResizeAlg::Convolution(fr::FilterType::CatmullRom));
+    resizer.resize(&src_image.view(), &mut dst_view).unwrap();
+
+    // Divide RGB channels of destination image by alpha
+    alpha_mul_div.divide_alpha_inplace(&mut dst_view).unwrap();
+
+    RgbaImage::from_raw(dst_width.get(), dst_height.get(), dst_image.into_vec()).unwrap()
+}
+
+/// Maps the tones between the `clip_percent` darkest and lightest pixels onto
+/// the full 0-255 range, which turns washed-out gray scans black and white.
+fn stretch_levels(page: &mut RgbaImage, clip_percent: f32) {
+    let mut histogram = [0u64; 256];
+    for pixel in page.pixels() {
+        histogram[luma(pixel.0) as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let clipped = (total as f64 * (clip_percent.clamp(0.0, 49.0) as f64 / 100.0)) as u64;
+
+    let low = percentile(histogram.iter().enumerate(), clipped);
+    let high = percentile(histogram.iter().enumerate().rev(), clipped);
+
+    // Nearly flat pages (blank or single tone) would only get noisier
+    if high <= low || high - low < 16 {
+        return;
+    }
+
+    let scale = 255.0 / (high - low) as f32;
+    let mut lookup = [0u8; 256];
+    for (value, mapped) in lookup.iter_mut().enumerate() {
+        *mapped = ((value as f32 - low as f32) * scale)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+
+    for pixel in page.pixels_mut() {
+        for channel in pixel.0.iter_mut().take(3) {
+            *channel = lookup[*channel as usize];
+        }
+    }
+}
+
+/// First tone, walking the histogram in the given order, past `skip` pixels
+fn percentile<'a, I: Iterator<Item = (usize, &'a u64)>>(histogram: I, skip: u64) -> u8 {
+    let mut seen = 0;
+    let mut last = 0;
+    for (value, count) in histogram {
+        last = value;
+        seen += count;
+        if seen > skip {
+            break;
+        }
+    }
+    last as u8
+}
+
+fn luma(rgba: [u8; 4]) -> u8 {
+    ((rgba[0] as u32 * 299 + rgba[1] as u32 * 587 + rgba[2] as u32 * 114) / 1000) as u8
+}
+
+#[test]
+fn levels_are_stretched() {
+    // A washed-out page that only uses tones 100 to 180
+    let mut page = RgbaImage::from_fn(16, 16, |x, _| {
+        let tone = if x < 8 { 100 } else { 180 };
+        image::Rgba([tone, tone, tone, 255])
+    });
+
+    enhance(
+        &mut page,
+        &Enhancement {
+            sharpen_sigma: 0.0,
+            ..Enhancement::default()
+        },
+    );
+
+    assert_eq!(page.get_pixel(0, 0).0, [0, 0, 0, 255]);
+    assert_eq!(page.get_pixel(15, 0).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn disabled_enhancement_leaves_page_alone() {
+    let mut page = RgbaImage::from_pixel(4, 4, image::Rgba([120, 120, 120, 255]));
+    let original = page.clone();
+
+    enhance(&mut page, &Enhancement::disabled());
+
+    assert_eq!(page, original);
+}
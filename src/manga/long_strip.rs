@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{imageops, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::manga::panels;
+use crate::manga::settings::BuildSettings;
+use crate::workspace::Workspace;
+
+/// The MangaDex tag that marks a series as a webtoon
+pub const LONG_STRIP_TAG: &str = "Long Strip";
+
+/// Largest difference between the lightest and darkest pixel of a row for it
+/// to count as a gap between panels
+const GAP_TOLERANCE: u8 = 12;
+
+/// Fraction of a page, counted back from its bottom, searched for a gap to cut at
+const GAP_SEARCH: f32 = 0.25;
+
+// ─── Settings ────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongStripMode {
+    /// Slice a chapter only if one of its images is taller than the threshold
+    Auto,
+    On,
+    Off,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LongStrip {
+    pub mode: LongStripMode,
+    /// Height to width ratio above which an image is treated as a strip
+    pub aspect_threshold: f32,
+    /// Rows repeated at the top of the next page when a cut goes through artwork
+    pub overlap: u32,
+}
+
+impl Default for LongStrip {
+    fn default() -> Self {
+        LongStrip {
+            mode: LongStripMode::Auto,
+            aspect_threshold: 2.5,
+            overlap: 48,
+        }
+    }
+}
+
+// ─── Slicer ──────────────────────────────────────────────────────────────────
+
+/// Stitches strip images together and cuts them into pages of `page_height`
+/// rows, preferring to cut in the whitespace between panels.
+pub struct Slicer {
+    width: u32,
+    page_height: u32,
+    overlap: u32,
+    /// RGB rows that have not been cut into a page yet
+    buffer: Vec<u8>,
+}
+
+impl Slicer {
+    pub fn new(width: u32, page_height: u32, overlap: u32) -> Slicer {
+        Slicer {
+            width,
+            page_height: page_height.max(1),
+            // An overlap of a whole page would never make progress
+            overlap: overlap.min(page_height / 4),
+            buffer: Vec::new(),
+        }
+    }
+
+    fn row_len(&self) -> usize {
+        self.width as usize * 3
+    }
+
+    fn buffered_rows(&self) -> u32 {
+        (self.buffer.len() / self.row_len()) as u32
+    }
+
+    fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.row_len();
+        &self.buffer[start..start + self.row_len()]
+    }
+
+    fn is_gap(&self, y: u32) -> bool {
+        let (min, max) = self
+            .row(y)
+            .chunks(3)
+            .map(|rgb| ((rgb[0] as u32 + rgb[1] as u32 + rgb[2] as u32) / 3) as u8)
+            .fold((u8::MAX, u8::MIN), |(min, max), luma| {
+                (min.min(luma), max.max(luma))
+            });
+
+        max - min <= GAP_TOLERANCE
+    }
+
+    /// Appends a strip image, returning every page that is now complete
+    pub fn push(&mut self, strip: &RgbImage) -> Vec<RgbImage> {
+        assert_eq!(strip.width(), self.width, "strips must share one width");
+
+        self.buffer.extend_from_slice(strip.as_raw());
+
+        let mut pages = Vec::new();
+
+        // Only cut once a whole page is buffered, so the gap search has
+        // the full page to look at
+        while self.buffered_rows() > self.page_height {
+            pages.push(self.cut());
+        }
+
+        pages
+    }
+
+    /// Returns whatever is left as the last page
+    pub fn finish(mut self) -> Option<RgbImage> {
+        if self.buffered_rows() == 0 {
+            return None;
+        }
+
+        let rows = self.buffered_rows();
+        let buffer = std::mem::take(&mut self.buffer);
+
+        RgbImage::from_raw(self.width, rows, buffer)
+    }
+
+    fn cut(&mut self) -> RgbImage {
+        let search_start = self.page_height - (self.page_height as f32 * GAP_SEARCH).round() as u32;
+
+        let gap = (search_start..=self.page_height)
+            .rev()
+            .find(|&y| self.is_gap(y));
+
+        // Cutting through a gap loses nothing; cutting through artwork repeats
+        // the last few rows on the next page so no line is split unreadably
+        let (cut_at, resume_at) = match gap {
+            Some(y) => (y.max(1), y.max(1)),
+            None => (self.page_height, self.page_height - self.overlap),
+        };
+
+        let page_bytes = cut_at as usize * self.row_len();
+        let page = self.buffer[..page_bytes].to_vec();
+
+        self.buffer.drain(..resume_at as usize * self.row_len());
+
+        RgbImage::from_raw(self.width, cut_at, page).unwrap()
+    }
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// Whether the pages of a chapter should be sliced
+pub fn is_long_strip(pages: &[PathBuf], long_strip: &LongStrip) -> bool {
+    match long_strip.mode {
+        LongStripMode::On => true,
+        LongStripMode::Off => false,
+        LongStripMode::Auto => pages.iter().any(|page| {
+            image::image_dimensions(page)
+                .map(|(width, height)| height as f32 / width as f32 > long_strip.aspect_threshold)
+                .unwrap_or(false)
+        }),
+    }
+}
+
+/// Cuts the processed strip images of a chapter into screen-sized pages.
+///
+/// The new pages are written to `workspace` as `<stem>-strip-NNN.jpg` and the
+/// strip images they were cut from are removed.
+pub fn slice_chapter(
+    strips: Vec<PathBuf>,
+    workspace: &Workspace,
+    stem: &str,
+    settings: &BuildSettings,
+) -> Vec<PathBuf> {
+    let profile = &settings.profile;
+
+    let mut pages: Vec<PathBuf> = Vec::new();
+    let mut save = |page: RgbImage| {
+        let path = workspace.file(format!("{}-strip-{:03}.jpg", stem, pages.len() + 1));
+        page.save(&path).unwrap();
+        if settings.panel_view {
+            panels::save_regions(
+                &imageops::grayscale(&page),
+                &path,
+                settings.reading_direction,
+            );
+        }
+        pages.push(path);
+    };
+
+    let mut slicer: Option<Slicer> = None;
+
+    for strip_path in strips.iter() {
+        let strip = image::open(strip_path).unwrap();
+
+        // Processed pages share the profile width; anything else starts a new run
+        if slicer.as_ref().map(|slicer| slicer.width) != Some(strip.width()) {
+            if let Some(page) = slicer.take().and_then(Slicer::finish) {
+                save(page);
+            }
+
+            let page_height = (strip.width() as f32 * profile.height as f32 / profile.width as f32)
+                .round() as u32;
+
+            slicer = Some(Slicer::new(
+                strip.width(),
+                page_height,
+                settings.long_strip.overlap,
+            ));
+        }
+
+        for page in slicer.as_mut().unwrap().push(&strip.to_rgb8()) {
+            save(page);
+        }
+
+        remove_strip(strip_path);
+    }
+
+    if let Some(page) = slicer.and_then(Slicer::finish) {
+        save(page);
+    }
+
+    pages
+}
+
+fn remove_strip(strip_path: &Path) {
+    if let Err(error) = fs::remove_file(strip_path) {
+        log::warn!("could not remove {}: {}", strip_path.display(), error);
+    }
+    panels::remove_regions(strip_path);
+}
+
+#[test]
+fn strips_are_cut_at_gaps() {
+    // Two panels of artwork separated by a white gap at rows 70..80
+    let strip = RgbImage::from_fn(10, 200, |x, y| {
+        if (70..80).contains(&y) || (x + y) % 2 == 0 {
+            image::Rgb([255, 255, 255])
+        } else {
+            image::Rgb([0, 0, 0])
+        }
+    });
+
+    let mut slicer = Slicer::new(10, 80, 8);
+    let pages = slicer.push(&strip);
+
+    // The first page ends in the gap instead of at the full page height
+    assert_eq!(pages[0].height(), 79);
+    assert!(pages.iter().all(|page| page.height() <= 80));
+
+    let total: u32 = pages.iter().map(|page| page.height()).sum::<u32>()
+        + slicer.finish().map_or(0, |page| page.height());
+    assert!(total >= 200);
+}
+
+#[test]
+fn strips_without_gaps_overlap() {
+    let strip = RgbImage::from_fn(10, 100, |x, y| {
+        let tone = if (x + y) % 2 == 0 { 0 } else { 255 };
+        image::Rgb([tone, tone, tone])
+    });
+
+    let mut slicer = Slicer::new(10, 40, 8);
+    let pages = slicer.push(&strip);
+    let last = slicer.finish().unwrap();
+
+    // 100 rows in pages of 40 that each repeat 8 rows: 40 + 40 + 36
+    assert_eq!(pages.len(), 2);
+    assert!(pages.iter().all(|page| page.height() == 40));
+    assert_eq!(last.height(), 36);
+}
@@ -0,0 +1,196 @@
+//! Writes books as CBZ: the pages in reading order in a zip archive, with a
+//! `ComicInfo.xml` that comic readers such as KOReader take the metadata from.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use eyre::{Context, Result};
+use libzip::write::FileOptions;
+use libzip::{CompressionMethod, ZipWriter};
+
+use super::{get_extension_from_filename, BookMetadata};
+use crate::manga::settings::ReadingDirection;
+
+/// Archives `images`, the cover first, as the CBZ at `cbz_path`. Files are
+/// stamped with `modified_date` if there is one, else with the current time.
+pub fn write(
+    images: &[PathBuf],
+    comic_info: &str,
+    cbz_path: &Path,
+    modified_date: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let file = fs::File::create(cbz_path)
+        .wrap_err_with(|| format!("could not create {}", cbz_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+
+    // Pages are already compressed images, deflating them gains nothing
+    let mut options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    if let Some(date) = modified_date {
+        options = options.last_modified_time(
+            libzip::DateTime::from_date_and_time(
+                u16::try_from(date.year()).unwrap_or_default(),
+                date.month() as u8,
+                date.day() as u8,
+                date.hour() as u8,
+                date.minute() as u8,
+                date.second() as u8,
+            )
+            .unwrap_or_default(),
+        );
+    }
+
+    zip.start_file(
+        "ComicInfo.xml",
+        options.compression_method(CompressionMethod::Deflated),
+    )?;
+    zip.write_all(comic_info.as_bytes())?;
+
+    // Readers sort pages by name, so they are numbered with leading zeros
+    let digits = images.len().to_string().len().max(3);
+    for (page_index, image) in images.iter().enumerate() {
+        let extension = get_extension_from_filename(image).unwrap_or("png");
+        zip.start_file(
+            format!("{:0digits$}.{}", page_index, extension, digits = digits),
+            options,
+        )?;
+        zip.write_all(
+            &fs::read(image).wrap_err_with(|| format!("could not read {}", image.display()))?,
+        )?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// The `ComicInfo.xml` of a book titled `title` with `page_count` pages, the
+/// cover being the first
+pub fn comic_info(
+    metadata: &BookMetadata,
+    title: &str,
+    page_count: usize,
+    direction: ReadingDirection,
+) -> String {
+    let mut fields: Vec<(&str, String)> = vec![
+        ("Title", title.to_string()),
+        ("Series", metadata.series.clone()),
+    ];
+    if let Some(chapter) = &metadata.chapter {
+        fields.push(("Number", chapter.clone()));
+    }
+    if let Some(volume) = &metadata.volume {
+        fields.push(("Volume", volume.clone()));
+    }
+    if let Some(description) = &metadata.description {
+        fields.push(("Summary", description.clone()));
+    }
+    if let Some(year) = &metadata.year {
+        fields.push(("Year", year.clone()));
+    }
+    fields.push(("Writer", metadata.author.clone()));
+    if !metadata.subjects.is_empty() {
+        fields.push(("Tags", metadata.subjects.join(", ")));
+    }
+    fields.push((
+        "Web",
+        format!("https://mangadex.org/title/{}", metadata.mangadex_id),
+    ));
+    fields.push(("PageCount", page_count.to_string()));
+    fields.push(("LanguageISO", metadata.language.clone()));
+    fields.push((
+        "Manga",
+        String::from(match direction {
+            ReadingDirection::RightToLeft => "YesAndRightToLeft",
+            ReadingDirection::LeftToRight => "Yes",
+        }),
+    ));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    for (name, value) in fields {
+        xml.push_str(&format!(
+            "  <{0}>{1}</{0}>\n",
+            name,
+            html_escape::encode_text(&value)
+        ));
+    }
+
+    xml.push_str("  <Pages>\n");
+    for page_index in 0..page_count {
+        match page_index {
+            0 => xml.push_str("    <Page Image=\"0\" Type=\"FrontCover\" />\n"),
+            _ => xml.push_str(&format!("    <Page Image=\"{}\" />\n", page_index)),
+        }
+    }
+    xml.push_str("  </Pages>\n</ComicInfo>\n");
+
+    xml
+}
+
+#[test]
+fn cbz_holds_pages_in_order_with_comic_info() {
+    use std::io::Read;
+
+    use crate::workspace::Workspace;
+
+    let workspace = Workspace::new().unwrap();
+    let images: Vec<PathBuf> = (0..3)
+        .map(|page_index| {
+            let image = workspace.file(format!("page {}.jpg", 3 - page_index));
+            fs::write(&image, [page_index as u8]).unwrap();
+            image
+        })
+        .collect();
+
+    let metadata = BookMetadata {
+        series: String::from("Spy x Family"),
+        author: String::from("KindleMangaReader"),
+        series_index: Some(String::from("12")),
+        description: Some(String::from("Twilight & Yor")),
+        subjects: vec![String::from("Action"), String::from("Comedy")],
+        year: Some(String::from("2019")),
+        language: String::from("en"),
+        mangadex_id: String::from("6b958848"),
+        chapter_ids: vec![String::from("a1")],
+        updated_at: None,
+        volume: Some(String::from("2")),
+        chapter: Some(String::from("12")),
+        part: None,
+    };
+    let comic_info = comic_info(
+        &metadata,
+        "Spy x Family volume 2 chapter 12",
+        images.len(),
+        ReadingDirection::RightToLeft,
+    );
+    assert!(comic_info.contains("<Number>12</Number>\n  <Volume>2</Volume>"));
+    assert!(comic_info.contains("<Summary>Twilight &amp; Yor</Summary>"));
+    assert!(comic_info.contains("<Tags>Action, Comedy</Tags>"));
+    assert!(comic_info.contains("<PageCount>3</PageCount>"));
+    assert!(comic_info.contains("<Manga>YesAndRightToLeft</Manga>"));
+    assert!(comic_info.contains("<Page Image=\"0\" Type=\"FrontCover\" />"));
+
+    let cbz_path = workspace.file("book.cbz");
+    write(&images, &comic_info, &cbz_path, None).unwrap();
+
+    let mut archive = libzip::ZipArchive::new(fs::File::open(&cbz_path).unwrap()).unwrap();
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec!["000.jpg", "001.jpg", "002.jpg", "ComicInfo.xml"]
+    );
+    for page_index in 0..3u8 {
+        let mut bytes: Vec<u8> = Vec::new();
+        archive
+            .by_name(&format!("{:03}.jpg", page_index))
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, vec![page_index]);
+    }
+}
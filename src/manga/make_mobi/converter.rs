@@ -0,0 +1,504 @@
+//! Turns the validated EPUB into the book that is delivered, either with the
+//! built-in KF8 writer or with an external tool such as kindlegen or Calibre's
+//! `ebook-convert`.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::kf8;
+use crate::manga::settings::{Compression, ConverterBackend, ConverterSettings, OutputFormat};
+use crate::paths;
+
+/// How often a running tool is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// ─── Results ─────────────────────────────────────────────────────────────────
+
+/// What a converter had to say about a book it did convert
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionLog {
+    pub warnings: Vec<String>,
+}
+
+/// Why a converter did not produce a book
+#[derive(Debug)]
+pub enum ConversionError {
+    /// The tool is neither at the configured path nor anywhere it is looked for
+    NotFound { converter: &'static str },
+    /// The tool is there but could not be started
+    Spawn {
+        converter: &'static str,
+        error: io::Error,
+    },
+    /// The tool was stopped after running for longer than `timeout`
+    TimedOut {
+        converter: &'static str,
+        timeout: Duration,
+    },
+    /// The tool ran and failed, `status` being its exit code if it had one
+    Failed {
+        converter: &'static str,
+        status: Option<i32>,
+        errors: Vec<String>,
+    },
+}
+
+impl Error for ConversionError {}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionError::NotFound { converter } => {
+                write!(
+                    f,
+                    "{} is not installed, put it on PATH or in {}",
+                    converter,
+                    paths::tools_folder().display()
+                )
+            }
+            ConversionError::Spawn { converter, error } => {
+                write!(f, "{} could not be started: {}", converter, error)
+            }
+            ConversionError::TimedOut { converter, timeout } => {
+                write!(
+                    f,
+                    "{} was stopped after {} seconds",
+                    converter,
+                    timeout.as_secs()
+                )
+            }
+            ConversionError::Failed {
+                converter,
+                status,
+                errors,
+            } => {
+                match status {
+                    Some(status) => write!(f, "{} failed with exit code {}", converter, status)?,
+                    None => write!(f, "{} failed", converter)?,
+                }
+                for error in errors {
+                    write!(f, "\n    {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// ─── Converters ──────────────────────────────────────────────────────────────
+
+pub trait Converter {
+    /// Name shown to the user when the converter fails or warns
+    fn name(&self) -> &'static str;
+
+    /// Extension of the books it writes
+    fn extension(&self) -> &'static str;
+
+    /// Converts the EPUB at `epub_path` to a book at `output_path`
+    fn convert(
+        &self,
+        epub_path: &Path,
+        output_path: &Path,
+    ) -> Result<ConversionLog, ConversionError>;
+}
+
+/// The converter `settings` ask for, or the passthrough when the book is
+/// wanted as an EPUB
+pub fn from_settings(
+    output_format: OutputFormat,
+    settings: &ConverterSettings,
+) -> Box<dyn Converter> {
+    if output_format == OutputFormat::Epub {
+        return Box::new(Passthrough);
+    }
+
+    let timeout = Duration::from_secs(settings.timeout_secs);
+
+    match settings.backend {
+        ConverterBackend::Native => Box::new(NativeKf8),
+        ConverterBackend::Kindlegen => Box::new(Kindlegen {
+            path: settings.tool_path.clone(),
+            compression: settings.compression,
+            timeout,
+        }),
+        ConverterBackend::EbookConvert => Box::new(EbookConvert {
+            path: settings.tool_path.clone(),
+            compression: settings.compression,
+            timeout,
+        }),
+    }
+}
+
+/// Keeps the EPUB as it is
+pub struct Passthrough;
+
+impl Converter for Passthrough {
+    fn name(&self) -> &'static str {
+        "no conversion"
+    }
+
+    fn extension(&self) -> &'static str {
+        "epub"
+    }
+
+    fn convert(
+        &self,
+        epub_path: &Path,
+        output_path: &Path,
+    ) -> Result<ConversionLog, ConversionError> {
+        if epub_path != output_path {
+            fs::copy(epub_path, output_path).map_err(|error| ConversionError::Failed {
+                converter: self.name(),
+                status: None,
+                errors: vec![error.to_string()],
+            })?;
+        }
+        Ok(ConversionLog::default())
+    }
+}
+
+/// The built-in KF8 writer, which never compresses the text
+pub struct NativeKf8;
+
+impl Converter for NativeKf8 {
+    fn name(&self) -> &'static str {
+        "KF8 writer"
+    }
+
+    fn extension(&self) -> &'static str {
+        "azw3"
+    }
+
+    fn convert(
+        &self,
+        epub_path: &Path,
+        output_path: &Path,
+    ) -> Result<ConversionLog, ConversionError> {
+        kf8::convert(epub_path, output_path).map_err(|error| ConversionError::Failed {
+            converter: self.name(),
+            status: None,
+            errors: error.chain().map(|cause| cause.to_string()).collect(),
+        })?;
+        Ok(ConversionLog::default())
+    }
+}
+
+/// Amazon's kindlegen, which writes a MOBI holding both a MOBI 7 and a KF8 book
+pub struct Kindlegen {
+    /// Where kindlegen is, if not on `PATH`
+    pub path: Option<PathBuf>,
+    pub compression: Compression,
+    pub timeout: Duration,
+}
+
+impl Converter for Kindlegen {
+    fn name(&self) -> &'static str {
+        "kindlegen"
+    }
+
+    fn extension(&self) -> &'static str {
+        "mobi"
+    }
+
+    fn convert(
+        &self,
+        epub_path: &Path,
+        output_path: &Path,
+    ) -> Result<ConversionLog, ConversionError> {
+        let program =
+            find_tool(self.path.as_deref(), "kindlegen").ok_or(ConversionError::NotFound {
+                converter: self.name(),
+            })?;
+
+        let level = match self.compression {
+            Compression::None => "-c0",
+            Compression::Standard => "-c1",
+            Compression::Huffdic => "-c2",
+        };
+
+        // kindlegen only takes a file name, and writes next to the EPUB
+        let output_name = output_path.file_name().unwrap_or_default();
+        let mut command = Command::new(program);
+        command.arg(epub_path).arg(level).arg("-o").arg(output_name);
+
+        let output = run(self.name(), command, self.timeout)?;
+        let (warnings, errors) = kindlegen_messages(&output.stdout);
+
+        // 0 is success, 1 success with warnings, anything else a failure
+        if !matches!(output.status, Some(0) | Some(1)) {
+            return Err(ConversionError::Failed {
+                converter: self.name(),
+                status: output.status,
+                errors: or_tail(errors, &output),
+            });
+        }
+
+        let written = epub_path.with_file_name(output_name);
+        if written != output_path {
+            fs::rename(&written, output_path).map_err(|error| ConversionError::Failed {
+                converter: self.name(),
+                status: output.status,
+                errors: vec![error.to_string()],
+            })?;
+        }
+
+        Ok(ConversionLog { warnings })
+    }
+}
+
+/// Calibre's `ebook-convert`, which picks the format from the output's extension
+pub struct EbookConvert {
+    /// Where ebook-convert is, if not on `PATH`
+    pub path: Option<PathBuf>,
+    pub compression: Compression,
+    pub timeout: Duration,
+}
+
+impl Converter for EbookConvert {
+    fn name(&self) -> &'static str {
+        "ebook-convert"
+    }
+
+    fn extension(&self) -> &'static str {
+        "azw3"
+    }
+
+    fn convert(
+        &self,
+        epub_path: &Path,
+        output_path: &Path,
+    ) -> Result<ConversionLog, ConversionError> {
+        let program =
+            find_tool(self.path.as_deref(), "ebook-convert").ok_or(ConversionError::NotFound {
+                converter: self.name(),
+            })?;
+
+        let mut command = Command::new(program);
+        command.arg(epub_path).arg(output_path);
+        // Calibre only knows whether to compress, not how much
+        if self.compression == Compression::None {
+            command.arg("--dont-compress");
+        }
+
+        let output = run(self.name(), command, self.timeout)?;
+        let (warnings, errors) = ebook_convert_messages(&output.stdout, &output.stderr);
+
+        if output.status != Some(0) || !output_path.exists() {
+            return Err(ConversionError::Failed {
+                converter: self.name(),
+                status: output.status,
+                errors: or_tail(errors, &output),
+            });
+        }
+
+        Ok(ConversionLog { warnings })
+    }
+}
+
+// ─── Running Tools ───────────────────────────────────────────────────────────
+
+/// `tool` at `configured`, or the first one on `PATH`, in the tools folder,
+/// next to the executable or where its installer puts it
+pub fn find_tool(configured: Option<&Path>, tool: &str) -> Option<PathBuf> {
+    if let Some(path) = configured {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let name = format!("{}{}", tool, env::consts::EXE_SUFFIX);
+    env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .chain([paths::tools_folder()])
+        .chain(paths::executable_folder())
+        .chain(install_folders(tool))
+        .map(|folder| folder.join(&name))
+        .find(|path| path.is_file())
+}
+
+/// Where the installers of `tool` put it, which they do not always add to `PATH`
+fn install_folders(tool: &str) -> Vec<PathBuf> {
+    match (env::consts::OS, tool) {
+        ("macos", "kindlegen") => {
+            vec![PathBuf::from(
+                "/Applications/Kindle Previewer 3.app/Contents/lib/fc/bin",
+            )]
+        }
+        ("macos", "ebook-convert") => {
+            vec![PathBuf::from("/Applications/calibre.app/Contents/MacOS")]
+        }
+        ("windows", "kindlegen") => env::var_os("LOCALAPPDATA")
+            .map(|folder| {
+                PathBuf::from(folder)
+                    .join("Amazon")
+                    .join("Kindle Previewer 3")
+                    .join("lib")
+                    .join("fc")
+                    .join("bin")
+            })
+            .into_iter()
+            .collect(),
+        ("windows", "ebook-convert") => env::var_os("ProgramFiles")
+            .map(|folder| PathBuf::from(folder).join("Calibre2"))
+            .into_iter()
+            .collect(),
+        ("linux", "ebook-convert") => vec![PathBuf::from("/opt/calibre")],
+        _ => Vec::new(),
+    }
+}
+
+/// What a tool printed and how it exited
+#[derive(Debug, Default)]
+struct Output {
+    status: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs `command`, killing it once it has run for `timeout`
+fn run(
+    converter: &'static str,
+    mut command: Command,
+    timeout: Duration,
+) -> Result<Output, ConversionError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| ConversionError::Spawn { converter, error })?;
+
+    // Read both pipes while waiting, so a chatty tool does not block on them
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    // Whatever the tool started may still hold the pipes open after it is
+    // killed, so the readers are left to finish on their own
+    let status =
+        wait(&mut child, timeout).ok_or(ConversionError::TimedOut { converter, timeout })?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Exit code of `child`, or `None` if it had to be killed. The inner option is
+/// empty when the child was ended by a signal.
+fn wait(child: &mut Child, timeout: Duration) -> Option<Option<i32>> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status.code()),
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes: Vec<u8> = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// `errors`, or the last lines the tool printed if it named none
+fn or_tail(errors: Vec<String>, output: &Output) -> Vec<String> {
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    let printed = if output.stderr.trim().is_empty() {
+        &output.stdout
+    } else {
+        &output.stderr
+    };
+    let lines: Vec<String> = printed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    lines[lines.len().saturating_sub(5)..].to_vec()
+}
+
+// ─── Parsing Messages ────────────────────────────────────────────────────────
+
+/// Warnings and errors in kindlegen's output, which look like
+/// `Warning(prcgen):W14001: Hyperlink not resolved`
+fn kindlegen_messages(stdout: &str) -> (Vec<String>, Vec<String>) {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for line in stdout.lines().map(str::trim) {
+        if line.starts_with("Warning(") {
+            warnings.push(line.to_string());
+        } else if line.starts_with("Error(") {
+            errors.push(line.to_string());
+        }
+    }
+
+    (warnings, errors)
+}
+
+/// Warnings and errors in ebook-convert's output. Calibre prefixes warnings
+/// with `WARNING:` and ends a failure with the exception that caused it.
+fn ebook_convert_messages(stdout: &str, stderr: &str) -> (Vec<String>, Vec<String>) {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for line in stdout.lines().chain(stderr.lines()).map(str::trim) {
+        if let Some(warning) = line.strip_prefix("WARNING:") {
+            warnings.push(warning.trim().to_string());
+        } else if line.starts_with("ERROR:") || line.contains("Error: ") {
+            errors.push(line.to_string());
+        }
+    }
+
+    (warnings, errors)
+}
+
+#[test]
+fn tool_messages_are_sorted_into_warnings_and_errors() {
+    let (warnings, errors) = kindlegen_messages(
+        "*************************************************************\n\
+         Amazon kindlegen(Linux) V2.9 build 1028-0897292\n\
+         Info(prcgen):I1047: Added metadata dc:Title        \"Spy x Family\"\n\
+         Warning(prcgen):W14016: Cover not specified\n\
+         Error(prcgen):E21018: Enhanced Mobipocket build failed\n",
+    );
+    assert_eq!(
+        warnings,
+        vec!["Warning(prcgen):W14016: Cover not specified"]
+    );
+    assert_eq!(
+        errors,
+        vec!["Error(prcgen):E21018: Enhanced Mobipocket build failed"]
+    );
+
+    let (warnings, errors) = ebook_convert_messages(
+        "Converting input to HTML...\nWARNING: Cover image is too large\n",
+        "Traceback (most recent call last):\n  File \"convert.py\", line 1\nValueError: No such file\n",
+    );
+    assert_eq!(warnings, vec!["Cover image is too large"]);
+    assert_eq!(errors, vec!["ValueError: No such file"]);
+
+    assert!(find_tool(Some(Path::new("does/not/exist")), "kindlegen").is_none());
+}
@@ -4,9 +4,9 @@
 
 use crate::manga::make_mobi::epub_builder::templates;
 use crate::manga::make_mobi::epub_builder::toc::{Toc, TocElement};
-use crate::manga::make_mobi::epub_builder::zip::Zip;
-use crate::manga::make_mobi::epub_builder::ReferenceType;
+use crate::manga::make_mobi::epub_builder::zip::{FinishZip, Zip};
 use crate::manga::make_mobi::epub_builder::{common, EpubContent};
+use crate::manga::make_mobi::epub_builder::{PageSpread, ReferenceType};
 
 use std::io;
 use std::io::Read;
@@ -27,6 +27,95 @@ pub enum EpubVersion {
     V30,
 }
 
+/// The direction pages are turned in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PageProgression {
+    /// Left to right, the default of most readers
+    Ltr,
+    /// Right to left, as in Japanese manga
+    Rtl,
+}
+
+impl PageProgression {
+    /// Value of the spine `page-progression-direction` attribute
+    fn direction(self) -> &'static str {
+        match self {
+            PageProgression::Ltr => "ltr",
+            PageProgression::Rtl => "rtl",
+        }
+    }
+
+    /// Value of the `primary-writing-mode` meta read by Kindle
+    fn writing_mode(self) -> &'static str {
+        match self {
+            PageProgression::Ltr => "horizontal-lr",
+            PageProgression::Rtl => "horizontal-rl",
+        }
+    }
+}
+
+/// Fixed-layout rendering of a comic: every page is an image filling the screen.
+///
+/// The reading direction is not part of it, it is set with
+/// [`EpubBuilder::page_progression`] and written next to these metas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedLayout {
+    /// Width in pixels of the screen the pages were made for
+    pub width: u32,
+    /// Height in pixels of the screen the pages were made for
+    pub height: u32,
+    /// Whether Kindle may magnify regions of a page when it is tapped
+    pub region_magnification: bool,
+}
+
+impl FixedLayout {
+    /// Orientation the pages are locked to, the one of the screen they were
+    /// made for
+    fn orientation(&self) -> &'static str {
+        if self.width > self.height {
+            "landscape"
+        } else {
+            "portrait"
+        }
+    }
+
+    /// The `<meta>` elements describing the layout in the package document
+    fn metas(&self, version: EpubVersion) -> Vec<String> {
+        let mut metas: Vec<String> = Vec::new();
+
+        // EPUB 3 readers only look at the rendition properties
+        if version == EpubVersion::V30 {
+            metas.push("<meta property=\"rendition:layout\">pre-paginated</meta>".to_string());
+            metas.push(format!(
+                "<meta property=\"rendition:orientation\">{}</meta>",
+                self.orientation()
+            ));
+            metas.push("<meta property=\"rendition:spread\">landscape</meta>".to_string());
+        }
+
+        // Kindle only renders comics full-screen and without margins with these
+        let kindle = [
+            ("fixed-layout", String::from("true")),
+            (
+                "original-resolution",
+                format!("{}x{}", self.width, self.height),
+            ),
+            ("book-type", String::from("comic")),
+            ("orientation-lock", String::from(self.orientation())),
+            ("region-mag", self.region_magnification.to_string()),
+            ("zero-gutter", String::from("true")),
+            ("zero-margin", String::from("true")),
+            ("ke-border-color", String::from("#FFFFFF")),
+            ("ke-border-width", String::from("0")),
+        ];
+        for (name, content) in kindle {
+            metas.push(format!("<meta name=\"{}\" content=\"{}\"/>", name, content));
+        }
+
+        metas
+    }
+}
+
 /// EPUB Metadata
 #[derive(Debug)]
 struct Metadata {
@@ -38,6 +127,10 @@ struct Metadata {
     pub description: Vec<String>,
     pub subject: Vec<String>,
     pub license: Option<String>,
+    pub date: Option<String>,
+    pub series: Option<String>,
+    pub series_index: Option<String>,
+    pub identifier: Vec<(String, String)>,
 }
 
 impl Metadata {
@@ -52,6 +145,10 @@ impl Metadata {
             description: vec![],
             subject: vec![],
             license: None,
+            date: None,
+            series: None,
+            series_index: None,
+            identifier: vec![],
         }
     }
 }
@@ -64,6 +161,7 @@ struct Content {
     pub itemref: bool,
     pub cover: bool,
     pub reftype: Option<ReferenceType>,
+    pub spread: Option<PageSpread>,
     pub title: String,
 }
 
@@ -80,6 +178,7 @@ impl Content {
             itemref: false,
             cover: false,
             reftype: None,
+            spread: None,
             title: String::new(),
         }
     }
@@ -91,9 +190,9 @@ impl Content {
 /// a wrapper to zip files; then you add content to it, and finally you generate
 /// the EPUB file by calling the `generate` method.
 ///
-/// ```
-/// use epub_builder::EpubBuilder;
-/// use epub_builder::ZipCommand;
+/// ```no_run
+/// use kindle_manga_reader_v2::manga::epub_builder::EpubBuilder;
+/// use kindle_manga_reader_v2::manga::epub_builder::ZipCommand;
 /// use std::io;
 ///
 /// // "Empty" EPUB file
@@ -105,6 +204,10 @@ impl Content {
 #[derive(Debug)]
 pub struct EpubBuilder<Z: Zip> {
     version: EpubVersion,
+    page_progression: Option<PageProgression>,
+    fixed_layout: Option<FixedLayout>,
+    uuid: Option<uuid::Uuid>,
+    modified_date: Option<chrono::DateTime<chrono::Utc>>,
     zip: Z,
     files: Vec<Content>,
     metadata: Metadata,
@@ -118,6 +221,10 @@ impl<Z: Zip> EpubBuilder<Z> {
     pub fn new(zip: Z) -> Result<EpubBuilder<Z>> {
         let mut epub = EpubBuilder {
             version: EpubVersion::V20,
+            page_progression: None,
+            fixed_layout: None,
+            uuid: None,
+            modified_date: None,
             zip,
             files: vec![],
             metadata: Metadata::new(),
@@ -127,10 +234,10 @@ impl<Z: Zip> EpubBuilder<Z> {
         };
 
         epub.zip
-            .write_file("META-INF/container.xml", templates::CONTAINER)?;
+            .write_file("META-INF/container.xml", *templates::CONTAINER)?;
         epub.zip.write_file(
             "META-INF/com.apple.ibooks.display-options.xml",
-            templates::IBOOKS,
+            *templates::IBOOKS,
         )?;
 
         Ok(epub)
@@ -147,6 +254,41 @@ impl<Z: Zip> EpubBuilder<Z> {
         self
     }
 
+    /// Set the direction pages are turned in (default: unset, left to the reader)
+    ///
+    /// This writes the spine `page-progression-direction` and the matching
+    /// `primary-writing-mode` meta.
+    pub fn page_progression(&mut self, page_progression: PageProgression) -> &mut Self {
+        self.page_progression = Some(page_progression);
+        self
+    }
+
+    /// Make the book fixed-layout (default: reflowable)
+    ///
+    /// This writes the EPUB 3 rendition properties and the metadata Kindle needs
+    /// to show a comic full-screen.
+    pub fn fixed_layout(&mut self, fixed_layout: FixedLayout) -> &mut Self {
+        self.fixed_layout = Some(fixed_layout);
+        self
+    }
+
+    /// Set the unique identifier of the book (default: a random one)
+    ///
+    /// Readers use it to tell books apart, so a rebuilt book keeps its place
+    /// in the library only if it has the same identifier.
+    pub fn set_uuid(&mut self, uuid: uuid::Uuid) -> &mut Self {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    /// Set the date the book was last modified (default: the time it is generated)
+    ///
+    /// It is also the publication date when no `date` metadata is set.
+    pub fn set_modified_date(&mut self, date: chrono::DateTime<chrono::Utc>) -> &mut Self {
+        self.modified_date = Some(date);
+        self
+    }
+
     /// Set some EPUB metadata
     ///
     /// For most metadata, this function will replace the existing metadata, but for subject, cteator and identifier who
@@ -163,8 +305,11 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// * `toc_name`: the name to use for table of contents (by default, "Table of Contents");
     /// * `subject`;
     /// * `description`;
-    /// * `license`.
-
+    /// * `license`;
+    /// * `date`: publication date, e.g. just the year (by default, the date the book is generated);
+    /// * `series`: name of the series the book belongs to;
+    /// * `series_index`: position of the book in its series;
+    /// * `identifier`: an extra identifier, as `scheme:value`.
     pub fn metadata<S1, S2>(&mut self, key: S1, value: S2) -> Result<&mut Self>
     where
         S1: AsRef<str>,
@@ -199,6 +344,23 @@ impl<Z: Zip> EpubBuilder<Z> {
                 }
             }
             "license" => self.metadata.license = Some(value.into()),
+            "date" => self.metadata.date = Some(value.into()),
+            "series" => self.metadata.series = Some(value.into()),
+            "series_index" => self.metadata.series_index = Some(value.into()),
+            "identifier" => {
+                let value = value.into();
+                if value.is_empty() {
+                    self.metadata.identifier = vec![];
+                } else {
+                    match value.split_once(':') {
+                        Some((scheme, id)) => self
+                            .metadata
+                            .identifier
+                            .push((scheme.to_string(), id.to_string())),
+                        None => bail!("identifier '{}' is not 'scheme:value'", value),
+                    }
+                }
+            }
             "toc_name" => self.metadata.toc_name = value.into(),
             s => bail!("invalid metadata '{}'", s),
         }
@@ -259,8 +421,8 @@ impl<Z: Zip> EpubBuilder<Z> {
         P: AsRef<Path>,
         S: Into<String>,
     {
-        self.zip
-            .write_file(Path::new("OEBPS").join(path.as_ref()), content)?;
+        let mime_type = mime_type.into();
+        self.write_resource(path.as_ref(), content, &mime_type)?;
         log::debug!("Add resource: {:?}", path.as_ref().display());
         self.files.push(Content::new(
             format!("{}", path.as_ref().display()),
@@ -285,8 +447,8 @@ impl<Z: Zip> EpubBuilder<Z> {
         P: AsRef<Path>,
         S: Into<String>,
     {
-        self.zip
-            .write_file(Path::new("OEBPS").join(path.as_ref()), content)?;
+        let mime_type = mime_type.into();
+        self.write_resource(path.as_ref(), content, &mime_type)?;
         let mut file = Content::new(format!("{}", path.as_ref().display()), mime_type);
         file.cover = true;
         self.files.push(file);
@@ -298,7 +460,7 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// # Examples
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # use kindle_manga_reader_v2::manga::epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
     /// let content = "Some content";
     /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
     /// // Add a chapter that won't be added to the Table of Contents
@@ -306,7 +468,7 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// ```
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent, TocElement};
+    /// # use kindle_manga_reader_v2::manga::epub_builder::{EpubBuilder, ZipLibrary, EpubContent, TocElement};
     /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
     /// # let content = "Some content";
     /// // Sets the title of a chapter so it is added to the Table of contents
@@ -317,7 +479,7 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// ```
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # use kindle_manga_reader_v2::manga::epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
     /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
     /// # let content = "Some content";
     /// // Add a section, by setting the level to 2 (instead of the default value 1)
@@ -341,6 +503,7 @@ impl<Z: Zip> EpubBuilder<Z> {
         let mut file = Content::new(content.toc.url.as_str(), "application/xhtml+xml");
         file.itemref = true;
         file.reftype = content.reftype;
+        file.spread = content.spread;
         if file.reftype.is_some() {
             file.title = content.toc.title.clone();
         }
@@ -351,18 +514,46 @@ impl<Z: Zip> EpubBuilder<Z> {
         Ok(self)
     }
 
+    /// Writes a resource to the archive, storing formats that are already
+    /// compressed as they are instead of deflating them again
+    fn write_resource<R: Read>(&mut self, path: &Path, content: R, mime_type: &str) -> Result<()> {
+        let path = Path::new("OEBPS").join(path);
+        match mime_type {
+            "image/jpeg" | "image/png" | "image/gif" | "image/webp" => {
+                self.zip.write_stored_file(path, content)
+            }
+            _ => self.zip.write_file(path, content),
+        }
+    }
+
     /// Generate the EPUB file and write it to the writer
     ///
     /// # Example
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// # use kindle_manga_reader_v2::manga::epub_builder::{EpubBuilder, ZipLibrary};
     /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
     /// // Write the EPUB file into a Vec<u8>
     /// let mut epub: Vec<u8> = vec!();
     /// builder.generate(&mut epub).unwrap();
     /// ```
     pub fn generate<W: io::Write>(&mut self, to: W) -> Result<()> {
+        self.write_package()?;
+        self.zip.generate(to)?;
+        Ok(())
+    }
+
+    /// Finish the EPUB file, for zips that write it to its file as it is built
+    pub fn finish(&mut self) -> Result<()>
+    where
+        Z: FinishZip,
+    {
+        self.write_package()?;
+        self.zip.finish()
+    }
+
+    /// Write the package documents, which list everything added so far
+    fn write_package(&mut self) -> Result<()> {
         // If no styleesheet was provided, generate a dummy one
         if !self.stylesheet {
             self.stylesheet(b"".as_ref())?;
@@ -382,7 +573,6 @@ impl<Z: Zip> EpubBuilder<Z> {
             self.zip.write_file("OEBPS/toc.xhtml", &*bytes)?;
         }
 
-        self.zip.generate(to)?;
         Ok(())
     }
 
@@ -391,16 +581,85 @@ impl<Z: Zip> EpubBuilder<Z> {
         log::debug!("render_opf...");
         let mut optional: Vec<String> = Vec::new();
         for desc in &self.metadata.description {
-            optional.push(format!("<dc:description>{}</dc:description>", desc));
+            optional.push(format!(
+                "<dc:description>{}</dc:description>",
+                html_escape::encode_text(desc)
+            ));
         }
         for subject in &self.metadata.subject {
-            optional.push(format!("<dc:subject>{}</dc:subject>", subject));
+            optional.push(format!(
+                "<dc:subject>{}</dc:subject>",
+                html_escape::encode_text(subject)
+            ));
+        }
+        for (scheme, id) in &self.metadata.identifier {
+            optional.push(match self.version {
+                EpubVersion::V20 => format!(
+                    "<dc:identifier opf:scheme=\"{}\">{}</dc:identifier>",
+                    common::escape_quote(scheme),
+                    html_escape::encode_text(id)
+                ),
+                EpubVersion::V30 => format!(
+                    "<dc:identifier>{}:{}</dc:identifier>",
+                    html_escape::encode_text(scheme),
+                    html_escape::encode_text(id)
+                ),
+            });
+        }
+        if let Some(ref series) = self.metadata.series {
+            // calibre reads its own meta from both versions, EPUB 3 readers the collection
+            optional.push(format!(
+                "<meta name=\"calibre:series\" content=\"{}\"/>",
+                common::escape_quote(html_escape::encode_text(series))
+            ));
+            if let Some(ref index) = self.metadata.series_index {
+                optional.push(format!(
+                    "<meta name=\"calibre:series_index\" content=\"{}\"/>",
+                    common::escape_quote(index)
+                ));
+            }
+            if self.version == EpubVersion::V30 {
+                optional.push(format!(
+                    "<meta property=\"belongs-to-collection\" id=\"series\">{}</meta>",
+                    html_escape::encode_text(series)
+                ));
+                optional.push(
+                    "<meta refines=\"#series\" property=\"collection-type\">series</meta>"
+                        .to_string(),
+                );
+                if let Some(ref index) = self.metadata.series_index {
+                    optional.push(format!(
+                        "<meta refines=\"#series\" property=\"group-position\">{}</meta>",
+                        html_escape::encode_text(index)
+                    ));
+                }
+            }
         }
         if let Some(ref rights) = self.metadata.license {
             optional.push(format!("<dc:rights>{}</dc:rights>", rights));
         }
-        let date = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
-        let uuid = uuid::fmt::Urn::from_uuid(uuid::Uuid::new_v4()).to_string();
+        if let Some(ref fixed_layout) = self.fixed_layout {
+            optional.extend(fixed_layout.metas(self.version));
+        }
+        if let Some(page_progression) = self.page_progression {
+            optional.push(format!(
+                "<meta name=\"primary-writing-mode\" content=\"{}\"/>",
+                page_progression.writing_mode()
+            ));
+        }
+        let spine_direction = match self.page_progression {
+            Some(page_progression) => format!(
+                " page-progression-direction=\"{}\"",
+                page_progression.direction()
+            ),
+            None => String::new(),
+        };
+        let date = self
+            .modified_date
+            .unwrap_or_else(chrono::Utc::now)
+            .format("%Y-%m-%dT%H:%M:%SZ");
+        let uuid =
+            uuid::fmt::Urn::from_uuid(self.uuid.unwrap_or_else(uuid::Uuid::new_v4)).to_string();
 
         let mut items: Vec<String> = Vec::new();
         let mut itemrefs: Vec<String> = Vec::new();
@@ -430,7 +689,17 @@ impl<Z: Zip> EpubBuilder<Z> {
                 href = content.file.replace('\\', "/")
             ));
             if content.itemref {
-                itemrefs.push(format!("<itemref idref=\"{id}\"/>", id = id));
+                let properties = match (self.version, content.spread) {
+                    (EpubVersion::V30, Some(spread)) => {
+                        format!(" properties=\"{}\"", spread.property())
+                    }
+                    _ => String::new(),
+                };
+                itemrefs.push(format!(
+                    "<itemref idref=\"{id}\"{properties}/>",
+                    id = id,
+                    properties = properties
+                ));
             }
             if let Some(reftype) = content.reftype {
                 use crate::manga::make_mobi::epub_builder::ReferenceType::*;
@@ -483,7 +752,15 @@ impl<Z: Zip> EpubBuilder<Z> {
             .insert_str("optional", common::indent(optional.join("\n"), 2))
             .insert_str("items", common::indent(items.join("\n"), 2))
             .insert_str("itemrefs", common::indent(itemrefs.join("\n"), 2))
+            .insert_str("spine_direction", spine_direction)
             .insert_str("date", date.to_string())
+            .insert_str(
+                "publication_date",
+                self.metadata
+                    .date
+                    .clone()
+                    .unwrap_or_else(|| date.to_string()),
+            )
             .insert_str("uuid", uuid)
             .insert_str("guide", common::indent(guide.join("\n"), 2))
             .build();
@@ -549,7 +826,7 @@ impl<Z: Zip> EpubBuilder<Z> {
                                 {title}</a></li>",
                             reftype = reftype,
                             href = file.file,
-                            title = file.title
+                            title = html_escape::encode_text(&file.title)
                         ));
                     }
                 }
@@ -588,9 +865,9 @@ impl<Z: Zip> EpubBuilder<Z> {
 // Ordering to to look as similar as possible to the W3 Recommendation ruleset
 // Slightly more permissive, there are some that are invalid start chars, but this is ok.
 fn is_id_char(c: char) -> bool {
-    ('A'..='Z').contains(&c)
+    c.is_ascii_uppercase()
         || c == '_'
-        || ('a'..='z').contains(&c)
+        || c.is_ascii_lowercase()
         || ('\u{C0}'..='\u{D6}').contains(&c)
         || ('\u{D8}'..='\u{F6}').contains(&c)
         || ('\u{F8}'..='\u{2FF}').contains(&c)
@@ -605,7 +882,7 @@ fn is_id_char(c: char) -> bool {
         || ('\u{10000}'..='\u{EFFFF}').contains(&c)
         || c == '-'
         || c == '.'
-        || ('0'..='9').contains(&c)
+        || c.is_ascii_digit()
         || c == '\u{B7}'
         || ('\u{0300}'..='\u{036F}').contains(&c)
         || ('\u{203F}'..='\u{2040}').contains(&c)
@@ -615,3 +892,122 @@ fn is_id_char(c: char) -> bool {
 fn to_id(s: &str) -> String {
     s.replace(|c: char| !is_id_char(c), "_")
 }
+
+/// The content.opf of the EPUB `builder` generates
+#[cfg(all(test, feature = "zip-library"))]
+fn generated_opf(
+    mut builder: EpubBuilder<crate::manga::make_mobi::epub_builder::ZipLibrary>,
+) -> String {
+    let mut epub: Vec<u8> = vec![];
+    builder.generate(&mut epub).unwrap();
+
+    let mut archive = libzip::ZipArchive::new(io::Cursor::new(epub)).unwrap();
+    let mut opf = String::new();
+    archive
+        .by_name("OEBPS/content.opf")
+        .unwrap()
+        .read_to_string(&mut opf)
+        .unwrap();
+    opf
+}
+
+#[cfg(feature = "zip-library")]
+#[test]
+fn epub3_fixed_layout_package() {
+    use crate::manga::make_mobi::epub_builder::{PageSpread, ZipLibrary};
+
+    let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .page_progression(PageProgression::Rtl)
+        .fixed_layout(FixedLayout {
+            width: 1072,
+            height: 1448,
+            region_magnification: false,
+        });
+    builder
+        .add_cover_image("image-0.png", b"".as_ref(), "image/png")
+        .unwrap()
+        .add_content(
+            EpubContent::new("page_1.html", b"".as_ref())
+                .title("Page 1")
+                .spread(PageSpread::Right),
+        )
+        .unwrap()
+        .add_content(EpubContent::new("page_2.html", b"".as_ref()).spread(PageSpread::Left))
+        .unwrap();
+
+    let opf = generated_opf(builder);
+
+    assert!(opf.contains("<package version=\"3.0\""));
+    assert!(opf.contains("<spine toc=\"ncx\" page-progression-direction=\"rtl\">"));
+    assert!(opf.contains("<meta name=\"primary-writing-mode\" content=\"horizontal-rl\"/>"));
+    assert!(opf.contains("<meta property=\"rendition:layout\">pre-paginated</meta>"));
+    assert!(opf.contains("<meta name=\"original-resolution\" content=\"1072x1448\"/>"));
+    assert!(opf.contains("<meta name=\"book-type\" content=\"comic\"/>"));
+    assert!(opf.contains("<meta property=\"rendition:orientation\">portrait</meta>"));
+    assert!(opf.contains("<meta name=\"orientation-lock\" content=\"portrait\"/>"));
+    assert!(opf.contains("properties=\"nav\""));
+    assert!(opf.contains("properties=\"cover-image\""));
+    assert!(opf.contains("<itemref idref=\"page_1.html\" properties=\"page-spread-right\"/>"));
+    assert!(opf.contains("<itemref idref=\"page_2.html\" properties=\"page-spread-left\"/>"));
+}
+
+#[cfg(feature = "zip-library")]
+#[test]
+fn series_metadata() {
+    use crate::manga::make_mobi::epub_builder::ZipLibrary;
+
+    let series_builder = |version| {
+        let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+        builder.epub_version(version);
+        builder
+            .metadata("title", "Spy & Family volume 2")
+            .unwrap()
+            .metadata("series", "Spy & Family")
+            .unwrap()
+            .metadata("series_index", "2")
+            .unwrap()
+            .metadata("subject", "Comedy")
+            .unwrap()
+            .metadata("date", "2019")
+            .unwrap()
+            .metadata("identifier", "mangadex:6b958848")
+            .unwrap();
+        builder
+    };
+
+    let opf = generated_opf(series_builder(EpubVersion::V20));
+    assert!(opf.contains("<dc:title>Spy &amp; Family volume 2</dc:title>"));
+    assert!(opf.contains("<meta name=\"calibre:series\" content=\"Spy &amp; Family\"/>"));
+    assert!(opf.contains("<meta name=\"calibre:series_index\" content=\"2\"/>"));
+    assert!(opf.contains("<dc:subject>Comedy</dc:subject>"));
+    assert!(opf.contains("<dc:date>2019</dc:date>"));
+    assert!(opf.contains("<dc:identifier opf:scheme=\"mangadex\">6b958848</dc:identifier>"));
+    assert!(!opf.contains("belongs-to-collection"));
+    // Reflowable unless asked otherwise
+    assert!(!opf.contains("rendition:layout"));
+
+    let opf = generated_opf(series_builder(EpubVersion::V30));
+    assert!(opf.contains(
+        "<meta property=\"belongs-to-collection\" id=\"series\">Spy &amp; Family</meta>"
+    ));
+    assert!(opf.contains("<meta refines=\"#series\" property=\"group-position\">2</meta>"));
+    assert!(opf.contains("<dc:identifier>mangadex:6b958848</dc:identifier>"));
+}
+
+#[test]
+fn landscape_screens_lock_landscape() {
+    let layout = FixedLayout {
+        width: 1448,
+        height: 1072,
+        region_magnification: false,
+    };
+
+    let metas = layout.metas(EpubVersion::V30);
+
+    assert!(
+        metas.contains(&"<meta property=\"rendition:orientation\">landscape</meta>".to_string())
+    );
+    assert!(metas.contains(&"<meta name=\"orientation-lock\" content=\"landscape\"/>".to_string()));
+}
@@ -51,6 +51,33 @@ pub enum ReferenceType {
     Text,
 }
 
+/// Represents the side of a two-page spread a fixed-layout page is shown on.
+///
+/// Only used for EPUB 3.0, where it is written as the `page-spread-*` property
+/// of the page's spine `itemref`.
+///
+/// For more information, see https://www.w3.org/publishing/epub3/epub-packages.html#sec-page-spread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSpread {
+    /// Left page of the spread
+    Left,
+    /// Right page of the spread
+    Right,
+    /// Page shown on its own, centered across the spread
+    Center,
+}
+
+impl PageSpread {
+    /// The spine `itemref` property for this spread
+    pub fn property(self) -> &'static str {
+        match self {
+            PageSpread::Left => "page-spread-left",
+            PageSpread::Right => "page-spread-right",
+            PageSpread::Center => "rendition:page-spread-center",
+        }
+    }
+}
+
 /// Represents a XHTML file that can be added to an EPUB document.
 ///
 /// This struct is designed to be used with the `add_content` method
@@ -59,7 +86,7 @@ pub enum ReferenceType {
 /// # Example
 ///
 /// ```
-/// use epub_builder::{EpubContent, TocElement};
+/// use kindle_manga_reader_v2::manga::epub_builder::{EpubContent, TocElement};
 ///
 /// let page_content = "Some XHTML content";
 ///
@@ -79,6 +106,8 @@ pub struct EpubContent<R: Read> {
     pub content: R,
     /// Properties. See [EpubProperties](enum.EpubProperties.html)
     pub reftype: Option<ReferenceType>,
+    /// Side of the spread this page is shown on. See [PageSpread](enum.PageSpread.html)
+    pub spread: Option<PageSpread>,
 }
 
 impl<R: Read> EpubContent<R> {
@@ -91,6 +120,7 @@ impl<R: Read> EpubContent<R> {
             content,
             toc: TocElement::new(href, ""),
             reftype: None,
+            spread: None,
         }
     }
 
@@ -124,7 +154,7 @@ impl<R: Read> EpubContent<R> {
     /// Reference an item as the title page:
     ///
     /// ```
-    /// use epub_builder::{EpubContent, ReferenceType};
+    /// use kindle_manga_reader_v2::manga::epub_builder::{EpubContent, ReferenceType};
     /// let dummy = "Should be a XHTML file";
     /// let item = EpubContent::new("title.xhtml", dummy.as_bytes())
     ///      .title("Title")
@@ -134,4 +164,13 @@ impl<R: Read> EpubContent<R> {
         self.reftype = Some(reftype);
         self
     }
+
+    /// Sets the side of the spread this page is shown on
+    ///
+    /// This is only written to EPUB 3.0 packages, as the `page-spread-left` or
+    /// `page-spread-right` property of the spine item.
+    pub fn spread(mut self, spread: PageSpread) -> Self {
+        self.spread = Some(spread);
+        self
+    }
 }
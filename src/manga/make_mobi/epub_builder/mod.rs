@@ -19,13 +19,13 @@
 //!
 //! # Example
 //!
-//! ```rust
-//! use epub_builder::EpubBuilder;
-//! use epub_builder::Result;
-//! use epub_builder::ZipLibrary;
-//! use epub_builder::EpubContent;
-//! use epub_builder::ReferenceType;
-//! use epub_builder::TocElement;
+//! ```
+//! use kindle_manga_reader_v2::manga::epub_builder::EpubBuilder;
+//! use kindle_manga_reader_v2::manga::epub_builder::Result;
+//! use kindle_manga_reader_v2::manga::epub_builder::ZipLibrary;
+//! use kindle_manga_reader_v2::manga::epub_builder::EpubContent;
+//! use kindle_manga_reader_v2::manga::epub_builder::ReferenceType;
+//! use kindle_manga_reader_v2::manga::epub_builder::TocElement;
 //!
 //! use std::io::Write;
 //!
@@ -134,6 +134,8 @@ mod epub;
 mod epub_content;
 mod templates;
 mod toc;
+#[cfg(feature = "zip-library")]
+mod validate;
 mod zip;
 #[cfg(feature = "zip-command")]
 mod zip_command;
@@ -145,10 +147,17 @@ mod zip_library;
 
 pub use epub::EpubBuilder;
 pub use epub::EpubVersion;
+pub use epub::FixedLayout;
+pub use epub::PageProgression;
 pub use epub_content::EpubContent;
+pub use epub_content::PageSpread;
 pub use epub_content::ReferenceType;
 pub use toc::Toc;
 pub use toc::TocElement;
+#[cfg(feature = "zip-library")]
+pub(crate) use validate::{read_bytes, read_text, resolve, tags};
+#[cfg(feature = "zip-library")]
+pub use validate::{validate, Issue, Severity, ValidationReport};
 #[cfg(feature = "zip-command")]
 pub use zip_command::ZipCommand;
 #[cfg(feature = "zip-command")]
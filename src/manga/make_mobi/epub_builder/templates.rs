@@ -4,40 +4,41 @@
 
 use once_cell::sync::Lazy;
 
-use crate::assets::templates;
+use crate::assets::{self, Asset};
 
-pub static IBOOKS: &[u8] = templates::IBOOKS_XML;
-pub static CONTAINER: &[u8] = templates::CONTAINER_XML;
+pub static IBOOKS: Lazy<&[u8]> = Lazy::new(|| assets::bytes(Asset::IbooksXml));
+pub static CONTAINER: Lazy<&[u8]> = Lazy::new(|| assets::bytes(Asset::ContainerXml));
 
 pub static TOC_NCX: Lazy<::mustache::Template> = Lazy::new(|| {
-    ::mustache::compile_str(templates::TOC_NCX).expect("error compiling 'toc.ncx' template'")
+    ::mustache::compile_str(assets::text(Asset::TocNcx))
+        .expect("error compiling 'toc.ncx' template'")
 });
 
 pub mod v2 {
     use once_cell::sync::Lazy;
 
-    use crate::assets::templates;
+    use crate::assets::{self, Asset};
 
     pub static CONTENT_OPF: Lazy<::mustache::Template> = Lazy::new(|| {
-        ::mustache::compile_str(templates::v2::CONTENT_OPF)
+        ::mustache::compile_str(assets::text(Asset::V2ContentOpf))
             .expect("error compiling 'content.opf' (for EPUB 2.0) template")
     });
     pub static NAV_XHTML: Lazy<::mustache::Template> = Lazy::new(|| {
-        ::mustache::compile_str(templates::v2::NAV_XHTML)
+        ::mustache::compile_str(assets::text(Asset::V2NavXhtml))
             .expect("error compiling 'nav.xhtml' (for EPUB 2.0) template")
     });
 }
 pub mod v3 {
     use once_cell::sync::Lazy;
 
-    use crate::assets::templates;
+    use crate::assets::{self, Asset};
 
     pub static CONTENT_OPF: Lazy<::mustache::Template> = Lazy::new(|| {
-        ::mustache::compile_str(templates::v3::CONTENT_OPF)
+        ::mustache::compile_str(assets::text(Asset::V3ContentOpf))
             .expect("error compiling 'content.opf' (for EPUB 3.0) template")
     });
     pub static NAV_XHTML: Lazy<::mustache::Template> = Lazy::new(|| {
-        ::mustache::compile_str(templates::v3::NAV_XHTML)
+        ::mustache::compile_str(assets::text(Asset::V3NavXhtml))
             .expect("error compiling 'nav.xhtml' (for EPUB 3.0) template")
     });
 }
@@ -8,7 +8,7 @@ use crate::manga::make_mobi::epub_builder::common;
 /// # Example
 ///
 /// ```
-/// use epub_builder::TocElement;
+/// use kindle_manga_reader_v2::manga::epub_builder::TocElement;
 /// TocElement::new("chapter_1.xhtml", "Chapter 1")
 ///     .child(TocElement::new("chapter_1.xhtml#1", "Chapter 1, section 1")
 ///               .child(TocElement::new("chapter_1.xhtml#1-1", "Chapter 1, section 1, subsection 1")));
@@ -63,7 +63,7 @@ impl TocElement {
     /// # Example
     ///
     /// ```
-    /// use epub_builder::TocElement;
+    /// use kindle_manga_reader_v2::manga::epub_builder::TocElement;
     /// let elem = TocElement::new("foo.xhtml", "Foo")
     ///     .child(TocElement::new("bar.xhtml", "Bar")
     ///          .level(42));
@@ -180,7 +180,7 @@ impl TocElement {
 /// Creates a Toc, fills it, and render it to HTML:
 ///
 /// ```
-/// use epub_builder::{Toc, TocElement};
+/// use kindle_manga_reader_v2::manga::epub_builder::{Toc, TocElement};
 /// Toc::new()
 ///    // add a level-1 element
 ///    .add(TocElement::new("intro.xhtml", "Introduction"))
@@ -222,7 +222,7 @@ impl Toc {
     /// # Example
     ///
     /// ```
-    /// # use epub_builder::{Toc, TocElement};
+    /// # use kindle_manga_reader_v2::manga::epub_builder::{Toc, TocElement};
     /// let mut toc = Toc::new();
     /// // Insert an element at default level (1)
     /// toc.add(TocElement::new("chapter_1.xhtml", "Chapter 1"));
@@ -0,0 +1,549 @@
+//! Checks a generated EPUB package before it is handed to a converter, so a
+//! broken book is reported with the reason instead of failing somewhere later.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek};
+
+use eyre::{Context, Result};
+use libzip::{CompressionMethod, ZipArchive};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Bytes read from the start of an image, enough for every signature [`check_image`] knows
+const IMAGE_HEAD_LENGTH: u64 = 12;
+
+/// How bad an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The package is broken and must not be converted
+    Error,
+    /// The package works, but some readers may handle it badly
+    Warning,
+}
+
+/// A single problem found in a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Whether the issue blocks conversion
+    pub severity: Severity,
+    /// Path in the archive of the file the issue is about
+    pub file: String,
+    /// What is wrong with the file
+    pub message: String,
+}
+
+/// Everything found wrong with a package
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Issues in the order they were found
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Whether the package is broken and must not be converted
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// The issues that block conversion
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+    }
+
+    fn error<S1: Into<String>, S2: Into<String>>(&mut self, file: S1, message: S2) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            file: file.into(),
+            message: message.into(),
+        });
+    }
+
+    fn warning<S1: Into<String>, S2: Into<String>>(&mut self, file: S1, message: S2) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            file: file.into(),
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(f, "{}: {}: {}", severity, issue.file, issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// A manifest `item` of the package
+struct Item {
+    id: String,
+    /// Path in the archive, resolved against the package document
+    path: String,
+    media_type: String,
+}
+
+/// Validates the EPUB in `epub`.
+///
+/// Only a package that cannot be read as a zip at all is an `Err`; everything
+/// else wrong with it ends up in the report.
+pub fn validate<R: Read + Seek>(epub: R) -> Result<ValidationReport> {
+    let mut archive = ZipArchive::new(epub).wrap_err("epub is not a readable zip archive")?;
+    let mut report = ValidationReport::default();
+
+    check_mimetype(&mut archive, &mut report);
+
+    let container = match read_text(&mut archive, "META-INF/container.xml") {
+        Some(container) => container,
+        None => {
+            report.error("META-INF/container.xml", "missing");
+            return Ok(report);
+        }
+    };
+    check_well_formed("META-INF/container.xml", &container, &mut report);
+
+    let opf_path = match tags(&container, "rootfile")
+        .into_iter()
+        .find_map(|attributes| attributes.get("full-path").cloned())
+    {
+        Some(opf_path) => opf_path,
+        None => {
+            report.error("META-INF/container.xml", "no rootfile full-path");
+            return Ok(report);
+        }
+    };
+
+    let opf = match read_text(&mut archive, &opf_path) {
+        Some(opf) => opf,
+        None => {
+            report.error(&opf_path, "package document is missing");
+            return Ok(report);
+        }
+    };
+    check_well_formed(&opf_path, &opf, &mut report);
+
+    let items = check_manifest(&mut archive, &opf_path, &opf, &mut report);
+    check_spine(&opf_path, &opf, &items, &mut report);
+
+    for item in &items {
+        match item.media_type.as_str() {
+            "application/xhtml+xml" | "application/x-dtbncx+xml" => {
+                if let Some(text) = read_text(&mut archive, &item.path) {
+                    check_well_formed(&item.path, &text, &mut report);
+                    check_links(&item.path, &text, &items, &mut report);
+                }
+            }
+            media_type if media_type.starts_with("image/") => {
+                if let Some(head) = read_head(&mut archive, &item.path, IMAGE_HEAD_LENGTH) {
+                    check_image(item, &head, &mut report);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
+
+// ─── Checks ──────────────────────────────────────────────────────────────────
+
+fn check_mimetype<R: Read + Seek>(archive: &mut ZipArchive<R>, report: &mut ValidationReport) {
+    let mut first = match archive.by_index(0) {
+        Ok(first) => first,
+        Err(_) => {
+            report.error("mimetype", "archive is empty");
+            return;
+        }
+    };
+
+    if first.name() != "mimetype" {
+        report.error("mimetype", "must be the first file of the archive");
+        return;
+    }
+    if first.compression() != CompressionMethod::Stored {
+        report.error("mimetype", "must be stored, not compressed");
+    }
+
+    let mut content = String::new();
+    if first.read_to_string(&mut content).is_err() || content != "application/epub+zip" {
+        report.error("mimetype", "must contain exactly 'application/epub+zip'");
+    }
+}
+
+/// Checks every manifest item exists and has a unique id, returning the items
+fn check_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    opf_path: &str,
+    opf: &str,
+    report: &mut ValidationReport,
+) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    let mut ids: HashSet<String> = HashSet::new();
+
+    for attributes in tags(opf, "item") {
+        let (id, href, media_type) = match (
+            attributes.get("id"),
+            attributes.get("href"),
+            attributes.get("media-type"),
+        ) {
+            (Some(id), Some(href), Some(media_type)) => (id, href, media_type),
+            _ => {
+                report.error(opf_path, "manifest item without id, href or media-type");
+                continue;
+            }
+        };
+
+        if !ids.insert(id.clone()) {
+            report.error(opf_path, format!("manifest id '{}' is used twice", id));
+        }
+        if id.starts_with(|c: char| c.is_ascii_digit()) {
+            report.error(
+                opf_path,
+                format!("manifest id '{}' starts with a digit", id),
+            );
+        }
+
+        let path = resolve(opf_path, href);
+        if archive.by_name(&path).is_err() {
+            report.error(
+                &path,
+                format!("listed in the manifest as '{}' but missing", id),
+            );
+        }
+
+        items.push(Item {
+            id: id.clone(),
+            path,
+            media_type: media_type.clone(),
+        });
+    }
+
+    items
+}
+
+/// Checks the spine only refers to manifest items, and the ncx it names exists
+fn check_spine(opf_path: &str, opf: &str, items: &[Item], report: &mut ValidationReport) {
+    let known = |id: &str| items.iter().any(|item| item.id == id);
+
+    for attributes in tags(opf, "spine") {
+        if let Some(toc) = attributes.get("toc") {
+            if !known(toc) {
+                report.error(
+                    opf_path,
+                    format!("spine toc '{}' is not in the manifest", toc),
+                );
+            }
+        }
+    }
+
+    let itemrefs = tags(opf, "itemref");
+    if itemrefs.is_empty() {
+        report.error(opf_path, "spine is empty");
+    }
+
+    for attributes in itemrefs {
+        match attributes.get("idref") {
+            Some(idref) if !known(idref) => {
+                report.error(
+                    opf_path,
+                    format!("spine itemref '{}' is not in the manifest", idref),
+                );
+            }
+            Some(_) => {}
+            None => report.error(opf_path, "spine itemref without idref"),
+        }
+    }
+}
+
+/// Checks the links of a content, nav or ncx document point into the manifest
+fn check_links(path: &str, text: &str, items: &[Item], report: &mut ValidationReport) {
+    let links = tags(text, "content")
+        .into_iter()
+        .filter_map(|attributes| attributes.get("src").cloned())
+        .chain(
+            tags(text, "a")
+                .into_iter()
+                .filter_map(|attributes| attributes.get("href").cloned()),
+        )
+        .chain(
+            tags(text, "img")
+                .into_iter()
+                .filter_map(|attributes| attributes.get("src").cloned()),
+        )
+        .chain(
+            tags(text, "image")
+                .into_iter()
+                .filter_map(|attributes| attributes.get("xlink:href").cloned()),
+        );
+
+    for link in links {
+        let target = link.split('#').next().unwrap_or_default();
+        if target.is_empty() || target.contains("://") {
+            continue;
+        }
+
+        let target = resolve(path, target);
+        if !items.iter().any(|item| item.path == target) {
+            report.error(
+                path,
+                format!("links to '{}', which is not in the manifest", link),
+            );
+        }
+    }
+}
+
+/// Checks the first bytes of an image are those of the type the manifest
+/// says it is
+fn check_image(item: &Item, bytes: &[u8], report: &mut ValidationReport) {
+    let actual = if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        report.warning(&item.path, "not an image type that could be recognised");
+        return;
+    };
+
+    if actual != item.media_type {
+        report.error(
+            &item.path,
+            format!("listed as {} but is {}", item.media_type, actual),
+        );
+    }
+}
+
+/// Checks every element of an XML document is closed in the right order.
+///
+/// This is not a full XML parser, but catches the mistakes templates make:
+/// unclosed or misnested tags and stray `<` or `&`.
+fn check_well_formed(path: &str, text: &str, report: &mut ValidationReport) {
+    static ENTITY: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^&(#[0-9]+|#x[0-9a-fA-F]+|[A-Za-z][A-Za-z0-9]*);")
+            .expect("This is a valid regexp")
+    });
+
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+
+    while let Some(position) = rest.find(['<', '&']) {
+        let from = &rest[position..];
+
+        if let Some(after) = from.strip_prefix('&') {
+            if !ENTITY.is_match(from) {
+                report.error(path, "unescaped '&'");
+                return;
+            }
+            rest = after;
+            continue;
+        }
+
+        // Comments, declarations and processing instructions hold no elements
+        let skip_to = |end: &str| from.find(end).map(|at| at + end.len());
+        let skipped = if from.starts_with("<!--") {
+            Some(skip_to("-->"))
+        } else if from.starts_with("<![CDATA[") {
+            Some(skip_to("]]>"))
+        } else if from.starts_with("<?") {
+            Some(skip_to("?>"))
+        } else if from.starts_with("<!") {
+            Some(skip_to(">"))
+        } else {
+            None
+        };
+        if let Some(skipped) = skipped {
+            match skipped {
+                Some(end) => rest = &from[end..],
+                None => {
+                    report.error(path, "unterminated comment or declaration");
+                    return;
+                }
+            }
+            continue;
+        }
+
+        let end = match tag_end(from) {
+            Some(end) => end,
+            None => {
+                report.error(path, "unterminated tag");
+                return;
+            }
+        };
+        let tag = &from[1..end];
+        rest = &from[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some(opened) if opened == name => {}
+                Some(opened) => {
+                    report.error(
+                        path,
+                        format!("</{}> closes <{}>, which is still open", name, opened),
+                    );
+                    return;
+                }
+                None => {
+                    report.error(path, format!("</{}> closes nothing", name));
+                    return;
+                }
+            }
+        } else if !tag.ends_with('/') {
+            let name = tag.split(|c: char| c.is_whitespace()).next().unwrap_or("");
+            if name.is_empty() {
+                report.error(path, "stray '<'");
+                return;
+            }
+            open.push(name);
+        }
+    }
+
+    if let Some(opened) = open.pop() {
+        report.error(path, format!("<{}> is never closed", opened));
+    }
+}
+
+// ─── Helpers ─────────────────────────────────────────────────────────────────
+
+/// Index of the `>` that ends the tag `from` starts with, skipping quoted values
+fn tag_end(from: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (index, c) in from.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Attributes of every `<name ...>` tag in `text`
+pub(crate) fn tags(text: &str, name: &str) -> Vec<HashMap<String, String>> {
+    static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"([A-Za-z_:][A-Za-z0-9_:.-]*)\s*=\s*"([^"]*)""#)
+            .expect("This is a valid regexp")
+    });
+
+    let tag = Regex::new(&format!(r"<{}(\s[^>]*)?/?>", regex::escape(name)))
+        .expect("This is a valid regexp");
+
+    tag.captures_iter(text)
+        .map(|captures| {
+            let attributes = captures.get(1).map_or("", |attributes| attributes.as_str());
+            ATTRIBUTE
+                .captures_iter(attributes)
+                .map(|attribute| (attribute[1].to_string(), attribute[2].to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Path in the archive of `href`, relative to the file at `base`
+pub(crate) fn resolve(base: &str, href: &str) -> String {
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop();
+
+    for part in href.split('/') {
+        match part {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
+pub(crate) fn read_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Option<Vec<u8>> {
+    let mut file = archive.by_name(path).ok()?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Up to the first `length` bytes of the file at `path`
+fn read_head<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+    length: u64,
+) -> Option<Vec<u8>> {
+    let file = archive.by_name(path).ok()?;
+    let mut bytes = Vec::new();
+    file.take(length).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+pub(crate) fn read_text<R: Read + Seek>(archive: &mut ZipArchive<R>, path: &str) -> Option<String> {
+    read_bytes(archive, path).and_then(|bytes| String::from_utf8(bytes).ok())
+}
+
+#[test]
+fn generated_epub_is_valid() {
+    use crate::manga::make_mobi::epub_builder::{EpubBuilder, EpubContent, ZipLibrary};
+
+    let page = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><body><img src="image-0.png"/></body></html>"#;
+
+    let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    builder
+        .add_cover_image("image-0.png", b"\x89PNG\r\n\x1a\n".as_ref(), "image/png")
+        .unwrap()
+        .add_content(EpubContent::new("cover.html", page.as_bytes()).title("Cover"))
+        .unwrap();
+
+    let mut epub: Vec<u8> = vec![];
+    builder.generate(&mut epub).unwrap();
+
+    let report = validate(std::io::Cursor::new(epub)).unwrap();
+    assert!(report.issues.is_empty(), "{}", report);
+}
+
+#[test]
+fn broken_documents_are_reported() {
+    let mut report = ValidationReport::default();
+
+    check_well_formed("ok.html", "<a><b/>&amp;<!-- <c> --></a>", &mut report);
+    assert!(report.issues.is_empty(), "{}", report);
+
+    check_well_formed("misnested.html", "<a><b></a></b>", &mut report);
+    check_well_formed("unclosed.html", "<a><b></b>", &mut report);
+    check_well_formed("ampersand.html", "<a>Spy & Family</a>", &mut report);
+    check_image(
+        &Item {
+            id: String::from("image-1"),
+            path: String::from("OEBPS/image-1.jpg"),
+            media_type: String::from("image/png"),
+        },
+        &[0xFF, 0xD8, 0xFF, 0xE0],
+        &mut report,
+    );
+
+    let files: Vec<&str> = report.errors().map(|issue| issue.file.as_str()).collect();
+    assert_eq!(
+        files,
+        vec![
+            "misnested.html",
+            "unclosed.html",
+            "ampersand.html",
+            "OEBPS/image-1.jpg"
+        ]
+    );
+}
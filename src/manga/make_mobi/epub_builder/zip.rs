@@ -16,6 +16,21 @@ pub trait Zip {
     /// Write the source content to a file in the archive
     fn write_file<P: AsRef<Path>, R: Read>(&mut self, file: P, content: R) -> Result<()>;
 
+    /// Write the source content to a file in the archive without compressing it,
+    /// for content such as JPEG or PNG images that deflate would not shrink
+    ///
+    /// Implementations that cannot choose per file compress it like any other file.
+    fn write_stored_file<P: AsRef<Path>, R: Read>(&mut self, file: P, content: R) -> Result<()> {
+        self.write_file(file, content)
+    }
+
     /// Generate the ZIP file
     fn generate<W: Write>(&mut self, _: W) -> Result<()>;
 }
+
+/// A zip that writes the archive to where it ends up as files are added, so
+/// finishing it is all that is left to do
+pub trait FinishZip: Zip {
+    /// Write what is left of the archive
+    fn finish(&mut self) -> Result<()>;
+}
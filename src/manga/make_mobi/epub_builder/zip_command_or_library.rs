@@ -30,6 +30,17 @@ impl Zip for ZipCommandOrLibrary {
         }
     }
 
+    fn write_stored_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        match self {
+            ZipCommandOrLibrary::Command(ref mut command) => {
+                command.write_stored_file(path, content)
+            }
+            ZipCommandOrLibrary::Library(ref mut library) => {
+                library.write_stored_file(path, content)
+            }
+        }
+    }
+
     fn generate<W: Write>(&mut self, to: W) -> Result<()> {
         match self {
             ZipCommandOrLibrary::Command(ref mut command) => command.generate(to),
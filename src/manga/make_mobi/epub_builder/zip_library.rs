@@ -2,15 +2,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use crate::manga::make_mobi::epub_builder::zip::Zip;
+use crate::manga::make_mobi::epub_builder::zip::{FinishZip, Zip};
 
 use std::fmt;
+use std::fs::{File, OpenOptions};
 use std::io;
 use std::io::Cursor;
 use std::io::Read;
+use std::io::Seek;
 use std::io::Write;
 use std::path::Path;
 
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use eyre::Context;
 use eyre::Result;
 use libzip::write::FileOptions;
@@ -25,11 +28,34 @@ use libzip::ZipWriter;
 ///
 /// Note that these takes care of adding the mimetype (since it must not be deflated), it
 /// should not be added manually.
-pub struct ZipLibrary {
-    writer: ZipWriter<Cursor<Vec<u8>>>,
+///
+/// By default the archive is built in memory and copied out by `generate`; use
+/// `ZipLibrary::to_file` to write it straight to disk and `finish` it instead.
+pub struct ZipLibrary<W: ZipOutput = Cursor<Vec<u8>>> {
+    writer: ZipWriter<W>,
+    last_modified: Option<libzip::DateTime>,
+}
+
+/// Where a `ZipLibrary` writes the archive while it is being built
+pub trait ZipOutput: Write + Seek {
+    /// Hands the finished archive over to `to`
+    fn copy_to<T: Write>(self, to: T) -> io::Result<()>;
+}
+
+impl ZipOutput for Cursor<Vec<u8>> {
+    fn copy_to<T: Write>(self, mut to: T) -> io::Result<()> {
+        to.write_all(self.into_inner().as_ref())
+    }
 }
 
-impl fmt::Debug for ZipLibrary {
+impl ZipOutput for File {
+    fn copy_to<T: Write>(mut self, mut to: T) -> io::Result<()> {
+        self.rewind()?;
+        io::copy(&mut self, &mut to).map(|_| ())
+    }
+}
+
+impl<W: ZipOutput> fmt::Debug for ZipLibrary<W> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "ZipLibrary")
     }
@@ -40,31 +66,93 @@ impl ZipLibrary {
     ///
     /// Also add mimetype at the beginning of the EPUB file.
     pub fn new() -> Result<ZipLibrary> {
-        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        ZipLibrary::with_output(Cursor::new(vec![]))
+    }
+}
+
+impl ZipLibrary<File> {
+    /// Creates a wrapper that writes every file to a new file at `path` as soon
+    /// as it is added, so the archive never has to fit in memory
+    pub fn to_file<P: AsRef<Path>>(path: P) -> Result<ZipLibrary<File>> {
+        let path = path.as_ref();
+        // Readable too, so `generate` can copy the archive out of it
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .wrap_err_with(|| format!("could not create {}", path.display()))?;
+        ZipLibrary::with_output(file)
+    }
+}
+
+impl FinishZip for ZipLibrary<File> {
+    fn finish(&mut self) -> Result<()> {
+        let mut file = self.writer.finish().wrap_err("error writing zip file")?;
+        file.flush().wrap_err("error writing zip file")?;
+        Ok(())
+    }
+}
+
+impl<W: ZipOutput> ZipLibrary<W> {
+    fn with_output(output: W) -> Result<ZipLibrary<W>> {
+        let mut writer = ZipWriter::new(output);
         writer.set_comment(""); // Fix issues with some readers
 
+        // The mimetype is the same in every book, so it gets the earliest zip time
+        // rather than the current one
         writer
             .start_file(
                 "mimetype",
-                FileOptions::default().compression_method(CompressionMethod::Stored),
+                FileOptions::default()
+                    .compression_method(CompressionMethod::Stored)
+                    .last_modified_time(libzip::DateTime::default()),
             )
             .wrap_err("could not create mimetype in epub")?;
         writer
             .write(b"application/epub+zip")
             .wrap_err("could not write mimetype in epub")?;
 
-        Ok(ZipLibrary { writer })
+        Ok(ZipLibrary {
+            writer,
+            last_modified: None,
+        })
     }
-}
 
-impl Zip for ZipLibrary {
-    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, mut content: R) -> Result<()> {
+    /// Stamps the files added from now on with `date` instead of the current
+    /// time, so building the same book twice gives the same archive. Dates zip
+    /// cannot hold, before 1980 or after 2107, are replaced by 1980-01-01.
+    pub fn set_modified_date(&mut self, date: DateTime<Utc>) -> &mut Self {
+        self.last_modified = Some(
+            libzip::DateTime::from_date_and_time(
+                u16::try_from(date.year()).unwrap_or_default(),
+                date.month() as u8,
+                date.day() as u8,
+                date.hour() as u8,
+                date.minute() as u8,
+                date.second() as u8,
+            )
+            .unwrap_or_default(),
+        );
+        self
+    }
+
+    fn write_with_options<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        mut content: R,
+        mut options: FileOptions,
+    ) -> Result<()> {
+        if let Some(last_modified) = self.last_modified {
+            options = options.last_modified_time(last_modified);
+        }
+
         let mut file = format!("{}", path.as_ref().display());
         if cfg!(target_os = "windows") {
             // Path names should not use backspaces in zip files
             file = file.replace('\\', "/");
         }
-        let options = FileOptions::default();
         self.writer
             .start_file(file.clone(), options)
             .wrap_err_with(|| format!("could not create file '{}' in epub", file))?;
@@ -72,12 +160,76 @@ impl Zip for ZipLibrary {
             .wrap_err_with(|| format!("could not write file '{}' in epub", file))?;
         Ok(())
     }
+}
+
+impl<W: ZipOutput> Zip for ZipLibrary<W> {
+    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        self.write_with_options(path, content, FileOptions::default())
+    }
+
+    fn write_stored_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        self.write_with_options(
+            path,
+            content,
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+    }
 
-    fn generate<W: Write>(&mut self, mut to: W) -> Result<()> {
-        let cursor = self.writer.finish().wrap_err("error writing zip file")?;
-        let bytes = cursor.into_inner();
-        to.write_all(bytes.as_ref())
-            .wrap_err("error writing zip file")?;
+    fn generate<T: Write>(&mut self, to: T) -> Result<()> {
+        let output = self.writer.finish().wrap_err("error writing zip file")?;
+        output.copy_to(to).wrap_err("error writing zip file")?;
         Ok(())
     }
 }
+
+#[cfg(feature = "zip-command")]
+#[test]
+fn streamed_archive_stores_images() {
+    let dir = tempdir::TempDir::new("zip_library").unwrap();
+    let path = dir.path().join("book.epub");
+
+    let mut zip = ZipLibrary::to_file(&path).unwrap();
+    zip.write_stored_file("OEBPS/image-0.jpg", [0u8; 512].as_ref())
+        .unwrap();
+    zip.write_file("OEBPS/page_1.html", [b'a'; 512].as_ref())
+        .unwrap();
+    zip.finish().unwrap();
+
+    let mut archive = libzip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+    assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+    assert_eq!(
+        archive.by_name("OEBPS/image-0.jpg").unwrap().compression(),
+        CompressionMethod::Stored
+    );
+    assert_eq!(
+        archive.by_name("OEBPS/page_1.html").unwrap().compression(),
+        CompressionMethod::Deflated
+    );
+}
+
+#[test]
+fn modified_date_is_kept() {
+    use chrono::TimeZone;
+
+    let mut zip = ZipLibrary::new().unwrap();
+    zip.set_modified_date(Utc.with_ymd_and_hms(2021, 6, 2, 10, 30, 4).unwrap());
+    zip.write_file("OEBPS/page_1.html", [b'a'; 16].as_ref())
+        .unwrap();
+
+    let mut archive = Vec::new();
+    zip.generate(&mut archive).unwrap();
+
+    let mut archive = libzip::ZipArchive::new(Cursor::new(archive)).unwrap();
+    let modified = archive
+        .by_name("OEBPS/page_1.html")
+        .unwrap()
+        .last_modified();
+    assert_eq!(
+        (modified.year(), modified.month(), modified.day()),
+        (2021, 6, 2)
+    );
+    assert_eq!(
+        (modified.hour(), modified.minute(), modified.second()),
+        (10, 30, 4)
+    );
+}
@@ -0,0 +1,232 @@
+//! Record 0 of a KF8 book: the PalmDOC and MOBI headers, and the EXTH
+//! metadata that carries the title, authors and fixed-layout settings.
+
+use super::index::{align, NULL};
+use super::package::Package;
+
+/// Size of every text record but the last
+pub const TEXT_RECORD_SIZE: usize = 4096;
+
+/// Length of the MOBI header of a KF8 book, counted from its `MOBI` magic
+const MOBI_HEADER_LENGTH: usize = 264;
+
+/// Where the records of a book ended up, as the headers refer to them by number
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub text_length: usize,
+    pub text_records: usize,
+    pub first_non_text: usize,
+    pub ncx_index: Option<usize>,
+    pub fragment_index: usize,
+    pub skeleton_index: usize,
+    pub first_resource: usize,
+    pub resource_count: usize,
+    /// Index among the resources of the cover and its thumbnail
+    pub cover: Option<usize>,
+    pub thumbnail: Option<usize>,
+    pub fdst: usize,
+    pub flow_count: usize,
+    pub flis: usize,
+    pub fcis: usize,
+}
+
+/// Record 0 of the book made of `package`, laid out as `layout`
+pub fn record0(package: &Package, layout: &Layout, unique_id: u32) -> Vec<u8> {
+    let exth = exth(package, layout);
+    let title = package.title.as_bytes();
+    let title_offset = 16 + MOBI_HEADER_LENGTH + exth.len();
+
+    let mut record: Vec<u8> = Vec::new();
+    let u16 = |record: &mut Vec<u8>, value: usize| record.extend((value as u16).to_be_bytes());
+    let u32 = |record: &mut Vec<u8>, value: u32| record.extend(value.to_be_bytes());
+    let index = |record: &mut Vec<u8>, value: Option<usize>| {
+        record.extend(value.map_or(NULL, |value| value as u32).to_be_bytes())
+    };
+
+    // ─── PalmDOC Header ──────────────────────────────────────────────────
+
+    // No compression
+    u16(&mut record, 1);
+    u16(&mut record, 0);
+    u32(&mut record, layout.text_length as u32);
+    u16(&mut record, layout.text_records);
+    u16(&mut record, TEXT_RECORD_SIZE);
+    // No encryption
+    u16(&mut record, 0);
+    u16(&mut record, 0);
+
+    // ─── MOBI Header ─────────────────────────────────────────────────────
+
+    record.extend(b"MOBI");
+    u32(&mut record, MOBI_HEADER_LENGTH as u32);
+    // Book
+    u32(&mut record, 2);
+    // UTF-8
+    u32(&mut record, 65001);
+    u32(&mut record, unique_id);
+    // File version
+    u32(&mut record, 8);
+    // Orthographic, inflection and extra indices, which only dictionaries have
+    for _ in 0..10 {
+        u32(&mut record, NULL);
+    }
+    u32(&mut record, layout.first_non_text as u32);
+    u32(&mut record, title_offset as u32);
+    u32(&mut record, title.len() as u32);
+    u32(&mut record, locale(&package.language));
+    // Dictionary input and output languages
+    u32(&mut record, 0);
+    u32(&mut record, 0);
+    // Minimum reader version
+    u32(&mut record, 8);
+    u32(&mut record, layout.first_resource as u32);
+    // No HUFF/CDIC compression
+    record.resize(record.len() + 16, 0);
+    // Has EXTH
+    u32(&mut record, 0x50);
+    record.resize(record.len() + 32, 0);
+    u32(&mut record, NULL);
+    // No DRM
+    u32(&mut record, NULL);
+    record.resize(record.len() + 12, 0);
+    record.resize(record.len() + 8, 0);
+    u32(&mut record, layout.fdst as u32);
+    u32(&mut record, layout.flow_count as u32);
+    u32(&mut record, layout.fcis as u32);
+    u32(&mut record, 1);
+    u32(&mut record, layout.flis as u32);
+    u32(&mut record, 1);
+    record.resize(record.len() + 8, 0);
+    // No SRCS
+    u32(&mut record, NULL);
+    u32(&mut record, 0);
+    record.extend([0xFF; 8]);
+    // Text records end in the bytes of a character split over two records
+    u32(&mut record, 1);
+    index(&mut record, layout.ncx_index);
+    index(&mut record, Some(layout.fragment_index));
+    index(&mut record, Some(layout.skeleton_index));
+    // No DATP or guide
+    u32(&mut record, NULL);
+    u32(&mut record, NULL);
+    record.extend([0xFF; 4]);
+    record.resize(record.len() + 4, 0);
+    record.extend([0xFF; 4]);
+    record.resize(record.len() + 4, 0);
+    debug_assert_eq!(record.len(), 16 + MOBI_HEADER_LENGTH);
+
+    // ─── EXTH And Title ──────────────────────────────────────────────────
+
+    record.extend(exth);
+    record.extend_from_slice(title);
+    // The title is followed by at least two zeros
+    record.extend([0, 0]);
+
+    align(record)
+}
+
+/// The EXTH block: metadata, where the cover is and how the book is laid out
+fn exth(package: &Package, layout: &Layout) -> Vec<u8> {
+    let mut records: Vec<(u32, Vec<u8>)> = Vec::new();
+    let mut text = |record_type: u32, value: &str| {
+        records.push((record_type, value.as_bytes().to_vec()));
+    };
+
+    for author in &package.authors {
+        text(100, author);
+    }
+    if let Some(description) = &package.description {
+        text(103, description);
+    }
+    for subject in &package.subjects {
+        text(105, subject);
+    }
+    if let Some(date) = &package.date {
+        text(106, date);
+    }
+    // Kindles tell books apart by ASIN, so a rebuilt book replaces the old one
+    text(113, &package.identifier);
+    text(504, &package.identifier);
+    text(501, "EBOK");
+    text(503, &package.title);
+    text(524, &package.language);
+
+    // Fixed layout, in the same words as the package metadata
+    for (record_type, name) in [
+        (122, "fixed-layout"),
+        (123, "book-type"),
+        (124, "orientation-lock"),
+        (126, "original-resolution"),
+        (127, "zero-gutter"),
+        (128, "zero-margin"),
+        (132, "region-mag"),
+        (525, "primary-writing-mode"),
+    ] {
+        if let Some(content) = package.meta(name) {
+            text(record_type, content);
+        }
+    }
+    if let Some(page_progression) = &package.page_progression {
+        text(527, page_progression);
+    }
+
+    if let Some(cover) = layout.cover {
+        text(
+            129,
+            &format!("kindle:embed:{}", super::to_base32(cover + 1, 4)),
+        );
+    }
+
+    let mut number = |record_type: u32, value: usize| {
+        records.push((record_type, (value as u32).to_be_bytes().to_vec()));
+    };
+
+    number(125, layout.resource_count);
+    if let Some(cover) = layout.cover {
+        number(201, cover);
+        number(203, 0);
+    }
+    if let Some(thumbnail) = layout.thumbnail {
+        number(202, thumbnail);
+    }
+    // Claim to be kindlegen 2.9 on Linux, as readers check for a version
+    // they know before using KF8 features
+    number(204, 202);
+    number(205, 2);
+    number(206, 9);
+    number(207, 0);
+
+    let mut exth: Vec<u8> = b"EXTH".to_vec();
+    let length: usize = 12
+        + records
+            .iter()
+            .map(|(_, data)| 8 + data.len())
+            .sum::<usize>();
+    exth.extend((length as u32).to_be_bytes());
+    exth.extend((records.len() as u32).to_be_bytes());
+    for (record_type, data) in records {
+        exth.extend(record_type.to_be_bytes());
+        exth.extend(((8 + data.len()) as u32).to_be_bytes());
+        exth.extend(data);
+    }
+
+    align(exth)
+}
+
+/// Windows language identifier of `language`, which readers show the book as
+/// written in when they do not read EXTH
+fn locale(language: &str) -> u32 {
+    match language.split('-').next().unwrap_or_default() {
+        "zh" => 0x04,
+        "de" => 0x07,
+        "en" => 0x09,
+        "es" => 0x0A,
+        "fr" => 0x0C,
+        "it" => 0x10,
+        "ja" => 0x11,
+        "ko" => 0x12,
+        "pt" => 0x16,
+        "ru" => 0x19,
+        _ => 0,
+    }
+}
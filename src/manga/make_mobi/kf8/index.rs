@@ -0,0 +1,285 @@
+//! The INDX records KF8 uses to find its parts: the skeleton and fragment
+//! tables that rebuild each document from the text, and the NCX table of
+//! contents.
+
+use std::collections::HashMap;
+
+/// Length of the fixed part of every INDX record
+const HEADER_LENGTH: usize = 192;
+
+/// Entries are moved to a new record before a record reaches 64 KB, leaving
+/// the same margin kindlegen does
+const RECORD_LIMIT: usize = 0x10000 - HEADER_LENGTH - 1048;
+
+/// CNCX records are kept well under 64 KB as well
+const CNCX_RECORD_LIMIT: usize = 0x10000 - 1024;
+
+/// Marks a field that is not used, e.g. an index the book does not have
+pub const NULL: u32 = 0xFFFF_FFFF;
+
+/// A field every entry of an index may have
+#[derive(Debug, Clone, Copy)]
+pub struct Tag {
+    pub number: u8,
+    /// How many values one occurrence of the tag has
+    pub values_per_entry: u8,
+    /// Bits of the entry's control byte holding how often the tag occurs
+    pub mask: u8,
+}
+
+/// One entry of an index
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    /// The values of each tag of the index, in the order of its tags; empty
+    /// for tags the entry does not have
+    pub values: Vec<Vec<u32>>,
+}
+
+/// The strings an index refers to by offset, such as table of contents labels
+#[derive(Debug, Default)]
+pub struct Cncx {
+    pub records: Vec<Vec<u8>>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Cncx {
+    pub fn new<'a>(strings: impl IntoIterator<Item = &'a str>) -> Cncx {
+        let mut cncx = Cncx::default();
+        let mut record: Vec<u8> = Vec::new();
+
+        for string in strings {
+            if cncx.offsets.contains_key(string) {
+                continue;
+            }
+
+            let mut raw = encint(string.len() as u32);
+            raw.extend_from_slice(string.as_bytes());
+
+            if record.len() + raw.len() > CNCX_RECORD_LIMIT {
+                cncx.records.push(align(record));
+                record = Vec::new();
+            }
+
+            // The record number is kept in the upper half of the offset
+            let offset = (cncx.records.len() * 0x10000 + record.len()) as u32;
+            cncx.offsets.insert(string.to_string(), offset);
+            record.extend(raw);
+        }
+
+        if !record.is_empty() {
+            cncx.records.push(align(record));
+        }
+
+        cncx
+    }
+
+    /// Offset of `string`, which must be one of the strings the CNCX was made of
+    pub fn offset(&self, string: &str) -> u32 {
+        self.offsets[string]
+    }
+}
+
+/// The records of an index with `tags`: its header record, the records
+/// holding `entries`, then the records of `cncx`
+pub fn records(tags: &[Tag], entries: &[Entry], cncx: &Cncx) -> Vec<Vec<u8>> {
+    let mut blocks: Vec<Vec<u8>> = vec![Vec::new()];
+    // Offsets of the entries in each block, for its IDXT
+    let mut idxt_blocks: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut counts: Vec<u16> = vec![0];
+    let mut last_names: Vec<&str> = vec![""];
+
+    for entry in entries {
+        let raw = encode_entry(tags, entry);
+
+        let block = blocks.last().unwrap();
+        let idxt = idxt_blocks.last().unwrap();
+        if block.len() + idxt.len() + raw.len() + 2 > RECORD_LIMIT {
+            blocks.push(Vec::new());
+            idxt_blocks.push(Vec::new());
+            counts.push(0);
+            last_names.push("");
+        }
+
+        let offset = (HEADER_LENGTH + blocks.last().unwrap().len()) as u16;
+        idxt_blocks.last_mut().unwrap().extend(offset.to_be_bytes());
+        blocks.last_mut().unwrap().extend(raw);
+        *counts.last_mut().unwrap() += 1;
+        *last_names.last_mut().unwrap() = &entry.name;
+    }
+
+    let mut records: Vec<Vec<u8>> = Vec::new();
+
+    // ─── Header Record ───────────────────────────────────────────────────
+
+    let tagx = align(tagx(tags));
+
+    // Every data record is described by its last entry's name and its count
+    let mut geometry: Vec<u8> = Vec::new();
+    let mut idxt: Vec<u8> = b"IDXT".to_vec();
+    for (name, count) in last_names.iter().zip(&counts) {
+        idxt.extend(((HEADER_LENGTH + tagx.len() + geometry.len()) as u16).to_be_bytes());
+        geometry.push(name.len() as u8);
+        geometry.extend_from_slice(name.as_bytes());
+        geometry.extend(count.to_be_bytes());
+    }
+    let geometry = align(geometry);
+    let idxt = align(idxt);
+
+    let mut header: Vec<u8> = b"INDX".to_vec();
+    for value in [
+        HEADER_LENGTH as u32,
+        0,
+        0,
+        2,
+        // IDXT offset
+        (HEADER_LENGTH + tagx.len() + geometry.len()) as u32,
+        blocks.len() as u32,
+        // UTF-8
+        65001,
+        NULL,
+        entries.len() as u32,
+        // ORDT and LIGT, which only dictionaries have
+        0,
+        0,
+        0,
+        cncx.records.len() as u32,
+    ] {
+        header.extend(value.to_be_bytes());
+    }
+    header.resize(180, 0);
+    // TAGX offset
+    header.extend((HEADER_LENGTH as u32).to_be_bytes());
+    header.resize(HEADER_LENGTH, 0);
+    header.extend(tagx);
+    header.extend(geometry);
+    header.extend(idxt);
+    records.push(header);
+
+    // ─── Data Records ────────────────────────────────────────────────────
+
+    for ((block, idxt_block), count) in blocks.into_iter().zip(idxt_blocks).zip(counts) {
+        let block = align(block);
+
+        let mut record: Vec<u8> = b"INDX".to_vec();
+        record.extend((HEADER_LENGTH as u32).to_be_bytes());
+        record.extend(0u32.to_be_bytes());
+        // Data record, the header record is type 0
+        record.extend(1u32.to_be_bytes());
+        record.extend(0u32.to_be_bytes());
+        // IDXT offset
+        record.extend(((HEADER_LENGTH + block.len()) as u32).to_be_bytes());
+        record.extend((count as u32).to_be_bytes());
+        record.extend([0xFF; 8]);
+        record.resize(HEADER_LENGTH, 0);
+        record.extend(block);
+        record.extend(align([b"IDXT".as_ref(), &idxt_block].concat()));
+        records.push(record);
+    }
+
+    records.extend(cncx.records.iter().cloned());
+
+    records
+}
+
+/// The TAGX block describing `tags`, all counted by a single control byte
+fn tagx(tags: &[Tag]) -> Vec<u8> {
+    let mut table: Vec<u8> = Vec::new();
+    for tag in tags {
+        table.extend([tag.number, tag.values_per_entry, tag.mask, 0]);
+    }
+    // End of the tags the control byte counts
+    table.extend([0, 0, 0, 1]);
+
+    let mut tagx: Vec<u8> = b"TAGX".to_vec();
+    tagx.extend(((12 + table.len()) as u32).to_be_bytes());
+    // Number of control bytes
+    tagx.extend(1u32.to_be_bytes());
+    tagx.extend(table);
+    tagx
+}
+
+fn encode_entry(tags: &[Tag], entry: &Entry) -> Vec<u8> {
+    let mut raw: Vec<u8> = vec![entry.name.len() as u8];
+    raw.extend_from_slice(entry.name.as_bytes());
+
+    // How often each tag occurs, shifted into its bits of the control byte
+    let control_byte = tags
+        .iter()
+        .zip(&entry.values)
+        .fold(0u8, |control_byte, (tag, values)| {
+            let occurrences = (values.len() / tag.values_per_entry as usize) as u8;
+            control_byte | (tag.mask & (occurrences << tag.mask.trailing_zeros()))
+        });
+    raw.push(control_byte);
+
+    for value in entry.values.iter().flatten() {
+        raw.extend(encint(*value));
+    }
+
+    raw
+}
+
+/// `value` as a variable width integer: seven bits per byte, most significant
+/// first, the last byte marked by its high bit
+pub fn encint(mut value: u32) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    loop {
+        bytes.push((value & 0x7F) as u8);
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    bytes[0] |= 0x80;
+    bytes.reverse();
+    bytes
+}
+
+/// Pads `block` with zeros to a multiple of four bytes
+pub fn align(mut block: Vec<u8>) -> Vec<u8> {
+    block.resize(block.len().div_ceil(4) * 4, 0);
+    block
+}
+
+#[test]
+fn entries_are_encoded_with_their_control_byte() {
+    assert_eq!(encint(0), vec![0x80]);
+    assert_eq!(encint(0x7F), vec![0xFF]);
+    assert_eq!(encint(0x80), vec![0x01, 0x80]);
+    assert_eq!(encint(4096), vec![0x20, 0x80]);
+
+    // The skeleton table repeats each value, so both tags occur twice
+    let tags = [
+        Tag {
+            number: 1,
+            values_per_entry: 1,
+            mask: 0b0011,
+        },
+        Tag {
+            number: 6,
+            values_per_entry: 2,
+            mask: 0b1100,
+        },
+    ];
+    let entry = Entry {
+        name: String::from("SKEL0000000000"),
+        values: vec![vec![1, 1], vec![0, 300, 0, 300]],
+    };
+
+    let raw = encode_entry(&tags, &entry);
+    assert_eq!(raw[0] as usize, "SKEL0000000000".len());
+    assert_eq!(raw[15], 0b1010);
+    assert_eq!(
+        &raw[16..],
+        &[0x81, 0x81, 0x80, 0x02, 0xAC, 0x80, 0x02, 0xAC]
+    );
+
+    let records = records(&tags, &[entry], &Cncx::default());
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|record| record.starts_with(b"INDX")));
+    assert_eq!(&records[0][HEADER_LENGTH..HEADER_LENGTH + 4], b"TAGX");
+    // One entry, in one data record
+    assert_eq!(&records[0][36..40], &1u32.to_be_bytes());
+    assert_eq!(&records[1][24..28], &1u32.to_be_bytes());
+}
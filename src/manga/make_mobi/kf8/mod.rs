@@ -0,0 +1,554 @@
+//! Writes KF8 (AZW3) books, the format Kindles read fixed-layout comics in,
+//! straight from the EPUB built for them, so no Kindle tools are needed.
+//!
+//! A book is a Palm database: record 0 holds the headers and metadata, then
+//! come the text records, the indices that find documents and chapters in the
+//! text, the images, and the FDST, FLIS, FCIS and EOF records readers expect
+//! at the end.
+
+mod header;
+mod index;
+mod package;
+mod palmdb;
+mod text;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use chrono::{DateTime, TimeZone, Utc};
+use eyre::{Context, Result};
+use image::ImageOutputFormat;
+use sha1::{Digest, Sha1};
+
+use self::header::{Layout, TEXT_RECORD_SIZE};
+use self::index::{Cncx, Entry, Tag};
+use self::package::{NavPoint, Package};
+use self::text::Text;
+
+/// Size readers show the cover in their library at
+const THUMBNAIL_SIZE: (u32, u32) = (330, 470);
+
+/// Marks the end of the book
+const EOF: &[u8] = b"\xE9\x8E\r\n";
+
+/// Converts the EPUB at `epub_path` to a KF8 book at `output_path`
+pub fn convert(epub_path: &Path, output_path: &Path) -> Result<()> {
+    let epub = fs::File::open(epub_path)
+        .wrap_err_with(|| format!("could not open {}", epub_path.display()))?;
+    let package = Package::read(epub)?;
+
+    fs::write(output_path, write(&package)?)
+        .wrap_err_with(|| format!("could not write {}", output_path.display()))
+}
+
+/// The KF8 book made of `package`
+fn write(package: &Package) -> Result<Vec<u8>> {
+    let text = Text::new(package)?;
+
+    let text_records = text_records(&text.bytes);
+    let ncx_records = ncx_records(package, &text);
+    let fragment_records = fragment_records(&text);
+    let skeleton_records = skeleton_records(&text);
+
+    let mut resources: Vec<Vec<u8>> = package
+        .images
+        .iter()
+        .map(|image| image.bytes.clone())
+        .collect();
+    let thumbnail = package
+        .cover
+        .and_then(|cover| thumbnail(&package.images[cover].bytes))
+        .map(|thumbnail| {
+            resources.push(thumbnail);
+            resources.len() - 1
+        });
+
+    // Records are numbered in the order they are written, record 0 first
+    let first_non_text = 1 + text_records.len();
+    let ncx_index = (!ncx_records.is_empty()).then_some(first_non_text);
+    let fragment_index = first_non_text + ncx_records.len();
+    let skeleton_index = fragment_index + fragment_records.len();
+    let first_resource = skeleton_index + skeleton_records.len();
+    let fdst = first_resource + resources.len();
+
+    let layout = Layout {
+        text_length: text.bytes.len(),
+        text_records: text_records.len(),
+        first_non_text,
+        ncx_index,
+        fragment_index,
+        skeleton_index,
+        first_resource,
+        resource_count: resources.len(),
+        cover: package.cover,
+        thumbnail,
+        fdst,
+        flow_count: text.flows.len(),
+        flis: fdst + 1,
+        fcis: fdst + 2,
+    };
+
+    let mut records: Vec<Vec<u8>> = vec![header::record0(
+        package,
+        &layout,
+        unique_id(&package.identifier),
+    )];
+    records.extend(text_records);
+    records.extend(ncx_records);
+    records.extend(fragment_records);
+    records.extend(skeleton_records);
+    records.extend(resources);
+    records.push(fdst_record(&text));
+    records.push(flis_record());
+    records.push(fcis_record(text.bytes.len()));
+    records.push(EOF.to_vec());
+
+    Ok(palmdb::write(
+        &package.title,
+        palm_date(package.date.as_deref()),
+        &records,
+    ))
+}
+
+/// `value` in the base 32 KF8 links use, padded with zeros to `digits`
+fn to_base32(mut value: usize, digits: usize) -> String {
+    const DIGITS: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+    let mut base32: Vec<u8> = Vec::new();
+    loop {
+        base32.push(DIGITS[value % 32]);
+        value /= 32;
+        if value == 0 {
+            break;
+        }
+    }
+    base32.resize(base32.len().max(digits), b'0');
+    base32.reverse();
+
+    String::from_utf8(base32).unwrap()
+}
+
+/// Number of the book in its header, derived from its identifier so the same
+/// book always gets the same one
+fn unique_id(identifier: &str) -> u32 {
+    let hash = Sha1::digest(identifier.as_bytes());
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// Seconds since 1970 of the book's date, a full date or just a year
+fn palm_date(date: Option<&str>) -> u32 {
+    let Some(date) = date else {
+        return 0;
+    };
+
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            Utc.with_ymd_and_hms(date.parse().ok()?, 1, 1, 0, 0, 0)
+                .single()
+        })
+        .map_or(0, |date| date.timestamp().clamp(0, u32::MAX as i64) as u32)
+}
+
+/// A small JPEG of the cover for the library view
+fn thumbnail(cover: &[u8]) -> Option<Vec<u8>> {
+    let cover = image::load_from_memory(cover).ok()?;
+    let thumbnail = image::DynamicImage::ImageRgb8(
+        cover
+            .thumbnail(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1)
+            .to_rgb8(),
+    );
+
+    let mut bytes: Vec<u8> = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Jpeg(90))
+        .ok()?;
+    Some(bytes)
+}
+
+// ─── Records ─────────────────────────────────────────────────────────────────
+
+/// The text cut into records, each followed by the start of a character it
+/// cuts in two and the number of those bytes
+fn text_records(text: &[u8]) -> Vec<Vec<u8>> {
+    text.chunks(TEXT_RECORD_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let next = &text[((index + 1) * TEXT_RECORD_SIZE).min(text.len())..];
+            let overlap: Vec<u8> = next
+                .iter()
+                .take(3)
+                .take_while(|byte| *byte & 0xC0 == 0x80)
+                .copied()
+                .collect();
+
+            let mut record = chunk.to_vec();
+            record.extend(&overlap);
+            record.push(overlap.len() as u8);
+            record
+        })
+        .collect()
+}
+
+fn skeleton_records(text: &Text) -> Vec<Vec<u8>> {
+    let tags = [
+        Tag {
+            number: 1,
+            values_per_entry: 1,
+            mask: 0b0011,
+        },
+        Tag {
+            number: 6,
+            values_per_entry: 2,
+            mask: 0b1100,
+        },
+    ];
+
+    // Readers expect every value of the skeleton table twice
+    let entries: Vec<Entry> = text
+        .skeletons
+        .iter()
+        .enumerate()
+        .map(|(index, skeleton)| Entry {
+            name: format!("SKEL{:010}", index),
+            values: vec![
+                vec![1, 1],
+                vec![
+                    skeleton.start as u32,
+                    skeleton.length as u32,
+                    skeleton.start as u32,
+                    skeleton.length as u32,
+                ],
+            ],
+        })
+        .collect();
+
+    index::records(&tags, &entries, &Cncx::default())
+}
+
+fn fragment_records(text: &Text) -> Vec<Vec<u8>> {
+    let tags = [
+        // Selector of the element the fragment goes into
+        Tag {
+            number: 2,
+            values_per_entry: 1,
+            mask: 0b0001,
+        },
+        Tag {
+            number: 3,
+            values_per_entry: 1,
+            mask: 0b0010,
+        },
+        Tag {
+            number: 4,
+            values_per_entry: 1,
+            mask: 0b0100,
+        },
+        // Start among the fragments of its skeleton, and length
+        Tag {
+            number: 6,
+            values_per_entry: 2,
+            mask: 0b1000,
+        },
+    ];
+
+    let cncx = Cncx::new(
+        text.fragments
+            .iter()
+            .map(|fragment| fragment.selector.as_str()),
+    );
+
+    let entries: Vec<Entry> = text
+        .fragments
+        .iter()
+        .enumerate()
+        .map(|(index, fragment)| Entry {
+            name: format!("{:010}", fragment.insert_position),
+            values: vec![
+                vec![cncx.offset(&fragment.selector)],
+                vec![fragment.file_number as u32],
+                vec![index as u32],
+                // Documents have a single fragment, which starts right away
+                vec![0, fragment.length as u32],
+            ],
+        })
+        .collect();
+
+    index::records(&tags, &entries, &cncx)
+}
+
+/// The table of contents, or nothing for a book without one
+fn ncx_records(package: &Package, text: &Text) -> Vec<Vec<u8>> {
+    // (nav point, depth, parent), breadth first as readers expect: all the
+    // top level entries, then their children and so on
+    let mut flat: Vec<(&NavPoint, u32, Option<usize>)> = Vec::new();
+    let mut queue: VecDeque<(&NavPoint, u32, Option<usize>)> =
+        package.toc.iter().map(|point| (point, 0, None)).collect();
+
+    while let Some((point, depth, parent)) = queue.pop_front() {
+        // Points outside the spine have nowhere to go
+        if !package
+            .documents
+            .iter()
+            .any(|document| document.path == point.path)
+        {
+            continue;
+        }
+
+        flat.push((point, depth, parent));
+        let index = flat.len() - 1;
+        queue.extend(
+            point
+                .children
+                .iter()
+                .map(|child| (child, depth + 1, Some(index))),
+        );
+    }
+
+    if flat.is_empty() {
+        return Vec::new();
+    }
+
+    let fragment = |point: &NavPoint| {
+        package
+            .documents
+            .iter()
+            .position(|document| document.path == point.path)
+            .unwrap()
+    };
+    let offset = |point: &NavPoint| text.fragments[fragment(point)].insert_position;
+
+    let mut offsets: Vec<usize> = flat.iter().map(|(point, ..)| offset(point)).collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    let cncx = Cncx::new(flat.iter().map(|(point, ..)| point.label.as_str()));
+    let name_digits = format!("{:X}", flat.len() - 1).len().max(2);
+
+    let tags = [
+        Tag {
+            number: 1,
+            values_per_entry: 1,
+            mask: 0b0000_0001,
+        },
+        Tag {
+            number: 2,
+            values_per_entry: 1,
+            mask: 0b0000_0010,
+        },
+        Tag {
+            number: 3,
+            values_per_entry: 1,
+            mask: 0b0000_0100,
+        },
+        Tag {
+            number: 4,
+            values_per_entry: 1,
+            mask: 0b0000_1000,
+        },
+        Tag {
+            number: 21,
+            values_per_entry: 1,
+            mask: 0b0001_0000,
+        },
+        Tag {
+            number: 22,
+            values_per_entry: 1,
+            mask: 0b0010_0000,
+        },
+        Tag {
+            number: 23,
+            values_per_entry: 1,
+            mask: 0b0100_0000,
+        },
+        Tag {
+            number: 6,
+            values_per_entry: 2,
+            mask: 0b1000_0000,
+        },
+    ];
+
+    let entries: Vec<Entry> = flat
+        .iter()
+        .enumerate()
+        .map(|(index, (point, depth, parent))| {
+            let start = offset(point);
+            // An entry runs until the next one starts, or the text ends
+            let end = offsets
+                .iter()
+                .find(|offset| **offset > start)
+                .copied()
+                .unwrap_or(text.documents_length());
+
+            let children: Vec<u32> = flat
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, _, child_parent))| *child_parent == Some(index))
+                .map(|(child, _)| child as u32)
+                .collect();
+
+            Entry {
+                name: format!("{:0width$X}", index, width = name_digits),
+                values: vec![
+                    vec![start as u32],
+                    vec![(end - start) as u32],
+                    vec![cncx.offset(&point.label)],
+                    vec![*depth],
+                    parent.map(|parent| parent as u32).into_iter().collect(),
+                    children.first().copied().into_iter().collect(),
+                    children.last().copied().into_iter().collect(),
+                    vec![fragment(point) as u32, 0],
+                ],
+            }
+        })
+        .collect();
+
+    index::records(&tags, &entries, &cncx)
+}
+
+/// Where each flow of the text starts and ends
+fn fdst_record(text: &Text) -> Vec<u8> {
+    let mut record: Vec<u8> = b"FDST".to_vec();
+    record.extend(12u32.to_be_bytes());
+    record.extend((text.flows.len() as u32).to_be_bytes());
+    for (start, end) in &text.flows {
+        record.extend((*start as u32).to_be_bytes());
+        record.extend((*end as u32).to_be_bytes());
+    }
+    record
+}
+
+/// Fixed record kindlegen writes, readers check it is there
+fn flis_record() -> Vec<u8> {
+    let mut record: Vec<u8> = b"FLIS".to_vec();
+    record.extend([
+        0, 0, 0, 8, 0, 0x41, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0, 1, 0, 3, 0, 0, 0, 3, 0,
+        0, 0, 1, 0xFF, 0xFF, 0xFF, 0xFF,
+    ]);
+    record
+}
+
+fn fcis_record(text_length: usize) -> Vec<u8> {
+    let mut record: Vec<u8> = b"FCIS".to_vec();
+    for value in [0x14u32, 0x10, 0x01, 0] {
+        record.extend(value.to_be_bytes());
+    }
+    record.extend((text_length as u32).to_be_bytes());
+    for value in [0u32, 0x20, 0x08] {
+        record.extend(value.to_be_bytes());
+    }
+    record.extend([0, 1, 0, 1, 0, 0, 0, 0]);
+    record
+}
+
+#[test]
+fn epub_becomes_kf8_book() {
+    use super::epub_builder::{
+        EpubBuilder, EpubContent, EpubVersion, FixedLayout, PageProgression, ZipLibrary,
+    };
+
+    let mut png: Vec<u8> = Vec::new();
+    image::DynamicImage::new_rgb8(60, 80)
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let page = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+        <html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>Page</title></head>\n\
+        <body epub:type=\"bodymatter\"><div><img src=\"image-1.png\" alt=\"\"/></div></body>\n</html>";
+
+    let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    builder
+        .epub_version(EpubVersion::V20)
+        .page_progression(PageProgression::Rtl)
+        .fixed_layout(FixedLayout {
+            width: 60,
+            height: 80,
+            region_magnification: false,
+        });
+    builder
+        .metadata("title", "Spy & Family volume 2")
+        .unwrap()
+        .add_cover_image("image-0.png", png.as_slice(), "image/png")
+        .unwrap()
+        .add_resource("image-1.png", png.as_slice(), "image/png")
+        .unwrap()
+        .add_content(EpubContent::new("page_1.html", page.as_bytes()).title("Chapter 1"))
+        .unwrap();
+    let mut epub: Vec<u8> = Vec::new();
+    builder.generate(&mut epub).unwrap();
+
+    let package = Package::read(Cursor::new(epub)).unwrap();
+    assert_eq!(package.title, "Spy & Family volume 2");
+    assert_eq!(package.cover, Some(0));
+    assert_eq!(package.toc[0].label, "Chapter 1");
+
+    // Documents are rebuilt by putting each fragment back into its skeleton
+    let text = Text::new(&package).unwrap();
+    let rebuilt = |index: usize| {
+        let skeleton = &text.skeletons[index];
+        let fragment = &text.fragments[index];
+        let insert = fragment.insert_position - skeleton.start;
+        let skeleton_text = &text.bytes[skeleton.start..skeleton.start + skeleton.length];
+        let fragment_start = skeleton.start + skeleton.length;
+        String::from_utf8(
+            [
+                &skeleton_text[..insert],
+                &text.bytes[fragment_start..fragment_start + fragment.length],
+                &skeleton_text[insert..],
+            ]
+            .concat(),
+        )
+        .unwrap()
+    };
+    let last = package.documents.len() - 1;
+    let page = rebuilt(last);
+    assert!(page.contains(&format!(
+        "<body epub:type=\"bodymatter\" aid=\"{}\"><div><img src=\"kindle:embed:0002?mime=image/png\" alt=\"\"/></div></body>",
+        to_base32(last, 1)
+    )));
+
+    let book = write(&package).unwrap();
+    let record_count = u16::from_be_bytes([book[76], book[77]]) as usize;
+    let record = |index: usize| {
+        let offset = |index: usize| {
+            let at = 78 + 8 * index;
+            u32::from_be_bytes(book[at..at + 4].try_into().unwrap()) as usize
+        };
+        let end = if index + 1 < record_count {
+            offset(index + 1)
+        } else {
+            book.len()
+        };
+        &book[offset(index)..end]
+    };
+
+    let record0 = record(0);
+    assert_eq!(&record0[16..20], b"MOBI");
+    assert_eq!(&record0[36..40], &8u32.to_be_bytes());
+    assert_eq!(&record0[280..284], b"EXTH");
+    let exth = String::from_utf8_lossy(record0);
+    assert!(exth.contains("Spy & Family volume 2"));
+    assert!(exth.contains("horizontal-rl"));
+    assert!(exth.contains("kindle:embed:0001"));
+
+    // The text records hold the text, less the bytes trailing each record
+    let text_records = u16::from_be_bytes([record0[8], record0[9]]) as usize;
+    let stored: Vec<u8> = (1..=text_records)
+        .flat_map(|index| {
+            let record = record(index);
+            let trailing = (record[record.len() - 1] & 0b11) as usize + 1;
+            record[..record.len() - trailing].to_vec()
+        })
+        .collect();
+    assert_eq!(stored, text.bytes);
+
+    let first_resource = u32::from_be_bytes(record0[108..112].try_into().unwrap()) as usize;
+    assert_eq!(record(first_resource), png.as_slice());
+    assert!(record(record_count - 4).starts_with(b"FDST"));
+    assert_eq!(record(record_count - 1), EOF);
+
+    // The same EPUB always gives the same book
+    assert_eq!(book, write(&package).unwrap());
+}
@@ -0,0 +1,272 @@
+//! Reads back the parts of an EPUB package a KF8 book is made of.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use eyre::{eyre, Context, Result};
+use libzip::ZipArchive;
+use regex::Regex;
+
+use crate::manga::make_mobi::epub_builder::{read_bytes, read_text, resolve, tags};
+
+/// A file of the package other than a content document
+#[derive(Debug, Clone)]
+pub struct Resource {
+    /// Path in the archive
+    pub path: String,
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A content document of the spine
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// Path in the archive
+    pub path: String,
+    pub text: String,
+}
+
+/// An entry of the table of contents
+#[derive(Debug, Clone)]
+pub struct NavPoint {
+    pub label: String,
+    /// Path in the archive of the document it points to
+    pub path: String,
+    pub children: Vec<NavPoint>,
+}
+
+/// Everything the KF8 writer needs from an EPUB
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub language: String,
+    /// The package's unique identifier
+    pub identifier: String,
+    pub date: Option<String>,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    /// `name` and `content` of every `<meta>` of the package, the fixed-layout
+    /// settings among them
+    pub metas: Vec<(String, String)>,
+    /// `ltr` or `rtl`, if the spine sets it
+    pub page_progression: Option<String>,
+    /// Content documents in reading order
+    pub documents: Vec<Document>,
+    pub stylesheets: Vec<Resource>,
+    pub images: Vec<Resource>,
+    /// Index into `images` of the cover
+    pub cover: Option<usize>,
+    pub toc: Vec<NavPoint>,
+}
+
+impl Package {
+    /// Reads the package of the EPUB in `epub`
+    pub fn read<R: Read + Seek>(epub: R) -> Result<Package> {
+        let mut archive = ZipArchive::new(epub).wrap_err("epub is not a readable zip archive")?;
+
+        let container = read_text(&mut archive, "META-INF/container.xml")
+            .ok_or_else(|| eyre!("epub has no META-INF/container.xml"))?;
+        let opf_path = tags(&container, "rootfile")
+            .into_iter()
+            .find_map(|attributes| attributes.get("full-path").cloned())
+            .ok_or_else(|| eyre!("epub container names no package document"))?;
+        let opf = read_text(&mut archive, &opf_path)
+            .ok_or_else(|| eyre!("epub has no package document at {}", opf_path))?;
+
+        // id -> (path, media type)
+        let manifest: HashMap<String, (String, String)> = tags(&opf, "item")
+            .into_iter()
+            .filter_map(|attributes| {
+                Some((
+                    attributes.get("id")?.clone(),
+                    (
+                        resolve(&opf_path, attributes.get("href")?),
+                        attributes.get("media-type")?.clone(),
+                    ),
+                ))
+            })
+            .collect();
+
+        let mut documents: Vec<Document> = Vec::new();
+        for idref in tags(&opf, "itemref")
+            .into_iter()
+            .filter_map(|attributes| attributes.get("idref").cloned())
+        {
+            let (path, _) = manifest
+                .get(&idref)
+                .ok_or_else(|| eyre!("spine item '{}' is not in the manifest", idref))?;
+            let text = read_text(&mut archive, path)
+                .ok_or_else(|| eyre!("spine document {} is missing", path))?;
+            documents.push(Document {
+                path: path.clone(),
+                text,
+            });
+        }
+
+        // Resources keep the order of the manifest, so the book does not
+        // depend on how the map iterates
+        let mut stylesheets: Vec<Resource> = Vec::new();
+        let mut images: Vec<Resource> = Vec::new();
+        for attributes in tags(&opf, "item") {
+            let Some((path, media_type)) = attributes.get("id").and_then(|id| manifest.get(id))
+            else {
+                continue;
+            };
+
+            let list = match media_type.as_str() {
+                "text/css" => &mut stylesheets,
+                media_type if media_type.starts_with("image/") => &mut images,
+                _ => continue,
+            };
+
+            let bytes = read_bytes(&mut archive, path)
+                .ok_or_else(|| eyre!("manifest item {} is missing", path))?;
+            list.push(Resource {
+                path: path.clone(),
+                media_type: media_type.clone(),
+                bytes,
+            });
+        }
+
+        let metas: Vec<(String, String)> = tags(&opf, "meta")
+            .into_iter()
+            .filter_map(|attributes| {
+                Some((
+                    attributes.get("name")?.clone(),
+                    decode(attributes.get("content")?),
+                ))
+            })
+            .collect();
+
+        let cover = metas
+            .iter()
+            .find(|(name, _)| name == "cover")
+            .and_then(|(_, id)| manifest.get(id))
+            .and_then(|(path, _)| images.iter().position(|image| &image.path == path));
+
+        let identifier_id = tags(&opf, "package")
+            .into_iter()
+            .find_map(|attributes| attributes.get("unique-identifier").cloned());
+        let identifiers = elements(&opf, "dc:identifier");
+        let identifier = identifiers
+            .iter()
+            .find(|(attributes, _)| attributes.get("id") == identifier_id.as_ref())
+            .or_else(|| identifiers.first())
+            .map(|(_, identifier)| identifier.clone())
+            .unwrap_or_default();
+
+        let texts = |name: &str| -> Vec<String> {
+            elements(&opf, name)
+                .into_iter()
+                .map(|(_, text)| text)
+                .filter(|text| !text.is_empty())
+                .collect()
+        };
+
+        let toc = match tags(&opf, "spine")
+            .into_iter()
+            .find_map(|attributes| attributes.get("toc").cloned())
+            .and_then(|id| manifest.get(&id))
+        {
+            Some((ncx_path, _)) => {
+                let ncx = read_text(&mut archive, ncx_path)
+                    .ok_or_else(|| eyre!("table of contents {} is missing", ncx_path))?;
+                nav_points(ncx_path, &ncx)
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Package {
+            title: texts("dc:title").into_iter().next().unwrap_or_default(),
+            authors: texts("dc:creator"),
+            language: texts("dc:language")
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| String::from("en")),
+            identifier,
+            date: texts("dc:date").into_iter().next(),
+            description: texts("dc:description").into_iter().next(),
+            subjects: texts("dc:subject"),
+            metas,
+            page_progression: tags(&opf, "spine")
+                .into_iter()
+                .find_map(|attributes| attributes.get("page-progression-direction").cloned()),
+            documents,
+            stylesheets,
+            images,
+            cover,
+            toc,
+        })
+    }
+
+    /// Content of the `<meta>` named `name`
+    pub fn meta(&self, name: &str) -> Option<&str> {
+        self.metas
+            .iter()
+            .find(|(meta_name, _)| meta_name == name)
+            .map(|(_, content)| content.as_str())
+    }
+}
+
+/// Attributes and text of every `<name ...>text</name>` element in `xml`
+fn elements(xml: &str, name: &str) -> Vec<(HashMap<String, String>, String)> {
+    let element = Regex::new(&format!(
+        r"(?s)(<{0}(?:\s[^>]*)?>)(.*?)</{0}>",
+        regex::escape(name)
+    ))
+    .expect("This is a valid regexp");
+
+    element
+        .captures_iter(xml)
+        .map(|captures| {
+            (
+                tags(&captures[1], name).pop().unwrap_or_default(),
+                decode(captures[2].trim()),
+            )
+        })
+        .collect()
+}
+
+fn decode(text: &str) -> String {
+    html_escape::decode_html_entities(text).into_owned()
+}
+
+/// The table of contents of the NCX document at `ncx_path`
+fn nav_points(ncx_path: &str, ncx: &str) -> Vec<NavPoint> {
+    let token = Regex::new(
+        r#"(?s)<navPoint[\s>]|</navPoint>|<text>(.*?)</text>|<content\s[^>]*src="([^"]*)""#,
+    )
+    .expect("This is a valid regexp");
+
+    // The navPoints still open, innermost last
+    let mut open: Vec<NavPoint> = Vec::new();
+    let mut toc: Vec<NavPoint> = Vec::new();
+
+    for captures in token.captures_iter(ncx) {
+        let matched = &captures[0];
+
+        if matched.starts_with("<navPoint") {
+            open.push(NavPoint {
+                label: String::new(),
+                path: String::new(),
+                children: Vec::new(),
+            });
+        } else if matched == "</navPoint>" {
+            let Some(nav_point) = open.pop() else {
+                continue;
+            };
+            match open.last_mut() {
+                Some(parent) => parent.children.push(nav_point),
+                None => toc.push(nav_point),
+            }
+        } else if let (Some(label), Some(nav_point)) = (captures.get(1), open.last_mut()) {
+            nav_point.label = decode(label.as_str().trim());
+        } else if let (Some(src), Some(nav_point)) = (captures.get(2), open.last_mut()) {
+            let target = decode(src.as_str());
+            nav_point.path = resolve(ncx_path, target.split('#').next().unwrap_or_default());
+        }
+    }
+
+    toc
+}
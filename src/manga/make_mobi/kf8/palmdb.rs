@@ -0,0 +1,64 @@
+//! The Palm database every MOBI book is stored in: a header, a list of where
+//! each record starts, then the records.
+
+/// Length of the database header, before the record list
+const HEADER_LENGTH: usize = 78;
+
+/// Longest database name, which is followed by at least one zero
+const NAME_LENGTH: usize = 31;
+
+/// A database named after `title` holding `records`, dated `date` in seconds
+/// since 1970
+pub fn write(title: &str, date: u32, records: &[Vec<u8>]) -> Vec<u8> {
+    let mut db: Vec<u8> = Vec::new();
+
+    // Readers only show the name when the book has no title of its own, so it
+    // is kept to the characters every one of them accepts
+    let mut name: Vec<u8> = title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c as u8
+            } else {
+                b'_'
+            }
+        })
+        .take(NAME_LENGTH)
+        .collect();
+    name.resize(NAME_LENGTH + 1, 0);
+    db.extend(name);
+
+    // Attributes and version
+    db.extend([0; 4]);
+    // Creation and modification dates, never backed up
+    db.extend(date.to_be_bytes());
+    db.extend(date.to_be_bytes());
+    db.extend([0; 4]);
+    // Modification number, app info and sort info
+    db.extend([0; 12]);
+    db.extend(b"BOOK");
+    db.extend(b"MOBI");
+    // Seed of the record ids
+    db.extend(((2 * records.len()).saturating_sub(1) as u32).to_be_bytes());
+    // No next record list
+    db.extend([0; 4]);
+    db.extend((records.len() as u16).to_be_bytes());
+    debug_assert_eq!(db.len(), HEADER_LENGTH);
+
+    let mut offset = HEADER_LENGTH + 8 * records.len() + 2;
+    for (index, record) in records.iter().enumerate() {
+        db.extend((offset as u32).to_be_bytes());
+        // Attributes, then a three byte id
+        db.push(0);
+        db.extend(&((2 * index) as u32).to_be_bytes()[1..]);
+        offset += record.len();
+    }
+    // Gap before the first record
+    db.extend([0; 2]);
+
+    for record in records {
+        db.extend(record);
+    }
+
+    db
+}
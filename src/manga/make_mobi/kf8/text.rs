@@ -0,0 +1,165 @@
+//! Lays the documents of a package out as KF8 text.
+//!
+//! Each document is stored as a skeleton, the document with its body emptied,
+//! followed by a fragment holding the body. Readers put them back together
+//! through the skeleton and fragment tables.
+
+use std::collections::HashMap;
+
+use eyre::{eyre, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::package::Package;
+use super::to_base32;
+use crate::manga::make_mobi::epub_builder::resolve;
+
+/// A document with its body taken out
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    /// Position of the skeleton in the text
+    pub start: usize,
+    pub length: usize,
+}
+
+/// The body of a document, inserted back into its skeleton when read
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    /// Position in the rebuilt text the fragment is inserted at
+    pub insert_position: usize,
+    /// Which element of the skeleton the fragment goes into
+    pub selector: String,
+    /// Index of the skeleton
+    pub file_number: usize,
+    pub length: usize,
+}
+
+/// The text of a book: its documents, then each stylesheet as a flow of its own
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub bytes: Vec<u8>,
+    /// Start and end of each flow, the documents being flow 0
+    pub flows: Vec<(usize, usize)>,
+    /// One skeleton and one fragment per document, in reading order
+    pub skeletons: Vec<Skeleton>,
+    pub fragments: Vec<Fragment>,
+}
+
+impl Text {
+    pub fn new(package: &Package) -> Result<Text> {
+        // Where a link to a file of the package points once it is in the book
+        let mut targets: HashMap<&str, String> = HashMap::new();
+        for (index, image) in package.images.iter().enumerate() {
+            targets.insert(&image.path, embed_link(index, &image.media_type));
+        }
+        for (index, stylesheet) in package.stylesheets.iter().enumerate() {
+            targets.insert(
+                &stylesheet.path,
+                format!("kindle:flow:{}?mime=text/css", to_base32(index + 1, 4)),
+            );
+        }
+        for (index, document) in package.documents.iter().enumerate() {
+            targets.insert(
+                &document.path,
+                format!(
+                    "kindle:pos:fid:{}:off:{}",
+                    to_base32(index, 4),
+                    to_base32(0, 10)
+                ),
+            );
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut skeletons: Vec<Skeleton> = Vec::new();
+        let mut fragments: Vec<Fragment> = Vec::new();
+
+        for (index, document) in package.documents.iter().enumerate() {
+            let text = rewrite_links(&document.path, &document.text, &targets);
+
+            static BODY: Lazy<Regex> =
+                Lazy::new(|| Regex::new(r"<body(\s[^>]*)?>").expect("This is a valid regexp"));
+
+            let (body_open, body_close) = match (BODY.find(&text), text.rfind("</body>")) {
+                (Some(open), Some(close)) if open.end() <= close => (open, close),
+                _ => return Err(eyre!("{} has no body", document.path)),
+            };
+
+            // The fragment is found again by the aid of the element it goes into
+            let aid = to_base32(index, 1);
+            let skeleton = format!(
+                "{} aid=\"{}\">{}",
+                text[..body_open.end() - 1].trim_end_matches('/'),
+                aid,
+                &text[body_close..]
+            );
+            let insert_offset = skeleton.find("</body>").unwrap();
+            let fragment = &text[body_open.end()..body_close];
+
+            skeletons.push(Skeleton {
+                start: bytes.len(),
+                length: skeleton.len(),
+            });
+            fragments.push(Fragment {
+                insert_position: bytes.len() + insert_offset,
+                selector: format!("P-//*[@aid='{}']", aid),
+                file_number: index,
+                length: fragment.len(),
+            });
+
+            bytes.extend_from_slice(skeleton.as_bytes());
+            bytes.extend_from_slice(fragment.as_bytes());
+        }
+
+        let mut flows = vec![(0, bytes.len())];
+        for stylesheet in &package.stylesheets {
+            let start = bytes.len();
+            bytes.extend_from_slice(&stylesheet.bytes);
+            flows.push((start, bytes.len()));
+        }
+
+        Ok(Text {
+            bytes,
+            flows,
+            skeletons,
+            fragments,
+        })
+    }
+
+    /// Length of flow 0, the part of the text readers page through
+    pub fn documents_length(&self) -> usize {
+        self.flows[0].1
+    }
+}
+
+/// Link to the `index`th resource of the book
+pub fn embed_link(index: usize, media_type: &str) -> String {
+    format!(
+        "kindle:embed:{}?mime={}",
+        to_base32(index + 1, 4),
+        media_type
+    )
+}
+
+/// `text` of the document at `path` with every link into the package replaced
+/// by where it points in the book
+fn rewrite_links(path: &str, text: &str, targets: &HashMap<&str, String>) -> String {
+    static LINK: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"(\s(?:src|href|xlink:href)\s*=\s*")([^"]*)(")"#)
+            .expect("This is a valid regexp")
+    });
+
+    LINK.replace_all(text, |captures: &Captures| {
+        let link = &captures[2];
+        let target = link.split('#').next().unwrap_or_default();
+
+        if target.is_empty() || target.contains(':') {
+            return captures[0].to_string();
+        }
+
+        match targets.get(resolve(path, target).as_str()) {
+            Some(target) => format!("{}{}{}", &captures[1], target, &captures[3]),
+            None => captures[0].to_string(),
+        }
+    })
+    .into_owned()
+}
@@ -1,17 +1,82 @@
 use std::ffi::OsStr;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-#[allow(dead_code)]
-mod epub_builder;
-mod epub_to_mobi;
-
-use epub_builder::{EpubBuilder, EpubContent, ReferenceType, ZipLibrary};
+mod cbz;
+mod converter;
+#[allow(dead_code, unused_imports)]
+pub mod epub_builder;
+mod kf8;
+mod pdf;
+mod split;
+
+use chrono::{DateTime, TimeZone, Utc};
+pub use converter::{ConversionError, ConversionLog};
+pub use epub_builder::ValidationReport;
+use epub_builder::{
+    EpubBuilder, EpubContent, EpubVersion, FixedLayout, PageProgression, PageSpread, ReferenceType,
+    TocElement, ZipLibrary,
+};
 use handlebars::Handlebars;
-use image::GenericImageView;
 use serde_json::json;
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+use crate::assets::{self, Asset};
+use crate::manga::naming::{self, NameFields};
+use crate::manga::panels;
+use crate::manga::settings::{BuildSettings, ConverterSettings, OutputFormat, ReadingDirection};
+use crate::manga::{BuildError, Part};
+use crate::workspace::Workspace;
+
+// ─── Structs ─────────────────────────────────────────────────────────────────
+
+/// A chapter listed in the table of contents of a book
+#[derive(Debug, Clone)]
+pub struct TocChapter {
+    pub title: String,
+    /// Index into the book's images of the chapter's first page, the cover being `0`
+    pub first_page: usize,
+}
 
-use crate::assets::templates;
+/// What a book says about itself and the series it belongs to
+#[derive(Debug, Clone)]
+pub struct BookMetadata {
+    pub series: String,
+    pub author: String,
+    /// Position of the book in the series
+    pub series_index: Option<String>,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    /// Year the series was first published
+    pub year: Option<String>,
+    pub language: String,
+    pub mangadex_id: String,
+    /// MangaDex ids of the chapters in the book
+    pub chapter_ids: Vec<String>,
+    /// When the newest chapter in the book was published, in RFC 3339
+    pub updated_at: Option<String>,
+    /// Number of the volume the book is or belongs to
+    pub volume: Option<String>,
+    /// Number of the chapter, for books of a single chapter
+    pub chapter: Option<String>,
+    /// Which part of its volume or chapter the book is, if that was split
+    pub part: Option<Part>,
+}
+
+/// A finished book
+#[derive(Debug)]
+pub struct Book {
+    pub path: PathBuf,
+    /// What the converter warned about
+    pub log: ConversionLog,
+    /// Which part of its volume or chapter the book is, if that was split
+    pub part: Option<Part>,
+}
+
+/// How often parts are made smaller when, converted, they still are too large
+const SPLIT_ATTEMPTS: usize = 3;
 
 // ─── Functions ───────────────────────────────────────────────────────────────
 
@@ -19,8 +84,27 @@ pub fn get_extension_from_filename(filename: &PathBuf) -> Option<&str> {
     Path::new(filename).extension().and_then(OsStr::to_str)
 }
 
-fn read_as_bytes(file: &PathBuf) -> Vec<u8> {
-    fs::read(file).unwrap()
+/// Mime type of an image, `jpg` being the one extension that differs from its subtype
+fn image_mime_type(file_extension: &str) -> String {
+    match file_extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => String::from("image/jpeg"),
+        other => format!("image/{}", other),
+    }
+}
+
+/// Fixed-layout pages alternate sides, starting on the side a printed book
+/// read in `direction` opens on
+fn page_spread(page_index: usize, direction: ReadingDirection) -> PageSpread {
+    let (first, second) = match direction {
+        ReadingDirection::LeftToRight => (PageSpread::Right, PageSpread::Left),
+        ReadingDirection::RightToLeft => (PageSpread::Left, PageSpread::Right),
+    };
+
+    if page_index.is_multiple_of(2) {
+        first
+    } else {
+        second
+    }
 }
 
 fn render_template(html_data: &str, data: serde_json::Value) -> String {
@@ -30,23 +114,158 @@ fn render_template(html_data: &str, data: serde_json::Value) -> String {
 
 // ─── Make Epub ───────────────────────────────────────────────────────────────
 
+/// Content file of the page showing `images[page_index]`
+fn page_file_name(page_index: usize) -> String {
+    match page_index {
+        0 => String::from("cover.html"),
+        _ => format!("page_{}.html", page_index),
+    }
+}
+
+/// Titles the page at `page_index` after the chapter that starts on it, if any,
+/// listing the rest of the chapter's pages under it when the settings ask for that
+fn toc_entry<R: Read>(
+    page: EpubContent<R>,
+    page_index: usize,
+    page_count: usize,
+    toc_chapters: &[TocChapter],
+    settings: &BuildSettings,
+) -> EpubContent<R> {
+    let Some(position) = toc_chapters
+        .iter()
+        .position(|chapter| chapter.first_page == page_index)
+    else {
+        return page;
+    };
+
+    let mut page = page.title(toc_chapters[position].title.as_str());
+
+    if settings.toc_page_entries {
+        let next_chapter_page = toc_chapters
+            .get(position + 1)
+            .map_or(page_count, |chapter| chapter.first_page);
+
+        for page_index in page_index + 1..next_chapter_page {
+            page = page.child(TocElement::new(
+                page_file_name(page_index),
+                format!("Page {}", page_index),
+            ));
+        }
+    }
+
+    page
+}
+
+/// Identifier of a reproducible book: a name-based UUID of its MangaDex ids
+/// and build settings, so rebuilding it replaces the copy already on the Kindle.
+/// The converter and file name are left out, as they do not change the EPUB.
+fn book_uuid(metadata: &BookMetadata, settings: &BuildSettings) -> Uuid {
+    let settings = BuildSettings {
+        converter: ConverterSettings::default(),
+        file_name_template: String::new(),
+        ..settings.clone()
+    };
+    let part = metadata
+        .part
+        .map(|part| format!("#{}", part))
+        .unwrap_or_default();
+    let name = format!(
+        "https://mangadex.org/title/{}#{}{}/{}",
+        metadata.mangadex_id,
+        metadata.chapter_ids.join(","),
+        part,
+        serde_json::to_string(&settings).unwrap()
+    );
+
+    // Version 5: the SHA-1 of the namespace and name
+    let mut hasher = Sha1::new();
+    hasher.update(Uuid::NAMESPACE_URL.as_bytes());
+    hasher.update(name.as_bytes());
+
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hasher.finalize()[..16]);
+    uuid::Builder::from_sha1_bytes(bytes).into_uuid()
+}
+
+/// When a reproducible book was last modified: the newest chapter's
+/// publication, else the start of the series' first year, else the earliest
+/// time zip files can hold
+fn source_date(metadata: &BookMetadata) -> DateTime<Utc> {
+    metadata
+        .updated_at
+        .as_deref()
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|| {
+            let year = metadata.year.as_deref()?.parse().ok()?;
+            Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).single()
+        })
+        .unwrap_or_else(|| Utc.with_ymd_and_hms(1980, 1, 1, 0, 0, 0).unwrap())
+}
+
 // make chapter as epub
 fn make_epub(
     images: &Vec<PathBuf>,
+    toc_chapters: &[TocChapter],
     epub_file_path: &PathBuf,
-    author: &String,
     epub_title: &String,
+    metadata: &BookMetadata,
+    settings: &BuildSettings,
 ) {
     let css = r#"@charset "utf-8";a {text-decoration: none;}#toc ol {list-style-type: none;}img {display: block;width: 100%;object-fit: contain;}"#;
 
     let all_images = images.to_owned();
     // all_images.sort_by(|a, b| natord::compare(a.to_str().unwrap(), b.to_str().unwrap()));
 
-    let mut epub = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    // Pages are written to the file as they are added, a whole volume is never held in memory
+    let mut zip = ZipLibrary::to_file(epub_file_path).unwrap();
+    if settings.reproducible {
+        zip.set_modified_date(source_date(metadata));
+    }
+
+    let mut epub = EpubBuilder::new(zip).unwrap();
+
+    if settings.reproducible {
+        epub.set_uuid(book_uuid(metadata, settings))
+            .set_modified_date(source_date(metadata));
+    }
+
+    // Kindle books are made from EPUB 2, readers that take the book as is get a real EPUB 3
+    epub.epub_version(match settings.output_format {
+        OutputFormat::Epub => EpubVersion::V30,
+        _ => EpubVersion::V20,
+    });
+
+    epub.page_progression(match settings.reading_direction {
+        ReadingDirection::LeftToRight => PageProgression::Ltr,
+        ReadingDirection::RightToLeft => PageProgression::Rtl,
+    });
+
+    epub.fixed_layout(FixedLayout {
+        width: settings.profile.width,
+        height: settings.profile.height,
+        region_magnification: settings.panel_view,
+    });
 
     // Metadata
-    epub.metadata("author", author).unwrap();
+    epub.metadata("author", &metadata.author).unwrap();
     epub.metadata("title", epub_title).unwrap();
+    epub.metadata("lang", &metadata.language).unwrap();
+    epub.metadata("series", &metadata.series).unwrap();
+    if let Some(series_index) = &metadata.series_index {
+        epub.metadata("series_index", series_index).unwrap();
+    }
+    if let Some(description) = &metadata.description {
+        epub.metadata("description", description).unwrap();
+    }
+    for subject in metadata.subjects.iter() {
+        epub.metadata("subject", subject).unwrap();
+    }
+    if let Some(year) = &metadata.year {
+        epub.metadata("date", year).unwrap();
+    }
+    epub.metadata("identifier", format!("mangadex:{}", metadata.mangadex_id))
+        .unwrap();
 
     // stylesheet
     epub.stylesheet(css.as_bytes()).unwrap();
@@ -59,23 +278,20 @@ fn make_epub(
     // gets file extensions of cover image
     let file_extension = get_extension_from_filename(cover_image).unwrap();
 
-    // load cover image and convert to bytes < &[u8] >
-    let cover_as_bytes = &read_as_bytes(cover_image)[..];
-
     // add image to epub
     epub.add_cover_image(
         format!("image-0.{}", file_extension),
-        cover_as_bytes,
-        format!("image/{}", file_extension),
+        fs::File::open(cover_image).unwrap(),
+        image_mime_type(file_extension),
     )
     .unwrap();
 
-    // opens and gets dimensions of cover image
-    let (im_width, im_height) = image::open(cover_image).unwrap().dimensions();
+    // reads the dimensions of cover image from its header
+    let (im_width, im_height) = image::image_dimensions(cover_image).unwrap();
 
     // render the fields in cover.html
     let binding = render_template(
-        templates::COVER_HTML,
+        assets::text(Asset::CoverHtml),
         json!({"width": im_width, "height": im_height, "cover_path": format!("image-0.{}", file_extension)}),
     );
 
@@ -83,12 +299,17 @@ fn make_epub(
     let file_as_bytes = binding.as_bytes();
 
     // add cover.html to epub
-    epub.add_content(
-        EpubContent::new("cover.html", file_as_bytes)
+    epub.add_content(toc_entry(
+        EpubContent::new(page_file_name(0), file_as_bytes)
             .title("Cover")
             .reftype(ReferenceType::Cover)
-            .reftype(ReferenceType::Text),
-    )
+            .reftype(ReferenceType::Text)
+            .spread(page_spread(0, settings.reading_direction)),
+        0,
+        all_images.len(),
+        toc_chapters,
+        settings,
+    ))
     .unwrap();
 
     // ─── Add Images To Epub ──────────────────────────────────────────────
@@ -101,134 +322,623 @@ fn make_epub(
         // get file extension of image
         let file_extension = get_extension_from_filename(image).unwrap();
 
-        // convert image to bytes
-        let image_as_bytes = &read_as_bytes(image)[..];
-
         // add image to epub
         epub.add_resource(
             format!("image-{}.{}", index + 1, file_extension),
-            image_as_bytes,
-            format!("image/{}", file_extension),
+            fs::File::open(image).unwrap(),
+            image_mime_type(file_extension),
         )
         .unwrap();
 
-        // get image width and height
-        let (im_width, im_height) = image::open(image).unwrap().dimensions();
+        // get image width and height from its header
+        let (im_width, im_height) = image::image_dimensions(image).unwrap();
+
+        // the panels Kindle steps through in Panel View, found when the page was processed
+        let panels = if settings.panel_view {
+            panels::saved_regions(image)
+        } else {
+            Vec::new()
+        };
 
         // render template html to string
         let binding = render_template(
-            templates::PAGE_HTML,
-            json!({"width": im_width, "height": im_height, "image": format!("image-{}.{}",index + 1, file_extension)}),
+            assets::text(Asset::PageHtml),
+            json!({"width": im_width, "height": im_height, "image": format!("image-{}.{}",index + 1, file_extension), "panels": panels}),
         );
 
         // convert html string to bytes
         let file_as_bytes = binding.as_bytes();
 
-        // add chapter_{}.html to epub
+        let mut page = EpubContent::new(page_file_name(index + 1), file_as_bytes)
+            .spread(page_spread(index + 1, settings.reading_direction));
+
         if index == 0 {
-            epub.add_content(
-                EpubContent::new(format!("page_{}.html", index + 1), file_as_bytes)
-                    .title(format!("Page {}", index + 1))
-                    .reftype(ReferenceType::Text),
-            )
-            .unwrap();
-        } else {
-            epub.add_content(
-                EpubContent::new(format!("page_{}.html", index + 1), file_as_bytes)
-                    .title(format!("Page {}", index + 1)),
-            )
-            .unwrap();
+            page = page.reftype(ReferenceType::Text);
         }
-    }
 
-    epub.inline_toc();
-
-    let file = fs::File::create(epub_file_path).unwrap();
-    epub.generate(file).unwrap();
-}
-
-fn clean_up(images: &[PathBuf], epub_file_path: PathBuf) {
-    // Remove all images
-    for image in images.iter() {
-        if !image
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .eq("endofthisvolume.png")
-            && !image
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .eq("endofthischapter.png")
-            && fs::remove_file(image).is_err()
-        {
-            break;
-        }
+        epub.add_content(toc_entry(
+            page,
+            index + 1,
+            all_images.len(),
+            toc_chapters,
+            settings,
+        ))
+        .unwrap();
     }
 
-    // Remove epub
-    fs::remove_file(epub_file_path).unwrap();
+    // No inline toc, a page of text would break up a fixed-layout comic; readers
+    // show the nav document instead
+
+    epub.finish().unwrap();
 }
+
 // ─── Public Methods ──────────────────────────────────────────────────────────
 
-pub fn make_chapter(
+/// Builds the epub for `ebook_title` in `workspace`, named after `name` with
+/// the settings' file name template, validates it and, unless
+/// the book is wanted as an epub, converts it with the configured converter.
+/// Books wanted as CBZ or PDF are made straight from the images instead.
+/// Returns the path of the final book and what the converter warned about,
+/// or why the epub is broken or could not be converted.
+fn build_book(
+    workspace: &Workspace,
     images: &Vec<PathBuf>,
-    manga_title: &String,
-    volume_title: &String,
-    chapter_title: &String,
-    author: &String,
-) -> PathBuf {
-    let mut ebook_title = format!(
-        "{} volume {} chapter {}",
-        manga_title, volume_title, chapter_title
+    toc_chapters: &[TocChapter],
+    ebook_title: &str,
+    name: &NameFields,
+    metadata: &BookMetadata,
+    settings: &BuildSettings,
+) -> Result<(PathBuf, ConversionLog), BuildError> {
+    let ebook_title = ebook_title.to_string();
+    let file_stem = naming::file_stem(&settings.file_name_template, name);
+
+    // Formats made straight from the images, without an epub
+    let written = |path: PathBuf, writer: &'static str, result: eyre::Result<()>| match result {
+        Ok(()) => Ok((path, ConversionLog::default())),
+        Err(error) => Err(BuildError::Conversion {
+            book_title: ebook_title.clone(),
+            error: ConversionError::Failed {
+                converter: writer,
+                status: None,
+                errors: error.chain().map(|cause| cause.to_string()).collect(),
+            },
+        }),
+    };
+    let date = settings.reproducible.then(|| source_date(metadata));
+
+    match settings.output_format {
+        OutputFormat::Cbz => {
+            let cbz_file_path = workspace.file(format!("{}.cbz", &file_stem));
+            let comic_info = cbz::comic_info(
+                metadata,
+                &ebook_title,
+                images.len(),
+                settings.reading_direction,
+            );
+            let result = cbz::write(images, &comic_info, &cbz_file_path, date);
+            return written(cbz_file_path, "CBZ writer", result);
+        }
+        OutputFormat::Pdf => {
+            let pdf_file_path = workspace.file(format!("{}.pdf", &file_stem));
+            let book = pdf::PdfBook {
+                title: &ebook_title,
+                metadata,
+                toc_chapters,
+                profile: &settings.profile,
+                direction: settings.reading_direction,
+                date,
+            };
+            let result = pdf::write(images, &book, &pdf_file_path);
+            return written(pdf_file_path, "PDF writer", result);
+        }
+        OutputFormat::Mobi | OutputFormat::Epub => {}
+    }
+
+    let epub_file_path = workspace.file(format!("{}.epub", &file_stem));
+
+    make_epub(
+        images,
+        toc_chapters,
+        &epub_file_path,
+        &ebook_title,
+        metadata,
+        settings,
     );
 
-    // Make epub path legal
-    ebook_title = ebook_title.replace(':', " ");
+    let report = epub_builder::validate(fs::File::open(&epub_file_path).unwrap()).unwrap();
 
-    let epub_file_path = PathBuf::from(format!("temp\\{}.epub", &ebook_title));
+    if report.has_errors() {
+        return Err(BuildError::InvalidEpub {
+            book_title: ebook_title,
+            report,
+        });
+    }
 
-    make_epub(images, &epub_file_path, author, &ebook_title);
+    if !report.issues.is_empty() {
+        log::warn!("{} has minor issues:\n{}", ebook_title, report);
+    }
 
-    let mobi_file_name = format!("{}.mobi", ebook_title);
+    let converter = converter::from_settings(settings.output_format, &settings.converter);
+    let book_file_path = workspace.file(format!("{}.{}", file_stem, converter.extension()));
 
-    epub_to_mobi::convert(&epub_file_path, &mobi_file_name);
+    match converter.convert(&epub_file_path, &book_file_path) {
+        Ok(log) => Ok((book_file_path, log)),
+        Err(error) => Err(BuildError::Conversion {
+            book_title: ebook_title,
+            error,
+        }),
+    }
+}
 
-    let mobi_file_path = PathBuf::from(format!("temp\\{}", &mobi_file_name));
+/// Builds the book for `ebook_title` like [`build_book`], split into parts if
+/// it is larger than the settings allow. How large a book gets is guessed
+/// from its pages first, and parts that still are too large once converted
+/// are made smaller.
+fn make_book(
+    workspace: &Workspace,
+    images: &Vec<PathBuf>,
+    toc_chapters: &[TocChapter],
+    ebook_title: &str,
+    name: &NameFields,
+    metadata: &BookMetadata,
+    settings: &BuildSettings,
+) -> Result<Vec<Book>, BuildError> {
+    let whole = || {
+        build_book(
+            workspace,
+            images,
+            toc_chapters,
+            ebook_title,
+            name,
+            metadata,
+            settings,
+        )
+        .map(|(path, log)| Book {
+            path,
+            log,
+            part: None,
+        })
+    };
+    let file_size = |book: &Book| fs::metadata(&book.path).map_or(0, |data| data.len());
+
+    let Some(max_bytes) = settings.max_book_bytes else {
+        return Ok(vec![whole()?]);
+    };
+    let warn_if_too_large = |book: &mut Book| {
+        if file_size(book) > max_bytes {
+            book.log.warnings.push(format!(
+                "still larger than {} MB after splitting",
+                max_bytes / 1024 / 1024
+            ));
+        }
+    };
+
+    let page_sizes: Vec<u64> = images
+        .iter()
+        .map(|image| fs::metadata(image).map_or(0, |data| data.len()))
+        .collect();
+    let pages_size: u64 = page_sizes.iter().sum();
+
+    // Parts are planned to hold `budget` bytes of pages, which is made smaller
+    // by as much as the converter made the largest of them grow, and always by
+    // some, so that they end up fitting
+    let shrink = |budget: u64, pages_size: u64, book_size: u64| {
+        let fitting = u128::from(max_bytes) * u128::from(pages_size) / u128::from(book_size);
+        (fitting as u64).min(budget - budget / 10)
+    };
+
+    let mut budget = max_bytes;
+    // Kept in case the pages cannot be split after all
+    let mut whole_book = None;
+    if pages_size <= max_bytes {
+        let book = whole()?;
+        let book_size = file_size(&book);
+        if book_size <= max_bytes {
+            return Ok(vec![book]);
+        }
+        budget = shrink(budget, pages_size, book_size);
+        whole_book = Some(book);
+    }
 
-    assert!(&mobi_file_path.is_file());
+    let mut parts = Vec::new();
+    for _ in 0..SPLIT_ATTEMPTS {
+        let plan = split::plan(&page_sizes, toc_chapters, budget);
+        // Too few pages to split
+        if plan.len() == 1 {
+            let mut book = match whole_book {
+                Some(book) => book,
+                None => whole()?,
+            };
+            warn_if_too_large(&mut book);
+            return Ok(vec![book]);
+        }
 
-    clean_up(images, epub_file_path);
+        parts = Vec::new();
+        for (index, pages) in plan.iter().enumerate() {
+            let part = Part {
+                number: index + 1,
+                count: plan.len(),
+            };
+            let part_name = format!("{} of {}", part.number, part.count);
+
+            let (path, log) = build_book(
+                workspace,
+                &split::part_images(images, pages),
+                &split::part_toc(toc_chapters, pages),
+                &format!("{} ({})", ebook_title, part),
+                &NameFields {
+                    part: Some(&part_name),
+                    ..*name
+                },
+                &BookMetadata {
+                    part: Some(part),
+                    ..metadata.clone()
+                },
+                settings,
+            )?;
+            parts.push(Book {
+                path,
+                log,
+                part: Some(part),
+            });
+        }
+
+        let largest = parts
+            .iter()
+            .zip(plan.iter())
+            .map(|(book, pages)| (split::part_size(&page_sizes, pages), file_size(book)))
+            .filter(|(_, book_size)| *book_size > max_bytes)
+            .max_by_key(|(pages_size, book_size)| {
+                u128::from(*book_size) * 1000 / u128::from((*pages_size).max(1))
+            });
+
+        match largest {
+            Some((pages_size, book_size)) => budget = shrink(budget, pages_size, book_size),
+            None => return Ok(parts),
+        }
+    }
 
-    mobi_file_path
+    for book in parts.iter_mut() {
+        warn_if_too_large(book);
+    }
+
+    Ok(parts)
+}
+
+pub fn make_chapter(
+    workspace: &Workspace,
+    images: &Vec<PathBuf>,
+    toc_chapters: &[TocChapter],
+    metadata: &BookMetadata,
+    volume_title: &String,
+    chapter_title: &String,
+    settings: &BuildSettings,
+) -> Result<Vec<Book>, BuildError> {
+    let ebook_title = format!(
+        "{} volume {} chapter {}",
+        metadata.series, volume_title, chapter_title
+    );
+
+    let name = NameFields {
+        series: &metadata.series,
+        volume: volume_title,
+        chapter: Some(chapter_title),
+        chapters: None,
+        part: None,
+    };
+
+    make_book(
+        workspace,
+        images,
+        toc_chapters,
+        &ebook_title,
+        &name,
+        metadata,
+        settings,
+    )
 }
 
 pub fn make_volume(
+    workspace: &Workspace,
     images: &Vec<PathBuf>,
-    manga_title: &String,
+    toc_chapters: &[TocChapter],
+    metadata: &BookMetadata,
     volume_title: &String,
-    author: &String,
-) -> PathBuf {
-    let mut ebook_title = format!("{} volume {}", manga_title, volume_title);
+    settings: &BuildSettings,
+) -> Result<Vec<Book>, BuildError> {
+    let ebook_title = format!("{} volume {}", metadata.series, volume_title);
+    let name = NameFields {
+        series: &metadata.series,
+        volume: volume_title,
+        chapter: None,
+        chapters: None,
+        part: None,
+    };
+
+    make_book(
+        workspace,
+        images,
+        toc_chapters,
+        &ebook_title,
+        &name,
+        metadata,
+        settings,
+    )
+}
 
-    // Make epub path legal
-    ebook_title = ebook_title.replace(':', " ");
+/// Builds a book of chapters from any volumes, `bundle_title` being how the
+/// bundle is listed and `chapter_span` the numbers of its first and last chapter.
+/// Its name has no volume, as the chapters can be from several.
+pub fn make_bundle(
+    workspace: &Workspace,
+    images: &Vec<PathBuf>,
+    toc_chapters: &[TocChapter],
+    metadata: &BookMetadata,
+    bundle_title: &str,
+    chapter_span: &str,
+    settings: &BuildSettings,
+) -> Result<Vec<Book>, BuildError> {
+    let ebook_title = format!("{} {}", metadata.series, bundle_title);
+    let name = NameFields {
+        series: &metadata.series,
+        volume: "",
+        chapter: None,
+        chapters: Some(chapter_span),
+        part: None,
+    };
+
+    make_book(
+        workspace,
+        images,
+        toc_chapters,
+        &ebook_title,
+        &name,
+        metadata,
+        settings,
+    )
+}
 
-    let epub_file_path = PathBuf::from(format!("temp\\{}.epub", &ebook_title));
+#[test]
+fn toc_lists_chapters_with_their_pages() {
+    let toc_chapters = vec![
+        TocChapter {
+            title: String::from("Chapter 1"),
+            first_page: 1,
+        },
+        TocChapter {
+            title: String::from("Chapter 2: The End"),
+            first_page: 4,
+        },
+    ];
+    let settings = BuildSettings {
+        toc_page_entries: true,
+        ..BuildSettings::default()
+    };
+    let entry = |page_index| {
+        toc_entry(
+            EpubContent::new(page_file_name(page_index), b"".as_ref()),
+            page_index,
+            6,
+            &toc_chapters,
+            &settings,
+        )
+        .toc
+    };
+
+    let first = entry(1);
+    assert_eq!(first.title, "Chapter 1");
+    assert_eq!(
+        first
+            .children
+            .iter()
+            .map(|page| page.url.as_str())
+            .collect::<Vec<_>>(),
+        vec!["page_2.html", "page_3.html"]
+    );
 
-    make_epub(images, &epub_file_path, author, &ebook_title);
+    // Pages inside a chapter are not entries of their own
+    assert!(entry(2).title.is_empty());
 
-    let mobi_file_name = format!("{}.mobi", ebook_title);
+    let last = entry(4);
+    assert_eq!(last.title, "Chapter 2: The End");
+    assert_eq!(last.children.len(), 1);
+}
 
-    epub_to_mobi::convert(&epub_file_path, &mobi_file_name);
+#[test]
+fn reproducible_books_are_identified_by_their_sources() {
+    let metadata = BookMetadata {
+        series: String::from("Spy x Family"),
+        author: String::from("KindleMangaReader"),
+        series_index: Some(String::from("2")),
+        description: None,
+        subjects: Vec::new(),
+        year: Some(String::from("2019")),
+        language: String::from("en"),
+        mangadex_id: String::from("6b958848"),
+        chapter_ids: vec![String::from("a1"), String::from("a2")],
+        updated_at: Some(String::from("2021-06-02T10:30:04+02:00")),
+        volume: Some(String::from("2")),
+        chapter: None,
+        part: None,
+    };
+    let settings = BuildSettings::default();
+
+    let uuid = book_uuid(&metadata, &settings);
+    assert_eq!(uuid, book_uuid(&metadata.clone(), &settings.clone()));
+    assert_eq!(uuid.get_version(), Some(uuid::Version::Sha1));
+
+    let panel_view = BuildSettings {
+        panel_view: true,
+        ..settings.clone()
+    };
+    assert_ne!(uuid, book_uuid(&metadata, &panel_view));
+
+    let other_chapters = BookMetadata {
+        chapter_ids: vec![String::from("a1")],
+        ..metadata.clone()
+    };
+    assert_ne!(uuid, book_uuid(&other_chapters, &settings));
+
+    assert_eq!(
+        source_date(&metadata),
+        Utc.with_ymd_and_hms(2021, 6, 2, 8, 30, 4).unwrap()
+    );
+    let undated = BookMetadata {
+        updated_at: None,
+        ..metadata
+    };
+    assert_eq!(
+        source_date(&undated),
+        Utc.with_ymd_and_hms(2019, 1, 1, 0, 0, 0).unwrap()
+    );
+}
 
-    let mobi_file_path = PathBuf::from(format!("temp\\{}", mobi_file_name));
+#[test]
+fn books_larger_than_allowed_are_split_into_parts() {
+    let workspace = Workspace::new().unwrap();
+    // A cover and two chapters of 4 pages, stored in the CBZ as they are
+    let images: Vec<PathBuf> = (0..9)
+        .map(|page_index| {
+            let image = workspace.file(format!("page {}.jpg", page_index));
+            fs::write(&image, vec![page_index as u8; 1000]).unwrap();
+            image
+        })
+        .collect();
+    let toc_chapters = vec![
+        TocChapter {
+            title: String::from("Chapter 1"),
+            first_page: 1,
+        },
+        TocChapter {
+            title: String::from("Chapter 2"),
+            first_page: 5,
+        },
+    ];
+    let metadata = BookMetadata {
+        series: String::from("Spy x Family"),
+        author: String::from("KindleMangaReader"),
+        series_index: Some(String::from("2")),
+        description: None,
+        subjects: Vec::new(),
+        year: None,
+        language: String::from("en"),
+        mangadex_id: String::from("6b958848"),
+        chapter_ids: vec![String::from("a1"), String::from("a2")],
+        updated_at: None,
+        volume: Some(String::from("2")),
+        chapter: None,
+        part: None,
+    };
+    // The pages of either chapter with the cover fit, but not with the zip's
+    // own records, so the parts are made smaller once they are written
+    let settings = BuildSettings {
+        output_format: OutputFormat::Cbz,
+        max_book_bytes: Some(5000),
+        ..BuildSettings::default()
+    };
+
+    let books = make_volume(
+        &workspace,
+        &images,
+        &toc_chapters,
+        &metadata,
+        &String::from("2"),
+        &settings,
+    )
+    .unwrap();
 
-    assert!(&mobi_file_path.is_file());
+    assert!(books.len() > 2);
+    for (index, book) in books.iter().enumerate() {
+        assert_eq!(
+            book.part,
+            Some(Part {
+                number: index + 1,
+                count: books.len()
+            })
+        );
+        assert!(fs::metadata(&book.path).unwrap().len() <= 5000);
+        assert!(book.log.warnings.is_empty());
+    }
+    assert_eq!(
+        books[0].path.file_name().unwrap().to_str().unwrap(),
+        format!("Spy x Family volume 02 part 1 of {}.cbz", books.len())
+    );
 
-    clean_up(images, epub_file_path);
+    let unlimited = BuildSettings {
+        max_book_bytes: None,
+        ..settings
+    };
+    let books = make_volume(
+        &workspace,
+        &images,
+        &toc_chapters,
+        &metadata,
+        &String::from("2"),
+        &unlimited,
+    )
+    .unwrap();
+    assert_eq!(books.len(), 1);
+    assert_eq!(books[0].part, None);
+}
 
-    mobi_file_path
+#[test]
+fn reproducible_books_are_byte_identical() {
+    let metadata = BookMetadata {
+        series: String::from("Spy x Family"),
+        author: String::from("KindleMangaReader"),
+        series_index: Some(String::from("2")),
+        description: Some(String::from("Twilight & Yor")),
+        subjects: vec![String::from("Action")],
+        year: Some(String::from("2019")),
+        language: String::from("en"),
+        mangadex_id: String::from("6b958848"),
+        chapter_ids: vec![String::from("a1")],
+        updated_at: Some(String::from("2021-06-02T10:30:04+02:00")),
+        volume: Some(String::from("2")),
+        chapter: None,
+        part: None,
+    };
+    let toc_chapters = vec![TocChapter {
+        title: String::from("Chapter 1"),
+        first_page: 1,
+    }];
+
+    // Each build gets its own workspace, as it does when a book is built again later
+    let build = |output_format: OutputFormat| {
+        let workspace = Workspace::new().unwrap();
+        let images: Vec<PathBuf> = (0..3u8)
+            .map(|page_index| {
+                let image = workspace.file(format!("page {}.jpg", page_index));
+                image::RgbImage::from_pixel(60, 80, image::Rgb([page_index * 100, 0, 0]))
+                    .save(&image)
+                    .unwrap();
+                image
+            })
+            .collect();
+        let settings = BuildSettings {
+            output_format,
+            reproducible: true,
+            ..BuildSettings::default()
+        };
+
+        let books = make_volume(
+            &workspace,
+            &images,
+            &toc_chapters,
+            &metadata,
+            &String::from("2"),
+            &settings,
+        )
+        .unwrap();
+        assert_eq!(books.len(), 1);
+        fs::read(&books[0].path).unwrap()
+    };
+
+    for output_format in [OutputFormat::Epub, OutputFormat::Cbz, OutputFormat::Mobi] {
+        assert!(
+            build(output_format) == build(output_format),
+            "{:?} books differ",
+            output_format
+        );
+    }
 }
@@ -0,0 +1,423 @@
+//! Writes books as PDF: one page per image, sized for the device profile, an
+//! outline entry per chapter and the series metadata in the document info.
+//!
+//! Objects are numbered up front, so each page can be written and its image
+//! dropped before the next one is read: the catalog, page tree, document info
+//! and outline come first, then three objects per page (the page, its content
+//! and its image), then the outline items.
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use flate2::write::ZlibEncoder;
+use image::codecs::jpeg::JpegDecoder;
+use image::{ColorType, ImageDecoder};
+
+use super::{BookMetadata, TocChapter};
+use crate::manga::settings::{DeviceProfile, ReadingDirection};
+
+/// Pixels per inch the profiles' screens are assumed to have, which makes the
+/// A4 profile exactly A4
+const PIXELS_PER_INCH: f64 = 300.0;
+
+const CATALOG: usize = 1;
+const PAGE_TREE: usize = 2;
+const INFO: usize = 3;
+const OUTLINE: usize = 4;
+const FIRST_PAGE: usize = 5;
+
+/// What a PDF is made of besides its pages
+pub struct PdfBook<'a> {
+    pub title: &'a str,
+    pub metadata: &'a BookMetadata,
+    pub toc_chapters: &'a [TocChapter],
+    pub profile: &'a DeviceProfile,
+    pub direction: ReadingDirection,
+    /// Recorded as the creation date, the current time if there is none
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// Writes `images`, the cover first, as the PDF at `pdf_path`
+pub fn write(images: &[PathBuf], book: &PdfBook, pdf_path: &Path) -> Result<()> {
+    let file = fs::File::create(pdf_path)
+        .wrap_err_with(|| format!("could not create {}", pdf_path.display()))?;
+    let mut pdf = PdfWriter::new(BufWriter::new(file))?;
+
+    let page_number = |page_index: usize| FIRST_PAGE + 3 * page_index;
+    let first_outline_item = page_number(images.len());
+    let chapters: Vec<&TocChapter> = book
+        .toc_chapters
+        .iter()
+        .filter(|chapter| chapter.first_page < images.len())
+        .collect();
+
+    let points = |pixels: u32| pixels as f64 * 72.0 / PIXELS_PER_INCH;
+    let (page_width, page_height) = (points(book.profile.width), points(book.profile.height));
+
+    // ─── Pages ───────────────────────────────────────────────────────────
+
+    for (page_index, image) in images.iter().enumerate() {
+        let number = page_number(page_index);
+        let embedded = embed(image)?;
+
+        // The image fills as much of the page as it can and is centred on it
+        let scale = (page_width / embedded.width as f64).min(page_height / embedded.height as f64);
+        let (width, height) = (
+            embedded.width as f64 * scale,
+            embedded.height as f64 * scale,
+        );
+        let content = format!(
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im0 Do Q",
+            width,
+            height,
+            (page_width - width) / 2.0,
+            (page_height - height) / 2.0
+        );
+
+        pdf.object(
+            number,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                PAGE_TREE,
+                page_width,
+                page_height,
+                number + 2,
+                number + 1
+            ),
+        )?;
+        pdf.stream(number + 1, "", content.as_bytes())?;
+        pdf.stream(
+            number + 2,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} \
+                 /BitsPerComponent 8 /Filter {}",
+                embedded.width, embedded.height, embedded.color_space, embedded.filter
+            ),
+            &embedded.data,
+        )?;
+    }
+
+    // ─── Outline ─────────────────────────────────────────────────────────
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        let mut item = format!(
+            "<< /Title {} /Parent {} 0 R /Dest [{} 0 R /Fit]",
+            text_string(&chapter.title),
+            OUTLINE,
+            page_number(chapter.first_page)
+        );
+        if index > 0 {
+            item.push_str(&format!(" /Prev {} 0 R", first_outline_item + index - 1));
+        }
+        if index + 1 < chapters.len() {
+            item.push_str(&format!(" /Next {} 0 R", first_outline_item + index + 1));
+        }
+        item.push_str(" >>");
+        pdf.object(first_outline_item + index, &item)?;
+    }
+
+    pdf.object(
+        OUTLINE,
+        &match chapters.len() {
+            0 => String::from("<< /Type /Outlines /Count 0 >>"),
+            count => format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                first_outline_item,
+                first_outline_item + count - 1,
+                count
+            ),
+        },
+    )?;
+
+    // ─── Document ────────────────────────────────────────────────────────
+
+    let kids: Vec<String> = (0..images.len())
+        .map(|page_index| format!("{} 0 R", page_number(page_index)))
+        .collect();
+    pdf.object(
+        PAGE_TREE,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            images.len()
+        ),
+    )?;
+
+    let mut info = format!(
+        "<< /Title {} /Author {} /Creator (KindleMangaReader) /Producer (KindleMangaReader) \
+         /CreationDate {}",
+        text_string(book.title),
+        text_string(&book.metadata.author),
+        pdf_date(book.date.unwrap_or_else(Utc::now))
+    );
+    if let Some(description) = &book.metadata.description {
+        info.push_str(&format!(" /Subject {}", text_string(description)));
+    }
+    if !book.metadata.subjects.is_empty() {
+        info.push_str(&format!(
+            " /Keywords {}",
+            text_string(&book.metadata.subjects.join(", "))
+        ));
+    }
+    info.push_str(" >>");
+    pdf.object(INFO, &info)?;
+
+    // Viewers show pages side by side in the order the book is read in
+    let direction = match book.direction {
+        ReadingDirection::RightToLeft => "/R2L",
+        ReadingDirection::LeftToRight => "/L2R",
+    };
+    pdf.object(
+        CATALOG,
+        &format!(
+            "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode {} \
+             /ViewerPreferences << /Direction {} /DisplayDocTitle true >> /Lang {} >>",
+            PAGE_TREE,
+            OUTLINE,
+            if chapters.is_empty() {
+                "/UseNone"
+            } else {
+                "/UseOutlines"
+            },
+            direction,
+            text_string(&book.metadata.language)
+        ),
+    )?;
+
+    pdf.finish(first_outline_item + chapters.len())
+        .wrap_err_with(|| format!("could not write {}", pdf_path.display()))
+}
+
+// ─── Images ──────────────────────────────────────────────────────────────────
+
+/// An image as a PDF image XObject holds it
+struct Embedded {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+/// JPEG pages are embedded as they are, anything else is decoded and deflated
+fn embed(image_path: &Path) -> Result<Embedded> {
+    let bytes = fs::read(image_path)
+        .wrap_err_with(|| format!("could not read {}", image_path.display()))?;
+
+    if let Ok(decoder) = JpegDecoder::new(bytes.as_slice()) {
+        let (width, height) = decoder.dimensions();
+        let color_space = match decoder.color_type() {
+            ColorType::L8 => Some("/DeviceGray"),
+            ColorType::Rgb8 => Some("/DeviceRGB"),
+            // CMYK JPEGs are decoded to RGB below instead
+            _ => None,
+        };
+        if let Some(color_space) = color_space {
+            return Ok(Embedded {
+                width,
+                height,
+                color_space,
+                filter: "/DCTDecode",
+                data: bytes,
+            });
+        }
+    }
+
+    let image = image::load_from_memory(&bytes)
+        .wrap_err_with(|| format!("could not decode {}", image_path.display()))?;
+    let (color_space, pixels) = match image.color() {
+        ColorType::L8 | ColorType::La8 | ColorType::L16 | ColorType::La16 => {
+            ("/DeviceGray", image.to_luma8().into_raw())
+        }
+        _ => ("/DeviceRGB", image.to_rgb8().into_raw()),
+    };
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&pixels)?;
+
+    Ok(Embedded {
+        width: image.width(),
+        height: image.height(),
+        color_space,
+        filter: "/FlateDecode",
+        data: encoder.finish()?,
+    })
+}
+
+// ─── Writing Objects ─────────────────────────────────────────────────────────
+
+/// Writes numbered objects, remembering where each starts for the xref table
+struct PdfWriter<W: Write> {
+    out: W,
+    position: usize,
+    /// Offset of each object, by number
+    offsets: Vec<Option<usize>>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(out: W) -> io::Result<PdfWriter<W>> {
+        let mut pdf = PdfWriter {
+            out,
+            position: 0,
+            offsets: Vec::new(),
+        };
+        // The binary comment tells transfer tools the file is not text
+        pdf.write(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n")?;
+        Ok(pdf)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn start(&mut self, number: usize) -> io::Result<()> {
+        if self.offsets.len() <= number {
+            self.offsets.resize(number + 1, None);
+        }
+        self.offsets[number] = Some(self.position);
+        self.write(format!("{} 0 obj\n", number).as_bytes())
+    }
+
+    fn object(&mut self, number: usize, body: &str) -> io::Result<()> {
+        self.start(number)?;
+        self.write(body.as_bytes())?;
+        self.write(b"\nendobj\n")
+    }
+
+    /// A stream object, `dictionary` being the entries besides its length
+    fn stream(&mut self, number: usize, dictionary: &str, data: &[u8]) -> io::Result<()> {
+        self.start(number)?;
+        self.write(format!("<< {} /Length {} >>\nstream\n", dictionary, data.len()).as_bytes())?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    /// Writes the xref table and trailer of the `object_count` objects
+    fn finish(mut self, object_count: usize) -> io::Result<()> {
+        let xref = self.position;
+        self.offsets.resize(object_count, None);
+
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", object_count);
+        for offset in &self.offsets[1..] {
+            match offset {
+                Some(offset) => table.push_str(&format!("{:010} 00000 n \n", offset)),
+                None => table.push_str("0000000000 65535 f \n"),
+            }
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            object_count, CATALOG, INFO, xref
+        ));
+
+        self.write(table.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+/// `text` as a PDF text string, in UTF-16 so any title survives
+fn text_string(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+fn pdf_date(date: DateTime<Utc>) -> String {
+    format!("(D:{}Z)", date.format("%Y%m%d%H%M%S"))
+}
+
+#[test]
+fn pdf_has_a_page_per_image_and_an_outline_entry_per_chapter() {
+    use crate::workspace::Workspace;
+
+    let workspace = Workspace::new().unwrap();
+    let cover = workspace.file("cover.jpg");
+    image::DynamicImage::new_rgb8(124, 175)
+        .save(&cover)
+        .unwrap();
+    let page = workspace.file("page.png");
+    image::DynamicImage::new_luma8(124, 150)
+        .save(&page)
+        .unwrap();
+
+    let metadata = BookMetadata {
+        series: String::from("Spy x Family"),
+        author: String::from("KindleMangaReader"),
+        series_index: Some(String::from("2")),
+        description: Some(String::from("Twilight (a spy)")),
+        subjects: vec![String::from("Action")],
+        year: Some(String::from("2019")),
+        language: String::from("en"),
+        mangadex_id: String::from("6b958848"),
+        chapter_ids: Vec::new(),
+        updated_at: None,
+        volume: Some(String::from("2")),
+        chapter: None,
+        part: None,
+    };
+    let toc_chapters = vec![
+        TocChapter {
+            title: String::from("Chapter 1"),
+            first_page: 1,
+        },
+        TocChapter {
+            title: String::from("Chapter 2: ミッション"),
+            first_page: 2,
+        },
+    ];
+    let book = PdfBook {
+        title: "Spy x Family volume 2",
+        metadata: &metadata,
+        toc_chapters: &toc_chapters,
+        profile: &DeviceProfile::default(),
+        direction: ReadingDirection::RightToLeft,
+        date: None,
+    };
+    let pdf_path = workspace.file("book.pdf");
+    write(&[cover, page.clone(), page], &book, &pdf_path).unwrap();
+
+    let pdf = fs::read(&pdf_path).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with(b"%PDF-1.7"));
+    assert!(text.ends_with("%%EOF\n"));
+
+    // Every object is where the xref table says it is
+    let startxref = text.rsplit("startxref\n").next().unwrap();
+    let xref: usize = startxref.lines().next().unwrap().parse().unwrap();
+    let xref = String::from_utf8_lossy(&pdf[xref..]);
+    assert!(xref.starts_with("xref\n0 16\n"));
+    let objects: Vec<&str> = xref
+        .lines()
+        .skip(3)
+        .take_while(|line| line.ends_with(" n "))
+        .collect();
+    assert_eq!(objects.len(), 4 + 3 * 3 + 2);
+    for (index, entry) in objects.iter().enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+    }
+
+    // A4, with the JPEG cover kept as it is
+    assert_eq!(text.matches("/MediaBox [0 0 595.20 841.92]").count(), 3);
+    assert_eq!(text.matches("/DCTDecode").count(), 1);
+    assert_eq!(
+        text.matches("/ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode")
+            .count(),
+        2
+    );
+    assert!(text.contains("/Count 3 >>"));
+    assert!(text.contains("/Type /Outlines /First 14 0 R /Last 15 0 R /Count 2"));
+    assert!(text.contains(&format!(
+        "/Title {} /Parent 4 0 R /Dest [11 0 R /Fit]",
+        text_string("Chapter 2: ミッション")
+    )));
+    assert!(text.contains("/Direction /R2L"));
+}
@@ -0,0 +1,149 @@
+//! Splits books too large for the converter, email or the Kindle into parts.
+//!
+//! Parts end where a chapter starts if they can, and between pages where a
+//! single chapter is too large. Every part after the first begins with the
+//! book's cover, so each looks like a book of its own on the Kindle.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use super::TocChapter;
+
+/// Divides the book of pages with sizes `page_sizes`, the cover first, into
+/// runs of pages that are at most `budget` bytes with the cover. A page larger
+/// than that on its own still gets a part.
+pub fn plan(page_sizes: &[u64], toc_chapters: &[TocChapter], budget: u64) -> Vec<Range<usize>> {
+    let page_count = page_sizes.len();
+    let size = |pages: Range<usize>| part_size(page_sizes, &pages);
+
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    while start < page_count {
+        if size(start..page_count) <= budget {
+            parts.push(start..page_count);
+            break;
+        }
+
+        // The first part holds more than the cover
+        let first_end = start.max(1) + 1;
+
+        let chapter_end = toc_chapters
+            .iter()
+            .map(|chapter| chapter.first_page)
+            .filter(|&first_page| first_page >= first_end && first_page < page_count)
+            .filter(|&first_page| size(start..first_page) <= budget)
+            .max();
+
+        let end = chapter_end.unwrap_or_else(|| {
+            (first_end..page_count)
+                .take_while(|&end| size(start..end) <= budget)
+                .last()
+                .unwrap_or(first_end.min(page_count))
+        });
+
+        parts.push(start..end);
+        start = end;
+    }
+
+    parts
+}
+
+/// Size of the pages of the part made of `pages`, its cover included
+pub fn part_size(page_sizes: &[u64], pages: &Range<usize>) -> u64 {
+    let cover = match pages.start {
+        0 => 0,
+        _ => page_sizes[0],
+    };
+    cover + page_sizes[pages.clone()].iter().sum::<u64>()
+}
+
+/// Images of the part made of `pages`, with the cover in front if the part
+/// does not start with it
+pub fn part_images(images: &[PathBuf], pages: &Range<usize>) -> Vec<PathBuf> {
+    let mut part_images = Vec::new();
+    if pages.start != 0 {
+        part_images.push(images[0].clone());
+    }
+    part_images.extend_from_slice(&images[pages.clone()]);
+    part_images
+}
+
+/// The table of contents of the part made of `pages`. A chapter begun in an
+/// earlier part is listed as continued on the part's first page.
+pub fn part_toc(toc_chapters: &[TocChapter], pages: &Range<usize>) -> Vec<TocChapter> {
+    // Index in the part of page `page_index` of the book
+    let offset = |page_index: usize| match pages.start {
+        0 => page_index,
+        start => page_index - start + 1,
+    };
+
+    let mut part_toc = Vec::new();
+
+    if pages.start != 0 {
+        if let Some(chapter) = toc_chapters
+            .iter()
+            .rev()
+            .find(|chapter| chapter.first_page <= pages.start)
+            .filter(|chapter| chapter.first_page < pages.start)
+        {
+            part_toc.push(TocChapter {
+                title: format!("{} (continued)", chapter.title),
+                first_page: 1,
+            });
+        }
+    }
+
+    part_toc.extend(
+        toc_chapters
+            .iter()
+            .filter(|chapter| pages.contains(&chapter.first_page))
+            .map(|chapter| TocChapter {
+                title: chapter.title.clone(),
+                first_page: offset(chapter.first_page),
+            }),
+    );
+
+    part_toc
+}
+
+#[test]
+fn books_are_split_between_chapters_then_pages() {
+    let chapter = |title: &str, first_page| TocChapter {
+        title: title.to_string(),
+        first_page,
+    };
+    // A cover, a chapter of 3 pages and one of 5
+    let toc_chapters = vec![chapter("Chapter 1", 1), chapter("Chapter 2", 4)];
+    let page_sizes = [10, 10, 10, 10, 10, 10, 10, 10, 10];
+
+    assert_eq!(plan(&page_sizes, &toc_chapters, 100), vec![0..9]);
+
+    // Chapter 2 with the cover fits, so the parts end where it starts
+    assert_eq!(plan(&page_sizes, &toc_chapters, 60), vec![0..4, 4..9]);
+
+    // Chapter 2 does not, so it is split between its pages
+    assert_eq!(plan(&page_sizes, &toc_chapters, 40), vec![0..4, 4..7, 7..9]);
+
+    // A page too large on its own gets a part all the same
+    assert_eq!(
+        plan(&[10, 10, 50, 10], &[chapter("Chapter 1", 1)], 25),
+        vec![0..2, 2..3, 3..4]
+    );
+
+    let continued = part_toc(&toc_chapters, &(7..9));
+    assert_eq!(continued.len(), 1);
+    assert_eq!(continued[0].title, "Chapter 2 (continued)");
+    assert_eq!(continued[0].first_page, 1);
+
+    let second = part_toc(&toc_chapters, &(4..9));
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].title, "Chapter 2");
+    assert_eq!(second[0].first_page, 1);
+
+    let images: Vec<PathBuf> = (0..9).map(|page| PathBuf::from(page.to_string())).collect();
+    assert_eq!(
+        part_images(&images, &(7..9)),
+        vec![PathBuf::from("0"), PathBuf::from("7"), PathBuf::from("8")]
+    );
+}
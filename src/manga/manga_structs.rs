@@ -1,12 +1,19 @@
-use crate::assets::image_paths;
-use crate::manga::common::{get_json, Outputfile};
-use crate::manga::make_mobi;
+use crate::assets::{self, Asset};
+use crate::manga::common::Outputfile;
+use crate::manga::download::{
+    self, AtHomeServer, ChapterFailure, DownloadError, PageError, PageFailure,
+};
+use crate::manga::image_processing::process_page;
+use crate::manga::long_strip;
+use crate::manga::make_mobi::{self, BookMetadata, TocChapter};
+use crate::manga::settings::BuildSettings;
+use crate::manga::BuildError;
+use crate::workspace::Workspace;
 
 use cursive::utils::Counter;
 use image::{imageops, DynamicImage};
 use std::fs;
-use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::thread;
 
 // ─── Mangaseries ─────────────────────────────────────────────────────────────
@@ -19,11 +26,57 @@ pub struct MangaSeries {
     pub demographic: String,
     pub status: String,
     pub year: String,
+    /// MangaDex code of the language the series was first published in, e.g. `ja`
+    pub original_language: String,
+    /// MangaDex code of the language the chapters were fetched in
+    pub language: String,
     pub tags: Vec<String>,
     pub cover_url: String,
     pub volumes: Vec<MangaVolume>,
 }
 
+impl MangaSeries {
+    /// Metadata of the book at `series_index` in this series, e.g. a volume
+    /// number, made of `chapters`
+    pub fn book_metadata(&self, series_index: &str, chapters: &[MangaChapter]) -> BookMetadata {
+        // Fields MangaDex leaves empty come through as "null"
+        let known = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty() && value != "null").then(|| value.to_string())
+        };
+
+        BookMetadata {
+            series: self.title.clone(),
+            author: String::from("KindleMangaReader"),
+            // Volumes and chapters without a number, such as "none", are left unordered
+            series_index: series_index
+                .parse::<f32>()
+                .is_ok()
+                .then(|| series_index.to_string()),
+            description: known(&self.description),
+            subjects: known(&self.demographic)
+                .into_iter()
+                .chain(self.tags.iter().cloned())
+                .collect(),
+            year: known(&self.year),
+            language: self.language.clone(),
+            mangadex_id: self.id.clone(),
+            chapter_ids: chapters.iter().map(|chapter| chapter.id.clone()).collect(),
+            // RFC 3339 dates in the same offset sort like the times they stand for
+            updated_at: chapters
+                .iter()
+                .filter_map(|chapter| chapter.published_at.clone())
+                .max(),
+            volume: chapters
+                .first()
+                .map(|chapter| chapter.volume_title.clone())
+                .filter(|volume| volume.parse::<f32>().is_ok()),
+            chapter: None,
+            part: None,
+        }
+    }
+}
+
 // ─── Mangavolume ─────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone)]
@@ -35,36 +88,70 @@ pub struct MangaVolume {
 }
 
 impl MangaVolume {
-    fn download_images(&self) -> Vec<PathBuf> {
-        let volume_images: Vec<Vec<PathBuf>> = self
-            .chapters
-            .iter()
-            .map(|chapter| chapter.download_images())
-            .collect();
+    fn download_images(
+        &self,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+    ) -> Result<(Vec<PathBuf>, Vec<TocChapter>), DownloadError> {
+        let mut volume_images: Vec<Vec<PathBuf>> = Vec::new();
+        let mut failed_chapters: Vec<ChapterFailure> = Vec::new();
+
+        // Every chapter is attempted, so the report lists all missing pages at once
+        for chapter in self.chapters.iter() {
+            match chapter.download_images(workspace, settings) {
+                Ok(chapter_images) => volume_images.push(chapter_images),
+                Err(chapter_failure) => failed_chapters.push(chapter_failure),
+            }
+        }
 
-        let mut volume_images = volume_images.concat();
+        let cover = match self.download_cover(workspace, settings) {
+            Ok(cover) if failed_chapters.is_empty() => cover,
+            cover => {
+                return Err(DownloadError {
+                    manga_title: self.manga_title.clone(),
+                    chapters: failed_chapters,
+                    cover: cover.err(),
+                })
+            }
+        };
 
-        volume_images.insert(0, self.download_cover());
+        // The cover comes first, so the first chapter starts on the second page
+        let mut toc_chapters: Vec<TocChapter> = Vec::new();
+        let mut first_page = 1;
 
-        volume_images
-    }
+        for (chapter, chapter_images) in self.chapters.iter().zip(volume_images.iter()) {
+            toc_chapters.push(TocChapter {
+                title: chapter.toc_title(),
+                first_page,
+            });
+            first_page += chapter_images.len();
+        }
+
+        let mut volume_images = volume_images.concat();
 
-    fn download_cover(&self) -> PathBuf {
-        fn internal_download_cover(cover_url: String) -> PathBuf {
-            let cover_file_name = cover_url.split('/').last().unwrap();
+        volume_images.insert(0, cover);
 
-            let file_path = PathBuf::from(format!("temp\\{}", cover_file_name));
+        Ok((volume_images, toc_chapters))
+    }
 
-            let mut file = File::create(&file_path).unwrap();
+    /// Downloads and processes the cover, retrying it like a page
+    fn download_cover(
+        &self,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+    ) -> Result<PathBuf, PageError> {
+        fn internal_download_cover(
+            cover_url: String,
+            workspace: &Workspace,
+            settings: &BuildSettings,
+        ) -> Result<PathBuf, PageError> {
+            let file_path = workspace.file(file_name_with_extension_of("cover", &cover_url));
 
-            reqwest::blocking::get(&cover_url)
-                .unwrap()
-                .copy_to(&mut file)
-                .unwrap();
+            download::fetch_cover(&cover_url, &file_path)?;
 
-            resize_image_to_a4(&file_path);
+            process_page(&file_path, settings);
 
-            fs::canonicalize(&file_path).unwrap()
+            Ok(fs::canonicalize(&file_path).unwrap())
         }
 
         fn add_overlay(
@@ -78,57 +165,77 @@ impl MangaVolume {
         }
 
         match &self.cover_url {
-            VolumeCoverImage::Found(image_url) => internal_download_cover(image_url.to_string()),
+            VolumeCoverImage::Found(image_url) => {
+                internal_download_cover(image_url.to_string(), workspace, settings)
+            }
             VolumeCoverImage::NotFound(image_url) => {
-                let image_path = internal_download_cover(image_url.to_string());
-                add_overlay(
+                let image_path =
+                    internal_download_cover(image_url.to_string(), workspace, settings)?;
+                Ok(add_overlay(
                     image::open(&image_path).unwrap(),
-                    image::open(image_paths::VOLUME_COVER_NOT_FOUND).unwrap(),
+                    image::load_from_memory(assets::bytes(Asset::VolumeCoverNotFound)).unwrap(),
                     image_path,
-                )
+                ))
             }
         }
     }
 
-    pub fn to_mobi(&self, counter: &Counter) -> Outputfile {
-        //! 1. Downloads the volume images
+    pub fn to_mobi(
+        &self,
+        series: &MangaSeries,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+        counter: &Counter,
+    ) -> Result<Vec<Outputfile>, BuildError> {
+        //! 1. Downloads the volume images into `workspace`
         //! 2. Adds the end of volume image
         //! 3. Converts it to mobi
         //!
-        //! Returns `Outputfile` with `path` (mobi path) and `size` (mobi file size),
-        //!  `manga_title` (manga title), `volume_title` (volume title) and `chapter_title` as None
+        //! Returns an `Outputfile` for each part the volume was split into, one if it
+        //!  was not, with `path` (mobi path) and `size` (mobi file size),
+        //!  `manga_title` (manga title), `volume_title` (volume title) and `chapter_title` as None,
+        //!  or a `BuildError` listing every page that could not be downloaded or
+        //!  why the generated epub is invalid
 
         counter.tick(1);
 
-        let mut images = self.download_images();
+        let (mut images, toc_chapters) = self.download_images(workspace, settings)?;
 
         counter.tick(1);
 
-        images.push(fs::canonicalize(PathBuf::from(image_paths::END_OF_VOLUME)).unwrap());
+        images.push(assets::write_to(Asset::EndOfVolume, workspace));
 
         counter.tick(1);
 
-        let mobi_file = make_mobi::make_volume(
+        let books = make_mobi::make_volume(
+            workspace,
             &images,
-            &self.manga_title,
+            &toc_chapters,
+            &series.book_metadata(&self.title, &self.chapters),
             &self.title,
-            &String::from("KindleMangaReader"),
-        );
+            settings,
+        )?;
 
         counter.tick(1);
 
-        let mobi_size = mobi_file.metadata().unwrap().len().to_owned();
+        let output_files = books
+            .into_iter()
+            .map(|book| Outputfile {
+                content_type: String::from("volume"),
+                manga_title: self.manga_title.clone(),
+                volume_title: self.title.clone(),
+                chapter_title: None,
+                part: book.part,
+                size: book.path.metadata().unwrap().len(),
+                path: fs::canonicalize(book.path).unwrap(),
+                build_settings: Some(settings.clone()),
+                warnings: book.log.warnings,
+            })
+            .collect();
 
         counter.tick(1);
 
-        Outputfile {
-            content_type: String::from("volume"),
-            manga_title: self.manga_title.clone(),
-            volume_title: self.title.clone(),
-            chapter_title: None,
-            path: fs::canonicalize(mobi_file).unwrap(),
-            size: mobi_size,
-        }
+        Ok(output_files)
     }
 }
 
@@ -137,177 +244,306 @@ impl MangaVolume {
 #[derive(Debug, Clone)]
 pub struct MangaChapter {
     pub id: String,
+    /// Chapter number
     pub title: String,
+    /// Name the scanlators gave the chapter, if any
+    pub name: Option<String>,
+    /// When the chapter was published on MangaDex, in RFC 3339
+    pub published_at: Option<String>,
     pub volume_title: String,
     pub manga_title: String,
 }
 
 impl MangaChapter {
-    fn download_images(&self) -> Vec<PathBuf> {
-        let chapter_data = get_json(format!(
-            "https://api.mangadex.org/at-home/server/{}",
-            self.id
-        ));
+    /// How the chapter is listed in the table of contents
+    pub fn toc_title(&self) -> String {
+        match &self.name {
+            Some(name) => format!("Chapter {}: {}", self.title, name),
+            None => format!("Chapter {}", self.title),
+        }
+    }
 
-        let base_url = chapter_data["baseUrl"].as_str().unwrap().to_owned();
-        let chapter_hash = chapter_data["chapter"]["hash"].as_str().unwrap().to_owned();
+    fn download_images(
+        &self,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+    ) -> Result<Vec<PathBuf>, ChapterFailure> {
+        let failure = |server_error, pages| ChapterFailure {
+            volume_title: self.volume_title.clone(),
+            chapter_title: self.title.clone(),
+            server_error,
+            pages,
+        };
+
+        let server = AtHomeServer::request(&self.id, settings.source_quality)
+            .map_err(|error| failure(Some(error), vec![]))?;
 
         // vector of all join handles
         let mut join_handles = vec![];
 
-        for image in chapter_data["chapter"]["dataSaver"]
-            .as_array()
-            .unwrap()
-            .clone()
-        {
+        for (index, file_name) in server.pages.iter().enumerate() {
             // 👇 to stop the borrow checker from complaining
-            let local_base_url = base_url.clone();
-            let local_chapter_hash = chapter_hash.clone();
-
-            // Spawns a new thread with a closure that, creates the image urls
-            // and then downloads the images,
-            // and then resizes them to a4
+            let local_server = server.clone();
+            let local_chapter_id = self.id.clone();
+            let local_settings = settings.clone();
+            let file_name = file_name.clone();
+
+            // Pages are stored under our own names, so that server file names
+            // from different chapters of the same job can never clash
+            let file_path = workspace.file(file_name_with_extension_of(
+                &format!("{}-{:03}", self.id, index + 1),
+                &file_name,
+            ));
+
+            // Spawns a new thread with a closure that downloads and verifies
+            // the image, and then resizes and enhances it for the device
             let join_handle = thread::spawn(move || {
-                let file_name = image.as_str().unwrap();
-
-                let url = format!(
-                    "{}/data-saver/{}/{}",
-                    local_base_url, local_chapter_hash, file_name
-                );
-
-                let file_path = PathBuf::from(format!("temp\\{}", file_name));
-
-                let mut file = File::create(&file_path).unwrap();
-
-                reqwest::blocking::get(url)
-                    .unwrap()
-                    .copy_to(&mut file)
-                    .unwrap();
+                download::fetch_page(&local_chapter_id, &local_server, &file_name, &file_path)
+                    .map_err(|error| PageFailure {
+                        page: index + 1,
+                        file_name,
+                        error,
+                    })?;
 
                 let canonicalize_file_path = fs::canonicalize(file_path).unwrap();
 
-                resize_image_to_a4(&canonicalize_file_path);
+                process_page(&canonicalize_file_path, &local_settings);
 
-                canonicalize_file_path
+                Ok(canonicalize_file_path)
             });
 
             join_handles.push(join_handle);
-            // image_file_paths.push((image, base_url, chapter_hash))
         }
 
         // join the threads and get the image file path as the output
-        let image_file_paths: Vec<PathBuf> = join_handles
-            .into_iter()
-            .map(|handler| handler.join().unwrap())
-            .collect();
+        let mut image_file_paths: Vec<PathBuf> = Vec::new();
+        let mut failed_pages: Vec<PageFailure> = Vec::new();
+
+        for handler in join_handles {
+            match handler.join().unwrap() {
+                Ok(image_file_path) => image_file_paths.push(image_file_path),
+                Err(page_failure) => failed_pages.push(page_failure),
+            }
+        }
 
-        image_file_paths
+        if !failed_pages.is_empty() {
+            return Err(failure(None, failed_pages));
+        }
+
+        if long_strip::is_long_strip(&image_file_paths, &settings.long_strip) {
+            image_file_paths =
+                long_strip::slice_chapter(image_file_paths, workspace, &self.id, settings);
+        }
+
+        Ok(image_file_paths)
     }
 
-    pub fn to_mobi(&self, counter: &Counter) -> Outputfile {
-        //! 1. Downloads the chapter images
+    pub fn to_mobi(
+        &self,
+        series: &MangaSeries,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+        counter: &Counter,
+    ) -> Result<Vec<Outputfile>, BuildError> {
+        //! 1. Downloads the chapter images into `workspace`
         //! 2. Adds the end of chapter image
         //! 3. Converts it to mobi
         //!
-        //! Returns `Outputfile` with `path` (mobi path) and `size` (mobi file size),
-        //!  `manga_title` (manga title), `volume_title` (volume title) and `chapter_title` (chapter title)
+        //! Returns an `Outputfile` for each part the chapter was split into, one if it
+        //!  was not, with `path` (mobi path) and `size` (mobi file size),
+        //!  `manga_title` (manga title), `volume_title` (volume title) and `chapter_title` (chapter title),
+        //!  or a `BuildError` listing every page that could not be downloaded or
+        //!  why the generated epub is invalid
 
         counter.tick(1);
 
-        let mut images = self.download_images();
+        let mut images = self
+            .download_images(workspace, settings)
+            .map_err(|chapter_failure| DownloadError {
+                manga_title: self.manga_title.clone(),
+                chapters: vec![chapter_failure],
+                cover: None,
+            })?;
 
         counter.tick(1);
 
-        images.push(fs::canonicalize(PathBuf::from(image_paths::END_OF_CHAPTER)).unwrap());
+        images.push(assets::write_to(Asset::EndOfChapter, workspace));
 
         counter.tick(1);
 
-        let mobi_file = make_mobi::make_chapter(
+        // A chapter has no cover of its own, its first page is used as one
+        let toc_chapters = vec![TocChapter {
+            title: self.toc_title(),
+            first_page: 0,
+        }];
+
+        let books = make_mobi::make_chapter(
+            workspace,
             &images,
-            &self.manga_title,
+            &toc_chapters,
+            &BookMetadata {
+                chapter: Some(self.title.clone()),
+                ..series.book_metadata(&self.title, std::slice::from_ref(self))
+            },
             &self.volume_title,
             &self.title,
-            &String::from("KindleMangaReader"),
-        );
+            settings,
+        )?;
 
         counter.tick(1);
 
-        let mobi_size = mobi_file.metadata().unwrap().len().to_owned();
+        let output_files = books
+            .into_iter()
+            .map(|book| Outputfile {
+                content_type: String::from("chapter"),
+                manga_title: self.manga_title.clone(),
+                volume_title: self.volume_title.clone(),
+                chapter_title: Some(self.title.clone()),
+                part: book.part,
+                size: book.path.metadata().unwrap().len(),
+                path: fs::canonicalize(book.path).unwrap(),
+                build_settings: Some(settings.clone()),
+                warnings: book.log.warnings,
+            })
+            .collect();
 
         counter.tick(1);
 
-        Outputfile {
-            content_type: String::from("chapter"),
-            manga_title: self.manga_title.clone(),
-            volume_title: self.volume_title.clone(),
-            chapter_title: Some(self.title.clone()),
-            path: fs::canonicalize(mobi_file).unwrap(),
-            size: mobi_size,
+        Ok(output_files)
+    }
+}
+
+// ─── Chapterbundle ───────────────────────────────────────────────────────────
+
+/// Chapters of any volumes made into a single book
+#[derive(Debug, Clone)]
+pub struct ChapterBundle {
+    pub manga_title: String,
+    /// In order of their numbers
+    pub chapters: Vec<MangaChapter>,
+}
+
+impl ChapterBundle {
+    /// Bundles `chapters`, putting them in order of their numbers
+    pub fn new(mut chapters: Vec<MangaChapter>) -> ChapterBundle {
+        let number = |chapter: &MangaChapter| chapter.title.parse::<f32>().unwrap_or(f32::MAX);
+        chapters.sort_by(|a, b| number(a).total_cmp(&number(b)));
+
+        ChapterBundle {
+            manga_title: chapters
+                .first()
+                .map(|chapter| chapter.manga_title.clone())
+                .unwrap_or_default(),
+            chapters,
         }
     }
+
+    /// Numbers of the first and last chapter, e.g. `120–149`
+    pub fn chapter_span(&self) -> String {
+        span(self.chapters.iter().map(|chapter| &chapter.title))
+    }
+
+    /// Titles of the first and last volume the chapters are from
+    pub fn volume_span(&self) -> String {
+        span(self.chapters.iter().map(|chapter| &chapter.volume_title))
+    }
+
+    /// How the bundle is listed, e.g. `Chapters 120–149`
+    pub fn title(&self) -> String {
+        match self.chapters.len() {
+            1 => format!("Chapter {}", self.chapter_span()),
+            _ => format!("Chapters {}", self.chapter_span()),
+        }
+    }
+
+    pub fn to_mobi(
+        &self,
+        series: &MangaSeries,
+        workspace: &Workspace,
+        settings: &BuildSettings,
+        counter: &Counter,
+    ) -> Result<Vec<Outputfile>, BuildError> {
+        //! 1. Downloads the chapter images into `workspace`, behind the series cover
+        //! 2. Adds the end of chapter image
+        //! 3. Converts it to mobi
+        //!
+        //! Returns an `Outputfile` for each part the bundle was split into, one if it
+        //!  was not, with `volume_title` and `chapter_title` spanning the bundle's
+        //!  volumes and chapters, or a `BuildError` like `MangaVolume::to_mobi`
+
+        counter.tick(1);
+
+        // The chapters are gathered like a volume's, with a table of contents
+        let volume = MangaVolume {
+            title: self.volume_span(),
+            manga_title: self.manga_title.clone(),
+            cover_url: VolumeCoverImage::Found(series.cover_url.clone()),
+            chapters: self.chapters.clone(),
+        };
+        let (mut images, toc_chapters) = volume.download_images(workspace, settings)?;
+
+        counter.tick(1);
+
+        images.push(assets::write_to(Asset::EndOfChapter, workspace));
+
+        counter.tick(1);
+
+        let first_chapter = self.chapters.first().map_or("", |chapter| &chapter.title);
+        let books = make_mobi::make_bundle(
+            workspace,
+            &images,
+            &toc_chapters,
+            &series.book_metadata(first_chapter, &self.chapters),
+            &self.title(),
+            &self.chapter_span(),
+            settings,
+        )?;
+
+        counter.tick(1);
+
+        let output_files = books
+            .into_iter()
+            .map(|book| Outputfile {
+                content_type: String::from("bundle"),
+                manga_title: self.manga_title.clone(),
+                volume_title: self.volume_span(),
+                chapter_title: Some(self.chapter_span()),
+                part: book.part,
+                size: book.path.metadata().unwrap().len(),
+                path: fs::canonicalize(book.path).unwrap(),
+                build_settings: Some(settings.clone()),
+                warnings: book.log.warnings,
+            })
+            .collect();
+
+        counter.tick(1);
+
+        Ok(output_files)
+    }
 }
 
 // ─── Functions ───────────────────────────────────────────────────────────────
 
-// Resize image to have A4 page size
-use std::io::BufWriter;
-use std::num::NonZeroU32;
-
-use fast_image_resize as fr;
-use image::codecs::png::PngEncoder;
-use image::{io::Reader as ImageReader, ColorType, ImageEncoder};
-
-pub fn resize_image_to_a4(image_path: &PathBuf) {
-    let opened_image = ImageReader::open(image_path).unwrap().decode().unwrap();
-
-    let width = NonZeroU32::new(opened_image.width()).unwrap();
-    let height = NonZeroU32::new(opened_image.height()).unwrap();
-
-    let mut src_image = fr::Image::from_vec_u8(
-        width,
-        height,
-        opened_image.to_rgba8().into_raw(),
-        fr::PixelType::U8x4,
-    )
-    .unwrap();
-
-    // Multiple RGB channels of source image by alpha channel
-    // (not required for the Nearest algorithm)
-    let alpha_mul_div = fr::MulDiv::default();
-    alpha_mul_div
-        .multiply_alpha_inplace(&mut src_image.view_mut())
-        .unwrap();
-
-    // height of the destination image
-    let hszize =
-        ((opened_image.height() as f64) * (2480.0 / (opened_image.width() as f64))).round() as u32;
-
-    // Create container for data of destination image
-    let dst_width = NonZeroU32::new(2480).unwrap();
-    let dst_height = NonZeroU32::new(hszize).unwrap();
-    let mut dst_image = fr::Image::new(dst_width, dst_height, src_image.pixel_type());
-
-    // Get mutable view of destination image data
-    let mut dst_view = dst_image.view_mut();
-
-    // Create Resizer instance and resize source image
-    // into buffer of destination image
-    let mut resizer = fr::Resizer::new(fr::ResizeAlg::Convolution(fr::FilterType::CatmullRom));
-    resizer.resize(&src_image.view(), &mut dst_view).unwrap();
-
-    // Divide RGB channels of destination image by alpha
-    alpha_mul_div.divide_alpha_inplace(&mut dst_view).unwrap();
-
-    // Write destination image as PNG-file
-    let mut result_buf = BufWriter::new(Vec::new());
-    PngEncoder::new(&mut result_buf)
-        .write_image(
-            dst_image.buffer(),
-            dst_width.get(),
-            dst_height.get(),
-            ColorType::Rgba8,
-        )
-        .unwrap();
+/// The first and last of `titles` joined by a dash, or the one title if
+/// they are the same
+fn span<'a>(mut titles: impl DoubleEndedIterator<Item = &'a String>) -> String {
+    let first = titles.next().cloned().unwrap_or_default();
+    match titles.next_back() {
+        Some(last) if *last != first => format!("{}–{}", first, last),
+        _ => first,
+    }
+}
+
+/// `stem` with the extension of the file `url` points to, e.g. `("cover", ".../a1b2.jpg")`
+/// gives `cover.jpg`
+fn file_name_with_extension_of(stem: &str, url: &str) -> String {
+    match Path::new(url)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    }
 }
 
 // ─── Enums ───────────────────────────────────────────────────────────────────
@@ -316,3 +552,36 @@ pub enum VolumeCoverImage {
     Found(String),
     NotFound(String),
 }
+
+#[test]
+fn bundles_are_titled_after_their_first_and_last_chapter() {
+    let chapter = |volume: &str, title: &str| MangaChapter {
+        id: format!("id-{}", title),
+        title: title.to_string(),
+        name: None,
+        published_at: None,
+        volume_title: volume.to_string(),
+        manga_title: String::from("Spy x Family"),
+    };
+
+    let bundle = ChapterBundle::new(vec![
+        chapter("none", "149"),
+        chapter("14", "120"),
+        chapter("none", "120.5"),
+    ]);
+    assert_eq!(
+        bundle
+            .chapters
+            .iter()
+            .map(|chapter| chapter.title.as_str())
+            .collect::<Vec<_>>(),
+        vec!["120", "120.5", "149"]
+    );
+    assert_eq!(bundle.title(), "Chapters 120–149");
+    assert_eq!(bundle.volume_span(), "14–none");
+    assert_eq!(bundle.manga_title, "Spy x Family");
+
+    let single = ChapterBundle::new(vec![chapter("14", "120")]);
+    assert_eq!(single.title(), "Chapter 120");
+    assert_eq!(single.volume_span(), "14");
+}
@@ -1,14 +1,33 @@
 mod common;
+mod download;
+mod image_processing;
+mod long_strip;
 mod make_mobi;
 mod manga_structs;
-
-pub use self::manga_structs::MangaSeries;
-pub use common::Outputfile;
+pub mod naming;
+mod panels;
+mod settings;
+
+// Reachable from outside only so the vendored library's examples run as doctests
+#[doc(hidden)]
+pub use self::make_mobi::epub_builder;
+pub use self::manga_structs::{ChapterBundle, MangaChapter, MangaSeries};
+pub use common::{Outputfile, Part};
+pub use download::{ChapterFailure, DownloadError, PageError, PageFailure, SourceQuality};
+pub use image_processing::Enhancement;
+pub use long_strip::{LongStrip, LongStripMode};
+pub use make_mobi::ValidationReport;
+pub use make_mobi::{ConversionError, ConversionLog};
+pub use settings::{
+    BuildSettings, Compression, ConverterBackend, ConverterSettings, DeviceProfile, OutputFormat,
+    ReadingDirection,
+};
 
 use self::common::get_json;
-use self::manga_structs::{MangaChapter, MangaVolume, VolumeCoverImage};
+use self::manga_structs::{MangaVolume, VolumeCoverImage};
+use crate::config;
 
-use std::{error::Error, fmt};
+use std::{collections::HashMap, error::Error, fmt};
 
 #[derive(Debug)]
 pub struct MangaNotFound;
@@ -21,12 +40,116 @@ impl fmt::Display for MangaNotFound {
     }
 }
 
-/// Get the manga by id and return a `MangaSeries`
+/// Why a volume or chapter could not be built
+#[derive(Debug)]
+pub enum BuildError {
+    Download(DownloadError),
+    /// The generated EPUB is broken, so it was not converted
+    InvalidEpub {
+        book_title: String,
+        report: ValidationReport,
+    },
+    /// The EPUB is fine but could not be turned into the wanted format
+    Conversion {
+        book_title: String,
+        error: ConversionError,
+    },
+}
+
+impl Error for BuildError {}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Download(error) => write!(f, "{}", error),
+            BuildError::InvalidEpub { book_title, report } => {
+                writeln!(f, "{} was not converted, its EPUB is invalid:", book_title)?;
+                for issue in report.errors() {
+                    writeln!(f, "    {}: {}", issue.file, issue.message)?;
+                }
+                Ok(())
+            }
+            BuildError::Conversion { book_title, error } => {
+                writeln!(f, "{} was not converted: {}", book_title, error)
+            }
+        }
+    }
+}
+
+impl From<DownloadError> for BuildError {
+    fn from(error: DownloadError) -> Self {
+        BuildError::Download(error)
+    }
+}
+
+/// What the feed says about a chapter that the aggregate endpoint leaves out
+#[derive(Debug, Default)]
+struct FeedChapter {
+    name: Option<String>,
+    published_at: Option<String>,
+}
+
+/// Names and publication dates of the chapters of `manga_id` translated to
+/// `language`, keyed by chapter id.
+///
+/// The aggregate endpoint only lists chapter numbers, so these come from the
+/// feed, which is paged.
+fn get_feed_chapters(manga_id: &str, language: &str) -> HashMap<String, FeedChapter> {
+    let mut feed_chapters = HashMap::new();
+    let mut offset = 0;
+
+    loop {
+        let feed = get_json(format!(
+            "https://api.mangadex.org/manga/{}/feed?translatedLanguage%5B%5D={}&limit=500&offset={}",
+            manga_id, language, offset
+        ));
+
+        let chapters = match feed["data"].as_array() {
+            Some(chapters) if !chapters.is_empty() => chapters,
+            _ => break,
+        };
+
+        for chapter in chapters {
+            let Some(id) = chapter["id"].as_str() else {
+                continue;
+            };
+
+            let attribute = |key: &str| {
+                chapter["attributes"][key]
+                    .as_str()
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+
+            feed_chapters.insert(
+                id.to_string(),
+                FeedChapter {
+                    name: attribute("title"),
+                    published_at: attribute("publishAt"),
+                },
+            );
+        }
+
+        offset += chapters.len();
+
+        if offset as u64 >= feed["total"].as_u64().unwrap_or(0) {
+            break;
+        }
+    }
+
+    feed_chapters
+}
+
+/// Get the manga by id and return a `MangaSeries`, with the chapters translated
+/// to the configured language
 pub fn get_manga_by_id(manga_id: &str) -> Result<MangaSeries, MangaNotFound> {
     if manga_id.trim().is_empty() {
         return Err(MangaNotFound);
     }
 
+    let language = config::get().language;
+
     let manga_details_data = get_json(format!("https://api.mangadex.org/manga/{}", manga_id));
 
     if manga_details_data["result"]
@@ -57,6 +180,10 @@ pub fn get_manga_by_id(manga_id: &str) -> Result<MangaSeries, MangaNotFound> {
         .to_string()
         .replace('"', "");
 
+    let manga_original_language = manga_details_data["data"]["attributes"]["originalLanguage"]
+        .to_string()
+        .replace('"', "");
+
     let manga_tags: Vec<String> = manga_details_data["data"]["attributes"]["tags"]
         .as_array()
         .unwrap()
@@ -94,12 +221,14 @@ pub fn get_manga_by_id(manga_id: &str) -> Result<MangaSeries, MangaNotFound> {
 
     // Get aggregated manga data
     let aggregated_manga_data = get_json(format!(
-        "https://api.mangadex.org/manga/{}/aggregate?translatedLanguage%5B%5D=en",
-        manga_id
+        "https://api.mangadex.org/manga/{}/aggregate?translatedLanguage%5B%5D={}",
+        manga_id, language
     ));
 
     let manga_volume = &aggregated_manga_data["volumes"];
 
+    let feed_chapters = get_feed_chapters(manga_id, &language);
+
     let mut manga_volumes: Vec<MangaVolume> = Vec::new();
 
     for (volume_title, volume_data) in manga_volume.as_object().unwrap() {
@@ -124,8 +253,13 @@ pub fn get_manga_by_id(manga_id: &str) -> Result<MangaSeries, MangaNotFound> {
                 volume_data["chapters"].as_object().unwrap()
             {
                 if internal_chapter_title.eq(&chapter_title) {
+                    let chapter_id = chapter_data["id"].to_string().replace('"', "");
+                    let feed_chapter = feed_chapters.get(&chapter_id);
+
                     chapters.push(MangaChapter {
-                        id: chapter_data["id"].to_string().replace('"', ""),
+                        name: feed_chapter.and_then(|chapter| chapter.name.clone()),
+                        published_at: feed_chapter.and_then(|chapter| chapter.published_at.clone()),
+                        id: chapter_id,
                         title: chapter_data["chapter"].to_string().replace('"', ""),
                         volume_title: volume_title.to_owned(),
                         manga_title: manga_title.to_owned().replace('"', ""),
@@ -201,6 +335,8 @@ pub fn get_manga_by_id(manga_id: &str) -> Result<MangaSeries, MangaNotFound> {
         demographic: manga_demographic,
         status: manga_status,
         year: manga_year,
+        original_language: manga_original_language,
+        language,
         tags: manga_tags,
         cover_url: manga_cover_url,
         volumes: sorted_volumes,
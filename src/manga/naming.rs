@@ -0,0 +1,324 @@
+//! File names of built books, from a template such as
+//! `{series} - v{volume:02}[ c{chapter:03}]`.
+//!
+//! `{field}` is replaced by the field, `{field:0N}` pads its whole number part
+//! with zeros to N digits so volumes sort as 01, 02, 10, and a `[...]` group is
+//! left out when a field in it is empty, e.g. the chapter of a volume. The
+//! fields are `series`, `volume`, `chapter`, `chapters`, which bundles of
+//! chapters from any volumes have instead of a volume, e.g. `120–149`, and
+//! `part`, which books split for size have, e.g. `1 of 2`. Bundles and parts
+//! are named apart even if the template has no `{chapters}` or `{part}`.
+//!
+//! Names are made safe for the Kindle's FAT32 filesystem, which rejects
+//! `<>:"/\|?*`, control characters, trailing dots and over-long names.
+
+/// What books were named before templates existed, with sortable numbers
+pub const DEFAULT_TEMPLATE: &str =
+    "{series}[ volume {volume:02}][ chapter {chapter:03}][ chapters {chapters}][ part {part}]";
+
+/// Longest name kept, in UTF-16 units as FAT32 counts them, which leaves room
+/// under its 255 for an extension and a collision suffix
+const MAX_STEM_LENGTH: usize = 200;
+
+/// Names DOS devices took, which FAT32 still will not give to a file
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// What a file name template can refer to
+#[derive(Debug, Clone, Copy)]
+pub struct NameFields<'a> {
+    pub series: &'a str,
+    /// Empty for bundles, whose chapters can be from several volumes
+    pub volume: &'a str,
+    /// Only books of a single chapter have one
+    pub chapter: Option<&'a str>,
+    /// Only bundles have one, the numbers of their first and last chapter
+    pub chapters: Option<&'a str>,
+    /// Only books split for size have one
+    pub part: Option<&'a str>,
+}
+
+impl NameFields<'_> {
+    fn get(&self, field: &str) -> Option<&str> {
+        match field {
+            "series" => Some(self.series),
+            "volume" => Some(self.volume),
+            "chapter" => self.chapter,
+            "chapters" => self.chapters,
+            "part" => self.part,
+            _ => None,
+        }
+    }
+}
+
+/// The file name, without extension, `template` gives the book of `fields`
+pub fn file_stem(template: &str, fields: &NameFields) -> String {
+    let mut rendered = render(template, fields);
+    // Bundles must not overwrite each other, nor parts
+    if let (Some(chapters), false) = (fields.chapters, template.contains("{chapters")) {
+        rendered.push_str(&format!(" chapters {}", chapters));
+    }
+    if let (Some(part), false) = (fields.part, template.contains("{part")) {
+        rendered.push_str(&format!(" part {}", part));
+    }
+
+    let stem = sanitize(&rendered);
+
+    if stem.is_empty() {
+        sanitize(&render(DEFAULT_TEMPLATE, fields))
+    } else {
+        stem
+    }
+}
+
+/// Why `template` cannot name books, if it refers to a field there is not or
+/// leaves volumes without a name
+pub fn check_template(template: &str) -> Result<(), String> {
+    let sample = NameFields {
+        series: "Series",
+        volume: "1",
+        chapter: Some("1"),
+        chapters: Some("1–2"),
+        part: Some("1 of 2"),
+    };
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let placeholder = &rest[start + 1..];
+        let Some(end) = placeholder.find('}') else {
+            return Err(String::from("a { is never closed"));
+        };
+        let field = placeholder[..end].split(':').next().unwrap_or_default();
+        if sample.get(field).is_none() {
+            return Err(format!(
+                "{{{}}} is not series, volume, chapter, chapters or part",
+                field
+            ));
+        }
+        rest = &placeholder[end + 1..];
+    }
+
+    let volume = NameFields {
+        chapter: None,
+        chapters: None,
+        part: None,
+        ..sample
+    };
+    if sanitize(&render(template, &volume)).is_empty() {
+        return Err(String::from("gives volumes an empty name"));
+    }
+
+    Ok(())
+}
+
+/// `name` with everything FAT32 rejects replaced, shortened to the longest
+/// name kept
+pub fn sanitize(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|character| match character {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => ' ',
+            character if character.is_control() => ' ',
+            character => character,
+        })
+        .collect();
+
+    // Replacing characters leaves runs of spaces behind
+    let mut name = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut length = 0;
+    if let Some((end, _)) = name.char_indices().find(|(_, character)| {
+        length += character.len_utf16();
+        length > MAX_STEM_LENGTH
+    }) {
+        name.truncate(end);
+    }
+
+    let mut name = name.trim_end_matches(['.', ' ']).to_string();
+
+    let base = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base))
+    {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+/// `file_name`, or the first of `name (2).ext`, `name (3).ext`... that is not
+/// `taken` by another book
+pub fn unique(file_name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(file_name) {
+        return file_name.to_string();
+    }
+
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (file_name, String::new()),
+    };
+
+    (2..)
+        .map(|number| format!("{} ({}){}", stem, number, extension))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
+}
+
+fn render(template: &str, fields: &NameFields) -> String {
+    let mut rendered = String::new();
+    // Text of the `[...]` group being read, and whether a field in it was empty
+    let mut group: Option<(String, bool)> = None;
+    let mut characters = template.chars();
+
+    while let Some(character) = characters.next() {
+        let (text, value_missing) = match character {
+            '[' if group.is_none() => {
+                group = Some((String::new(), false));
+                continue;
+            }
+            ']' if group.is_some() => {
+                if let Some((text, false)) = group.take() {
+                    rendered.push_str(&text);
+                }
+                continue;
+            }
+            '{' => {
+                let placeholder: String = characters.by_ref().take_while(|c| *c != '}').collect();
+                let (field, format) = placeholder
+                    .split_once(':')
+                    .unwrap_or((placeholder.as_str(), ""));
+                match fields.get(field) {
+                    Some(value) if !value.is_empty() => (pad(value, format), false),
+                    _ => (String::new(), true),
+                }
+            }
+            character => (character.to_string(), false),
+        };
+
+        match &mut group {
+            Some((group_text, group_missing)) => {
+                group_text.push_str(&text);
+                *group_missing |= value_missing;
+            }
+            None => rendered.push_str(&text),
+        }
+    }
+
+    // A group that is never closed is kept as written
+    if let Some((text, false)) = group {
+        rendered.push_str(&text);
+    }
+
+    rendered
+}
+
+/// `value` with its whole number part padded with zeros to the width `format`,
+/// e.g. `03`, asks for. Values that are not numbers, like `none`, are left alone.
+fn pad(value: &str, format: &str) -> String {
+    let Some(width) = format
+        .strip_prefix('0')
+        .and_then(|width| width.parse::<usize>().ok())
+    else {
+        return value.to_string();
+    };
+
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (value, None),
+    };
+    if whole.is_empty() || !whole.chars().all(|c| c.is_ascii_digit()) {
+        return value.to_string();
+    }
+
+    match fraction {
+        Some(fraction) => format!("{:0>width$}.{}", whole, fraction, width = width),
+        None => format!("{:0>width$}", whole, width = width),
+    }
+}
+
+#[test]
+fn file_names_follow_the_template_and_suit_fat32() {
+    let chapter = NameFields {
+        series: "Fate/stay night: Heaven's Feel?",
+        volume: "3",
+        chapter: Some("12.5"),
+        chapters: None,
+        part: None,
+    };
+    let volume = NameFields {
+        chapter: None,
+        ..chapter
+    };
+
+    assert_eq!(
+        file_stem("{series} - v{volume:02}[ c{chapter:03}]", &chapter),
+        "Fate stay night Heaven's Feel - v03 c012.5"
+    );
+    assert_eq!(
+        file_stem("{series} - v{volume:02}[ c{chapter:03}]", &volume),
+        "Fate stay night Heaven's Feel - v03"
+    );
+    assert_eq!(
+        file_stem(DEFAULT_TEMPLATE, &volume),
+        "Fate stay night Heaven's Feel volume 03"
+    );
+    assert_eq!(
+        file_stem(
+            "{series} v{volume:02}",
+            &NameFields {
+                volume: "none",
+                ..volume
+            }
+        ),
+        "Fate stay night Heaven's Feel vnone"
+    );
+
+    let part = NameFields {
+        part: Some("1 of 2"),
+        ..volume
+    };
+    assert_eq!(
+        file_stem(DEFAULT_TEMPLATE, &part),
+        "Fate stay night Heaven's Feel volume 03 part 1 of 2"
+    );
+    assert_eq!(
+        file_stem("{series} v{volume:02}", &part),
+        "Fate stay night Heaven's Feel v03 part 1 of 2"
+    );
+
+    let bundle = NameFields {
+        volume: "",
+        chapter: None,
+        chapters: Some("120–149"),
+        ..part
+    };
+    assert_eq!(
+        file_stem(DEFAULT_TEMPLATE, &bundle),
+        "Fate stay night Heaven's Feel chapters 120–149 part 1 of 2"
+    );
+    assert_eq!(
+        file_stem("{series} - {chapters}", &bundle),
+        "Fate stay night Heaven's Feel - 120–149 part 1 of 2"
+    );
+
+    assert_eq!(sanitize("Dr. Stone..."), "Dr. Stone");
+    assert_eq!(sanitize("con"), "_con");
+    assert_eq!(sanitize(&"ア".repeat(300)).chars().count(), MAX_STEM_LENGTH);
+
+    let taken = ["Book.azw3", "Book (2).azw3"];
+    assert_eq!(
+        unique("Book.azw3", |name| taken.contains(&name)),
+        "Book (3).azw3"
+    );
+    assert_eq!(
+        unique("Other.azw3", |name| taken.contains(&name)),
+        "Other.azw3"
+    );
+    assert!(check_template(DEFAULT_TEMPLATE).is_ok());
+    assert!(check_template("{series} - {title}").is_err());
+    assert!(check_template("{series").is_err());
+    assert!(check_template("[{chapter}]").is_err());
+}
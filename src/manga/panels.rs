@@ -0,0 +1,324 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{imageops, GrayImage};
+use serde::{Deserialize, Serialize};
+
+use crate::manga::settings::ReadingDirection;
+
+/// Width pages are shrunk to before looking for panels, detection does not
+/// need more detail and is much faster on a small page
+const DETECTION_WIDTH: u32 = 400;
+
+/// Largest difference between the lightest and darkest pixel of a line for it
+/// to count as gutter
+const GUTTER_TOLERANCE: u8 = 24;
+
+/// Lines of artwork thinner than this fraction of the page are noise, such as
+/// page numbers, and not bands of panels
+const MIN_BAND: f32 = 0.03;
+
+/// Panels smaller than this fraction of the page area are not worth magnifying
+const MIN_PANEL_AREA: f32 = 0.02;
+
+/// How far a panel is magnified at most, so small panels do not turn into blur
+const MAX_MAGNIFICATION: f32 = 2.0;
+
+/// Recursion limit of the cuts, real pages never need more
+const MAX_DEPTH: u32 = 6;
+
+// ─── Panel ───────────────────────────────────────────────────────────────────
+
+/// A panel of a page, in fractions of the page size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Panel {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A panel as the page template places it, all values are percentages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Region {
+    /// Position of the panel in reading order, starting at 1
+    pub ordinal: usize,
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Placement of the whole page image when this panel is magnified
+    pub target_left: f64,
+    pub target_top: f64,
+    pub target_size: f64,
+}
+
+impl Panel {
+    /// The region showing this panel as the `ordinal`th stop of Panel View
+    pub fn region(&self, ordinal: usize) -> Region {
+        let magnification = (1.0 / self.width)
+            .min(1.0 / self.height)
+            .clamp(1.0, MAX_MAGNIFICATION);
+
+        // Centre the panel on the screen, without moving the page past its edges
+        let offset = |start: f32, size: f32| {
+            (0.5 - (start + size / 2.0) * magnification).clamp(1.0 - magnification, 0.0)
+        };
+
+        // Rounded as f64, so the template shows 3.3% rather than 3.299999952316284%
+        let percent = |fraction: f32| (f64::from(fraction) * 1000.0).round() / 10.0;
+
+        Region {
+            ordinal,
+            left: percent(self.x),
+            top: percent(self.y),
+            width: percent(self.width),
+            height: percent(self.height),
+            target_left: percent(offset(self.x, self.width)),
+            target_top: percent(offset(self.y, self.height)),
+            target_size: percent(magnification),
+        }
+    }
+}
+
+// ─── Detection ───────────────────────────────────────────────────────────────
+
+/// A rectangle of the detection image, in pixels
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Rows,
+    Columns,
+}
+
+/// Finds the panels of a page by cutting it along its gutters, returning them
+/// in reading order. Pages with fewer than two panels, such as splash pages,
+/// have none.
+pub fn detect(page: &GrayImage, direction: ReadingDirection) -> Vec<Panel> {
+    if page.width() == 0 || page.height() == 0 {
+        return Vec::new();
+    }
+
+    let page = if page.width() > DETECTION_WIDTH {
+        let height = (page.height() as f32 * DETECTION_WIDTH as f32 / page.width() as f32)
+            .round()
+            .max(1.0) as u32;
+        imageops::thumbnail(page, DETECTION_WIDTH, height)
+    } else {
+        page.clone()
+    };
+
+    let whole = Rect {
+        x: 0,
+        y: 0,
+        width: page.width(),
+        height: page.height(),
+    };
+
+    let mut rects: Vec<Rect> = Vec::new();
+    cut(&page, whole, direction, 0, &mut rects);
+
+    let panels: Vec<Panel> = rects
+        .into_iter()
+        .map(|rect| Panel {
+            x: rect.x as f32 / page.width() as f32,
+            y: rect.y as f32 / page.height() as f32,
+            width: rect.width as f32 / page.width() as f32,
+            height: rect.height as f32 / page.height() as f32,
+        })
+        .filter(|panel| panel.width * panel.height >= MIN_PANEL_AREA)
+        .collect();
+
+    if panels.len() < 2 {
+        Vec::new()
+    } else {
+        panels
+    }
+}
+
+/// Recursive XY-cut: split into bands of rows, then each band into columns,
+/// until a rectangle has no gutter running all the way through it
+fn cut(
+    page: &GrayImage,
+    rect: Rect,
+    direction: ReadingDirection,
+    depth: u32,
+    panels: &mut Vec<Rect>,
+) {
+    let rows = bands(page, rect, Axis::Rows);
+    if rows.len() > 1 && depth < MAX_DEPTH {
+        for row in rows {
+            cut(page, row, direction, depth + 1, panels);
+        }
+        return;
+    }
+
+    let mut columns = bands(page, rect, Axis::Columns);
+    if columns.len() > 1 && depth < MAX_DEPTH {
+        if direction == ReadingDirection::RightToLeft {
+            columns.reverse();
+        }
+        for column in columns {
+            cut(page, column, direction, depth + 1, panels);
+        }
+        return;
+    }
+
+    // No further gutters, trimmed to its artwork this is a panel
+    if let (Some(row), Some(column)) = (rows.first(), columns.first()) {
+        panels.push(Rect {
+            x: column.x,
+            y: row.y,
+            width: column.width,
+            height: row.height,
+        });
+    }
+}
+
+/// The runs of artwork between the gutters crossing `rect` along `axis`
+fn bands(page: &GrayImage, rect: Rect, axis: Axis) -> Vec<Rect> {
+    let (length, min_band) = match axis {
+        Axis::Rows => (rect.height, (page.height() as f32 * MIN_BAND) as u32),
+        Axis::Columns => (rect.width, (page.width() as f32 * MIN_BAND) as u32),
+    };
+
+    let mut bands: Vec<Rect> = Vec::new();
+    let mut start: Option<u32> = None;
+
+    for offset in 0..=length {
+        let gutter = offset == length || is_gutter(page, rect, axis, offset);
+
+        match (start, gutter) {
+            (None, false) => start = Some(offset),
+            (Some(band_start), true) => {
+                if offset - band_start > min_band.max(1) {
+                    bands.push(match axis {
+                        Axis::Rows => Rect {
+                            y: rect.y + band_start,
+                            height: offset - band_start,
+                            ..rect
+                        },
+                        Axis::Columns => Rect {
+                            x: rect.x + band_start,
+                            width: offset - band_start,
+                            ..rect
+                        },
+                    });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    bands
+}
+
+/// Whether the line at `offset` along `axis` of `rect` is a single tone
+fn is_gutter(page: &GrayImage, rect: Rect, axis: Axis, offset: u32) -> bool {
+    let pixels: Box<dyn Iterator<Item = u8>> = match axis {
+        Axis::Rows => Box::new(
+            (rect.x..rect.x + rect.width).map(move |x| page.get_pixel(x, rect.y + offset).0[0]),
+        ),
+        Axis::Columns => Box::new(
+            (rect.y..rect.y + rect.height).map(move |y| page.get_pixel(rect.x + offset, y).0[0]),
+        ),
+    };
+
+    let (min, max) = pixels.fold((u8::MAX, u8::MIN), |(min, max), luma| {
+        (min.min(luma), max.max(luma))
+    });
+
+    max.saturating_sub(min) <= GUTTER_TOLERANCE
+}
+
+// ─── Regions Files ───────────────────────────────────────────────────────────
+
+/// File the Panel View regions of the page at `page_path` are kept in, so
+/// the book is made without decoding the page again
+fn regions_path(page_path: &Path) -> PathBuf {
+    page_path.with_extension("panels.json")
+}
+
+/// Finds the panels of `page`, the processed page saved at `page_path`, and
+/// keeps their regions next to it. If they cannot be kept the page is shown
+/// whole, as if it had no panels.
+pub fn save_regions(page: &GrayImage, page_path: &Path, direction: ReadingDirection) {
+    let regions: Vec<Region> = detect(page, direction)
+        .iter()
+        .enumerate()
+        .map(|(index, panel)| panel.region(index + 1))
+        .collect();
+
+    let path = regions_path(page_path);
+    let written = fs::File::create(&path)
+        .map_err(serde_json::Error::io)
+        .and_then(|file| serde_json::to_writer(file, &regions));
+    if let Err(error) = written {
+        log::warn!("could not save {}: {}", path.display(), error);
+    }
+}
+
+/// Removes the regions kept for the page at `page_path`, if there are any
+pub fn remove_regions(page_path: &Path) {
+    let _ = fs::remove_file(regions_path(page_path));
+}
+
+/// The regions kept for the page at `page_path`, none if its panels were
+/// not looked for
+pub fn saved_regions(page_path: &Path) -> Vec<Region> {
+    fs::read_to_string(regions_path(page_path))
+        .ok()
+        .and_then(|serialized| serde_json::from_str(&serialized).ok())
+        .unwrap_or_default()
+}
+
+#[test]
+fn panels_follow_reading_direction() {
+    // A 2x2 grid of dark panels on white, with 10 pixel gutters
+    let page = GrayImage::from_fn(200, 300, |x, y| {
+        let in_gutter =
+            !(10..190).contains(&x) || (95..105).contains(&x) || !(10..290).contains(&y);
+        let in_row_gutter = (145..155).contains(&y);
+        if in_gutter || in_row_gutter {
+            image::Luma([255])
+        } else {
+            image::Luma([((x * 7 + y * 13) % 200) as u8])
+        }
+    });
+
+    let panels = detect(&page, ReadingDirection::RightToLeft);
+    assert_eq!(panels.len(), 4);
+
+    // Top right, top left, bottom right, bottom left
+    assert!(panels[0].x > 0.5 && panels[0].y < 0.5);
+    assert!(panels[1].x < 0.5 && panels[1].y < 0.5);
+    assert!(panels[2].x > 0.5 && panels[2].y > 0.5);
+    assert!(panels[3].x < 0.5 && panels[3].y > 0.5);
+
+    let panels = detect(&page, ReadingDirection::LeftToRight);
+    assert!(panels[0].x < 0.5 && panels[0].y < 0.5);
+
+    // A splash page is a single panel, so it gets no Panel View
+    let splash = GrayImage::from_fn(200, 300, |x, y| image::Luma([((x + y) % 200) as u8]));
+    assert!(detect(&splash, ReadingDirection::RightToLeft).is_empty());
+
+    // Regions reach the page template as short percentages
+    let region = Panel {
+        x: 0.033,
+        y: 0.5,
+        width: 0.45,
+        height: 0.45,
+    }
+    .region(1);
+    let region = serde_json::to_value(region).unwrap();
+    assert_eq!(region["left"], serde_json::json!(3.3));
+    assert_eq!(region["width"], serde_json::json!(45.0));
+}
@@ -0,0 +1,289 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::manga::download::SourceQuality;
+use crate::manga::image_processing::Enhancement;
+use crate::manga::long_strip::{LongStrip, LongStripMode, LONG_STRIP_TAG};
+use crate::manga::manga_structs::MangaSeries;
+use crate::manga::naming;
+use crate::series_settings::SeriesSettings;
+
+// ─── Device Profile ──────────────────────────────────────────────────────────
+
+/// The screen a book is built for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeviceProfile {
+    pub name: String,
+    /// Width in pixels pages are resized to
+    pub width: u32,
+    /// Height in pixels of the screen
+    pub height: u32,
+    /// Enhancement used for series that do not set their own
+    pub enhancement: Enhancement,
+}
+
+impl DeviceProfile {
+    fn new(name: &str, width: u32, height: u32, enhancement: Enhancement) -> DeviceProfile {
+        DeviceProfile {
+            name: name.to_string(),
+            width,
+            height,
+            enhancement,
+        }
+    }
+
+    /// All the built-in profiles, the default one first
+    pub fn all() -> Vec<DeviceProfile> {
+        vec![
+            // What every book was built with before profiles existed
+            DeviceProfile::new("A4", 2480, 3508, Enhancement::disabled()),
+            DeviceProfile::new("Kindle Basic", 1072, 1448, Enhancement::default()),
+            DeviceProfile::new("Kindle Paperwhite", 1236, 1648, Enhancement::default()),
+            DeviceProfile::new("Kindle Oasis", 1264, 1680, Enhancement::default()),
+            DeviceProfile::new("Kindle Scribe", 1860, 2480, Enhancement::default()),
+        ]
+    }
+
+    pub fn by_name(name: &str) -> Option<DeviceProfile> {
+        DeviceProfile::all()
+            .into_iter()
+            .find(|profile| profile.name.eq(name))
+    }
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        DeviceProfile::all().remove(0)
+    }
+}
+
+// ─── Output Format ───────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// KF8 (AZW3), for Kindles
+    #[default]
+    Mobi,
+    /// Fixed-layout EPUB 3, for readers that take EPUB directly
+    Epub,
+    /// The pages in a zip with a `ComicInfo.xml`, for comic readers like KOReader
+    Cbz,
+    /// One page per image, for the Kindle Scribe and for archiving
+    Pdf,
+}
+
+// ─── Converter ───────────────────────────────────────────────────────────────
+
+/// What turns the EPUB into a Kindle book
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConverterBackend {
+    /// The built-in KF8 writer, which needs nothing installed
+    #[default]
+    Native,
+    /// Amazon's kindlegen
+    Kindlegen,
+    /// Calibre's ebook-convert
+    EbookConvert,
+}
+
+/// How much an external converter compresses the text of a book
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Fastest to build, pages are images so there is little to gain
+    #[default]
+    None,
+    Standard,
+    /// kindlegen's slow Huffman/CDIC compression
+    Huffdic,
+}
+
+fn default_timeout_secs() -> u64 {
+    300
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConverterSettings {
+    #[serde(default)]
+    pub backend: ConverterBackend,
+    #[serde(default)]
+    pub compression: Compression,
+    /// External converters are stopped after running this long
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Where the external converter is, if it is not on `PATH`
+    #[serde(default)]
+    pub tool_path: Option<PathBuf>,
+}
+
+impl Default for ConverterSettings {
+    fn default() -> Self {
+        ConverterSettings {
+            backend: ConverterBackend::default(),
+            compression: Compression::default(),
+            timeout_secs: default_timeout_secs(),
+            tool_path: None,
+        }
+    }
+}
+
+// ─── Reading Direction ───────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadingDirection {
+    /// Pages turn towards the left, like Japanese manga
+    #[default]
+    RightToLeft,
+    LeftToRight,
+}
+
+impl ReadingDirection {
+    /// The direction comics originally written in `language` are read in
+    pub fn from_language(language: &str) -> ReadingDirection {
+        // MangaDex uses codes like "ja", "ja-ro" or "zh-hk"
+        match language.split('-').next().unwrap_or_default() {
+            "ja" => ReadingDirection::RightToLeft,
+            _ => ReadingDirection::LeftToRight,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReadingDirection::RightToLeft => "Right to Left",
+            ReadingDirection::LeftToRight => "Left to Right",
+        }
+    }
+}
+
+// ─── Build Settings ──────────────────────────────────────────────────────────
+
+/// Everything that decides how the pages of a book are processed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuildSettings {
+    /// Which of MangaDex's renditions the pages are downloaded in
+    #[serde(default)]
+    pub source_quality: SourceQuality,
+    pub profile: DeviceProfile,
+    pub enhancement: Enhancement,
+    #[serde(default)]
+    pub long_strip: LongStrip,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub reading_direction: ReadingDirection,
+    /// List every page under its chapter in the table of contents
+    #[serde(default)]
+    pub toc_page_entries: bool,
+    /// Mark the panels of each page so Kindle can step through them magnified
+    #[serde(default)]
+    pub panel_view: bool,
+    /// Derive the book's identifier and dates from its chapters and these
+    /// settings, so building it again gives an identical EPUB. Books queued
+    /// before this existed keep being built with a random identifier.
+    #[serde(default)]
+    pub reproducible: bool,
+    /// How books wanted for Kindle are converted from the EPUB
+    #[serde(default)]
+    pub converter: ConverterSettings,
+    /// Template the file names of books are made from, see [`naming`]
+    #[serde(default = "default_file_name_template")]
+    pub file_name_template: String,
+    /// Largest book made, in bytes. Larger volumes and chapters are split into
+    /// parts between chapters, or between pages where one chapter is too large.
+    #[serde(default)]
+    pub max_book_bytes: Option<u64>,
+}
+
+fn default_file_name_template() -> String {
+    String::from(naming::DEFAULT_TEMPLATE)
+}
+
+impl BuildSettings {
+    /// Settings for `profile`, with the series' own tuning taking precedence
+    pub fn new(profile: DeviceProfile, series: &SeriesSettings) -> BuildSettings {
+        let enhancement = series
+            .enhancement
+            .clone()
+            .unwrap_or_else(|| profile.enhancement.clone());
+
+        let long_strip = LongStrip {
+            mode: match series.long_strip {
+                Some(true) => LongStripMode::On,
+                Some(false) => LongStripMode::Off,
+                None => LongStripMode::Auto,
+            },
+            ..LongStrip::default()
+        };
+
+        BuildSettings {
+            source_quality: SourceQuality::default(),
+            profile,
+            enhancement,
+            long_strip,
+            output_format: OutputFormat::default(),
+            reading_direction: series.reading_direction.unwrap_or_default(),
+            toc_page_entries: false,
+            panel_view: series.panel_view,
+            reproducible: true,
+            converter: ConverterSettings::default(),
+            file_name_template: default_file_name_template(),
+            max_book_bytes: None,
+        }
+    }
+
+    /// Settings for building `manga`, taking its tags and original language
+    /// into account where the series has no override
+    pub fn for_series(
+        profile: DeviceProfile,
+        series: &SeriesSettings,
+        manga: &MangaSeries,
+    ) -> BuildSettings {
+        let mut settings = BuildSettings::new(profile, series).with_tags(&manga.tags);
+
+        if series.reading_direction.is_none() {
+            settings.reading_direction = ReadingDirection::from_language(&manga.original_language);
+        }
+
+        settings
+    }
+
+    /// Turns long-strip mode on for webtoons, unless the series decided otherwise
+    pub fn with_tags(mut self, tags: &[String]) -> BuildSettings {
+        if self.long_strip.mode == LongStripMode::Auto
+            && tags.iter().any(|tag| tag.eq(LONG_STRIP_TAG))
+        {
+            self.long_strip.mode = LongStripMode::On;
+        }
+        self
+    }
+}
+
+impl Default for BuildSettings {
+    fn default() -> Self {
+        BuildSettings::new(DeviceProfile::default(), &SeriesSettings::default())
+    }
+}
+
+#[test]
+fn reading_direction_follows_original_language() {
+    assert_eq!(
+        ReadingDirection::from_language("ja"),
+        ReadingDirection::RightToLeft
+    );
+    assert_eq!(
+        ReadingDirection::from_language("ja-ro"),
+        ReadingDirection::RightToLeft
+    );
+    assert_eq!(
+        ReadingDirection::from_language("ko"),
+        ReadingDirection::LeftToRight
+    );
+    assert_eq!(
+        ReadingDirection::from_language("zh-hk"),
+        ReadingDirection::LeftToRight
+    );
+    assert_eq!(
+        ReadingDirection::from_language("en"),
+        ReadingDirection::LeftToRight
+    );
+}
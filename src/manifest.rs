@@ -0,0 +1,306 @@
+//! Record of what every book was built from and what came out, so that a book
+//! already built from the same inputs is not downloaded and converted again,
+//! and copies on the Kindle that a newer build replaces can be pointed out.
+
+use std::{
+    fs::{self, read_to_string},
+    io,
+    path::Path,
+};
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use sha2::{Digest, Sha256};
+
+use crate::kindle::OnDeviceFile;
+use crate::manga::{BuildSettings, Outputfile, Part};
+use crate::paths;
+use crate::que::QueFile;
+
+// ─── Serde Structs ───────────────────────────────────────────────────────────
+
+/// Everything a book is built from. Books built from the same inputs are the
+/// same book, even if their files differ in dates or identifiers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuildInputs {
+    /// `volume`, `chapter` or `bundle`, as books of the same chapters differ by kind
+    pub kind: String,
+    pub manga_id: String,
+    /// MangaDex ids of the chapters in the book, in order
+    pub chapter_ids: Vec<String>,
+    /// How the pages are downloaded and processed, the source quality included
+    pub build_settings: BuildSettings,
+    /// Version of KindleMangaReader, whose templates and processing go into the book
+    pub app_version: String,
+}
+
+impl BuildInputs {
+    pub fn new(
+        kind: &str,
+        manga_id: &str,
+        chapter_ids: Vec<String>,
+        build_settings: &BuildSettings,
+    ) -> BuildInputs {
+        BuildInputs {
+            kind: kind.to_string(),
+            manga_id: manga_id.to_string(),
+            chapter_ids,
+            build_settings: build_settings.clone(),
+            app_version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// SHA-256 of the inputs, the same for every build of the same book
+    pub fn key(&self) -> String {
+        format!(
+            "{:x}",
+            Sha256::digest(serde_json::to_string(self).unwrap().as_bytes())
+        )
+    }
+}
+
+/// A book that was built, or one part of it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub inputs: BuildInputs,
+    /// Key of `inputs`
+    pub key: String,
+    pub content_type: String,
+    pub manga_title: String,
+    pub volume_title: String,
+    pub chapter_title: Option<String>,
+    pub part: Option<Part>,
+    /// SHA-256 of the book's file
+    pub sha256: String,
+    pub size: u64,
+    /// When the book was built, in RFC 3339
+    pub built_at: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    books: Vec<ManifestEntry>,
+}
+
+/// Where a book built from the same inputs already is
+pub enum ExistingBuild {
+    /// Every part is on the Kindle
+    OnKindle,
+    /// Every part is in the queue
+    Queued(Vec<QueFile>),
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+fn read_manifest() -> Manifest {
+    let path = paths::manifest_db();
+    if !path.exists() {
+        return Manifest::default();
+    }
+
+    let serialized = read_to_string(&path).unwrap();
+
+    serde_json::from_str(&serialized).unwrap_or_else(|error| {
+        log::warn!("ignoring unreadable {}: {}", path.display(), error);
+        Manifest::default()
+    })
+}
+
+fn write_manifest(manifest: &Manifest) {
+    let serialized = serde_json::to_string(manifest).unwrap();
+
+    fs::write(paths::manifest_db(), serialized).unwrap();
+}
+
+/// SHA-256 of the file at `path`
+pub fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Records that `output_file` was built from `inputs`, replacing the record of
+/// an earlier build of the same inputs and part
+pub fn record(inputs: &BuildInputs, output_file: &Outputfile) {
+    let Ok(sha256) = file_sha256(&output_file.path) else {
+        return;
+    };
+
+    let entry = ManifestEntry {
+        inputs: inputs.clone(),
+        key: inputs.key(),
+        content_type: output_file.content_type.clone(),
+        manga_title: output_file.manga_title.clone(),
+        volume_title: output_file.volume_title.clone(),
+        chapter_title: output_file.chapter_title.clone(),
+        part: output_file.part,
+        sha256,
+        size: output_file.size,
+        built_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+
+    let mut manifest = read_manifest();
+    // Only the other parts of the same split are kept
+    let part_count = |part: Option<Part>| part.map(|part| part.count);
+    manifest.books.retain(|book| {
+        book.key != entry.key
+            || (book.part != entry.part && part_count(book.part) == part_count(entry.part))
+    });
+    manifest.books.push(entry);
+
+    write_manifest(&manifest);
+}
+
+/// The build of `inputs` that is already on the Kindle or in the queue, with
+/// `on_device` and `queued` listing what is there
+pub fn existing_build(
+    inputs: &BuildInputs,
+    on_device: &[OnDeviceFile],
+    queued: &[QueFile],
+) -> Option<ExistingBuild> {
+    read_manifest().existing_build(inputs, on_device, queued)
+}
+
+/// Whether a newer build of the book has replaced `file`, which was copied to
+/// the Kindle. Copies made before builds were recorded are never stale.
+pub fn is_stale(file: &OnDeviceFile) -> bool {
+    read_manifest().is_stale(file)
+}
+
+impl Manifest {
+    fn existing_build(
+        &self,
+        inputs: &BuildInputs,
+        on_device: &[OnDeviceFile],
+        queued: &[QueFile],
+    ) -> Option<ExistingBuild> {
+        let key = inputs.key();
+        let parts: Vec<&ManifestEntry> = self.books.iter().filter(|book| book.key == key).collect();
+
+        if parts.is_empty() {
+            return None;
+        }
+
+        if parts.iter().all(|part| {
+            on_device
+                .iter()
+                .any(|file| file.sha256.as_deref() == Some(part.sha256.as_str()))
+        }) {
+            return Some(ExistingBuild::OnKindle);
+        }
+
+        parts
+            .iter()
+            .map(|part| {
+                queued
+                    .iter()
+                    .find(|file| file.sha256.as_deref() == Some(part.sha256.as_str()))
+                    .cloned()
+            })
+            .collect::<Option<Vec<QueFile>>>()
+            .map(ExistingBuild::Queued)
+    }
+
+    fn is_stale(&self, file: &OnDeviceFile) -> bool {
+        let Some(sha256) = &file.sha256 else {
+            return false;
+        };
+
+        self.books
+            .iter()
+            .filter(|book| {
+                book.content_type == file.r#type
+                    && book.manga_title == file.manga_title
+                    && book.volume_title == file.volume_title
+                    && book.chapter_title == file.chapter_title
+                    && book.part == file.part
+            })
+            .max_by(|a, b| a.built_at.cmp(&b.built_at))
+            .is_some_and(|latest| latest.sha256 != *sha256)
+    }
+}
+
+#[test]
+fn identical_builds_are_found_and_replaced_copies_are_stale() {
+    let settings = BuildSettings::default();
+    let inputs = BuildInputs::new("volume", "6b958848", vec![String::from("a1")], &settings);
+    assert_eq!(inputs.key(), inputs.clone().key());
+    assert_ne!(
+        inputs.key(),
+        BuildInputs::new("bundle", "6b958848", vec![String::from("a1")], &settings).key()
+    );
+    let panel_view = BuildInputs::new(
+        "volume",
+        "6b958848",
+        vec![String::from("a1")],
+        &BuildSettings {
+            panel_view: true,
+            ..settings.clone()
+        },
+    );
+    assert_ne!(inputs.key(), panel_view.key());
+    let original_pages = BuildInputs::new(
+        "volume",
+        "6b958848",
+        vec![String::from("a1")],
+        &BuildSettings {
+            source_quality: crate::manga::SourceQuality::Data,
+            ..settings.clone()
+        },
+    );
+    assert_ne!(inputs.key(), original_pages.key());
+
+    let entry = |inputs: &BuildInputs, sha256: &str, built_at: &str| ManifestEntry {
+        inputs: inputs.clone(),
+        key: inputs.key(),
+        content_type: String::from("volume"),
+        manga_title: String::from("Spy x Family"),
+        volume_title: String::from("2"),
+        chapter_title: None,
+        part: None,
+        sha256: sha256.to_string(),
+        size: 1,
+        built_at: built_at.to_string(),
+    };
+    let manifest = Manifest {
+        books: vec![
+            entry(&inputs, "old", "2026-01-01T00:00:00Z"),
+            entry(&panel_view, "new", "2026-02-01T00:00:00Z"),
+        ],
+    };
+
+    let on_device = OnDeviceFile {
+        r#type: String::from("volume"),
+        manga_title: String::from("Spy x Family"),
+        volume_title: String::from("2"),
+        chapter_title: None,
+        sha256: Some(String::from("old")),
+        ..OnDeviceFile::new()
+    };
+    let queued = QueFile {
+        sha256: Some(String::from("new")),
+        ..QueFile::new()
+    };
+
+    assert!(matches!(
+        manifest.existing_build(&inputs, std::slice::from_ref(&on_device), &[]),
+        Some(ExistingBuild::OnKindle)
+    ));
+    assert!(matches!(
+        manifest.existing_build(&panel_view, &[], std::slice::from_ref(&queued)),
+        Some(ExistingBuild::Queued(files)) if files.len() == 1
+    ));
+    assert!(manifest.existing_build(&panel_view, &[], &[]).is_none());
+
+    // The panel view build is newer than the copy on the Kindle
+    assert!(manifest.is_stale(&on_device));
+    assert!(!manifest.is_stale(&OnDeviceFile {
+        sha256: Some(String::from("new")),
+        ..on_device.clone()
+    }));
+    assert!(!manifest.is_stale(&OnDeviceFile {
+        sha256: None,
+        ..on_device
+    }));
+}
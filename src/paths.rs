@@ -0,0 +1,164 @@
+//! Where KindleMangaReader keeps its files.
+//!
+//! The queue and the build manifest go in the platform's data folder, the
+//! settings and asset pack in its config folder, and build jobs and the cart
+//! in its cache folder, e.g. `~/.local/share/KindleMangaReader` on Linux,
+//! `~/Library/Application Support/KindleMangaReader` on macOS and
+//! `%APPDATA%\KindleMangaReader` on Windows.
+//!
+//! A file named `portable` next to the executable keeps everything beside it
+//! instead, in `assets` and `temp` as earlier versions did, so a copy on a USB
+//! stick carries its queue and settings along. Copies that already have a
+//! queue there from an earlier version stay portable too, and so does a working
+//! directory that has one, as earlier versions kept their files relative to it.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use once_cell::sync::OnceCell;
+
+/// File next to the executable that switches on portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+
+const APPLICATION: &str = "KindleMangaReader";
+
+/// The folders everything else is kept in
+#[derive(Debug, Clone)]
+struct Folders {
+    data: PathBuf,
+    config: PathBuf,
+    cache: PathBuf,
+}
+
+static FOLDERS: OnceCell<Folders> = OnceCell::new();
+
+/// Folder the running executable is in
+pub fn executable_folder() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()?
+        .parent()
+        .map(|folder| folder.to_path_buf())
+}
+
+fn portable_folders(root: &Path) -> Folders {
+    Folders {
+        data: root.join("assets"),
+        config: root.join("assets"),
+        cache: root.join("temp"),
+    }
+}
+
+fn find_folders() -> Folders {
+    let has_legacy_queue = |folder: &PathBuf| portable_folders(folder).data.join("que").is_dir();
+    let is_portable =
+        |folder: &PathBuf| folder.join(PORTABLE_MARKER).is_file() || has_legacy_queue(folder);
+    if let Some(root) = executable_folder().filter(is_portable) {
+        return portable_folders(&root);
+    }
+    // Earlier versions kept their queue relative to the folder they were run from
+    if let Some(root) = env::current_dir().ok().filter(has_legacy_queue) {
+        return portable_folders(&root);
+    }
+
+    match ProjectDirs::from("", "", APPLICATION) {
+        Some(project) => Folders {
+            data: project.data_dir().to_path_buf(),
+            config: project.config_dir().to_path_buf(),
+            cache: project.cache_dir().to_path_buf(),
+        },
+        // No home folder to put them in, so the working directory has to do
+        None => portable_folders(Path::new(".")),
+    }
+}
+
+/// The folders, made on first use
+fn folders() -> &'static Folders {
+    FOLDERS.get_or_init(|| {
+        // Tests keep their files out of the user's folders
+        let folders = if cfg!(test) {
+            portable_folders(&env::temp_dir().join(format!("{}-tests", APPLICATION)))
+        } else {
+            find_folders()
+        };
+        for folder in [&folders.data.join("que"), &folders.config, &folders.cache] {
+            if let Err(error) = fs::create_dir_all(folder) {
+                log::warn!("could not create {}: {}", folder.display(), error);
+            }
+        }
+        folders
+    })
+}
+
+// ─── Data ────────────────────────────────────────────────────────────────────
+
+/// Folder books wait in for the Kindle
+pub fn que_folder() -> PathBuf {
+    folders().data.join("que")
+}
+
+/// What is in the queue
+pub fn que_db() -> PathBuf {
+    que_folder().join("que_db.json")
+}
+
+/// Record of every book built, see [`crate::manifest`]
+pub fn manifest_db() -> PathBuf {
+    folders().data.join("manifest.json")
+}
+
+/// Folder converters such as kindlegen are looked for in, besides `PATH`
+pub fn tools_folder() -> PathBuf {
+    folders().data.join("tools")
+}
+
+// ─── Config ──────────────────────────────────────────────────────────────────
+
+/// Folder whose files override the built-in assets of the same name, e.g.
+/// `pack/templates/page.html` replaces the page template
+pub fn asset_pack_folder() -> PathBuf {
+    folders().config.join("pack")
+}
+
+/// Settings for every book, see [`crate::config`]
+pub fn config_file() -> PathBuf {
+    folders().config.join("config.toml")
+}
+
+pub fn series_settings_db() -> PathBuf {
+    folders().config.join("series_settings.json")
+}
+
+pub fn email_settings() -> PathBuf {
+    folders().config.join("email_settings.json")
+}
+
+// ─── Cache ───────────────────────────────────────────────────────────────────
+
+/// Folder the workspaces of build jobs are made in
+pub fn temp_folder() -> PathBuf {
+    folders().cache.clone()
+}
+
+pub fn cart_file() -> PathBuf {
+    folders().cache.join("cart.txt")
+}
+
+#[test]
+fn portable_mode_keeps_the_earlier_layout() {
+    let root = Path::new("usb").join("KindleMangaReader");
+    let folders = portable_folders(&root);
+
+    assert_eq!(folders.data, root.join("assets"));
+    assert_eq!(folders.config, root.join("assets"));
+    assert_eq!(folders.cache, root.join("temp"));
+}
+
+#[test]
+fn tests_keep_their_files_in_the_temp_folder() {
+    for folder in [que_folder(), asset_pack_folder(), temp_folder()] {
+        assert!(folder.starts_with(env::temp_dir()), "{}", folder.display());
+    }
+}
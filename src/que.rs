@@ -1,43 +1,63 @@
 use std::{
-    error::Error,
     fmt,
     fs::{self, read_to_string},
-    io::Write,
-    path::Path,
-    path::PathBuf,
+    io::{self, Write},
     vec,
 };
 
+use chrono::{SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json;
 
-use crate::assets::que::{self, QUE_FOLDER};
-use crate::kindle::Mount;
-use crate::manga::Outputfile;
+use crate::email::{self, EmailError, EmailSettings};
+use crate::kindle::{KindleError, Mount};
+use crate::manga::{naming, BuildSettings, Outputfile, Part};
+use crate::manifest;
+use crate::paths;
 
-// ─── Errors ──────────────────────────────────────────────────────────────────
-
-#[derive(Debug)]
-pub struct KindleNoSpaceAvailableError;
+// ─── Serde Structs ───────────────────────────────────────────────────────────
 
-impl Error for KindleNoSpaceAvailableError {}
+/// Whether a queued book has reached the Kindle some other way than USB
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum Delivery {
+    /// Waiting for the Kindle to be plugged in
+    #[default]
+    Waiting,
+    /// Sent to the Kindle's email address, at an RFC 3339 time
+    Emailed { at: String },
+    /// Emailing it failed, and why
+    EmailFailed { at: String, error: String },
+}
 
-impl fmt::Display for KindleNoSpaceAvailableError {
+impl fmt::Display for Delivery {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "There is no space left on the kindle")
+        match self {
+            Delivery::Waiting => write!(f, "Waiting for the Kindle"),
+            Delivery::Emailed { at } => write!(f, "Emailed on {}", at),
+            Delivery::EmailFailed { at, error } => {
+                write!(f, "Emailing failed on {}: {}", at, error)
+            }
+        }
     }
 }
 
-// ─── Serde Structs ───────────────────────────────────────────────────────────
-
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct QueFile {
     pub r#type: String,
     pub manga_title: String,
     pub volume_title: String,
     pub chapter_title: Option<String>,
+    #[serde(default)]
+    pub part: Option<Part>,
     pub file_name: String,
     pub size: u64,
+    #[serde(default)]
+    pub build_settings: Option<BuildSettings>,
+    #[serde(default)]
+    pub delivery: Delivery,
+    /// SHA-256 of the book, none for books queued before builds were recorded
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 impl QueFile {
@@ -47,8 +67,12 @@ impl QueFile {
             manga_title: String::new(),
             volume_title: String::new(),
             chapter_title: Some(String::new()),
+            part: None,
             file_name: String::new(),
             size: 0,
+            build_settings: None,
+            delivery: Delivery::default(),
+            sha256: None,
         }
     }
 
@@ -58,8 +82,12 @@ impl QueFile {
             manga_title: self.manga_title.to_owned(),
             volume_title: self.volume_title.to_owned(),
             chapter_title: self.chapter_title.to_owned(),
-            path: PathBuf::from(format!("{}\\{}", QUE_FOLDER, self.file_name)),
+            part: self.part,
+            path: paths::que_folder().join(&self.file_name),
             size: self.size,
+            build_settings: self.build_settings.clone(),
+            // Reported when the book was built
+            warnings: Vec::new(),
         }
     }
 }
@@ -85,83 +113,140 @@ impl QueFiles {
 
 // ─── Functions ───────────────────────────────────────────────────────────────
 
-fn init_que_db() {
-    if !Path::new(que::QUE_DB).exists() {
+fn init_que_db() -> io::Result<()> {
+    if !paths::que_db().exists() {
         let serialized = serde_json::to_string(&QueFiles::new()).unwrap();
 
-        let mut que_db_data_file = fs::File::create(que::QUE_DB).unwrap();
+        let mut que_db_data_file = fs::File::create(paths::que_db())?;
 
-        que_db_data_file.write_all(serialized.as_bytes()).unwrap();
+        que_db_data_file.write_all(serialized.as_bytes())?;
     }
+    Ok(())
 }
 
 pub fn data() -> Vec<QueFile> {
-    init_que_db();
+    init_que_db().unwrap();
 
-    let serialized = read_to_string(que::QUE_DB).unwrap();
+    let serialized = read_to_string(paths::que_db()).unwrap();
 
     let data: QueFiles = serde_json::from_str(&serialized).unwrap();
 
     data.files
 }
 
-pub fn add(output_file: &Outputfile) {
-    init_que_db();
+/// Adds `output_file` to the queue and returns its entry. A book queued again
+/// replaces its earlier copy, another book of the same name gets a numbered one.
+pub fn add(output_file: &Outputfile) -> io::Result<QueFile> {
+    init_que_db()?;
 
     // ─── Edit Que File ───────────────────────────────────────────────────
 
-    let serialized = read_to_string(que::QUE_DB).unwrap();
+    let serialized = read_to_string(paths::que_db())?;
 
-    let mut data: QueFiles = serde_json::from_str(&serialized).unwrap();
+    let mut data: QueFiles = serde_json::from_str(&serialized)?;
 
     // Remove empty OnDeviceFile
-    let empty_data_index = data.files.iter().position(|x| x.r#type.eq(""));
+    let empty_data_index = data.files.iter().position(|x| x.r#type.is_empty());
     if let Some(index) = empty_data_index {
         data.files.remove(index);
     }
 
+    let same_book = |x: &QueFile| {
+        x.r#type == output_file.content_type
+            && x.manga_title == output_file.manga_title
+            && x.volume_title == output_file.volume_title
+            && x.chapter_title == output_file.chapter_title
+            && x.part == output_file.part
+    };
+    let file_name = naming::unique(
+        output_file.path.file_name().unwrap().to_str().unwrap(),
+        |name| match data.files.iter().find(|x| x.file_name == name) {
+            Some(queued) => !same_book(queued),
+            // A file nothing in the queue accounts for is not overwritten
+            None => paths::que_folder().join(name).exists(),
+        },
+    );
+
     let file_data = QueFile {
         r#type: output_file.content_type.to_owned(),
         manga_title: output_file.manga_title.to_owned(),
         volume_title: output_file.volume_title.to_owned(),
         chapter_title: output_file.chapter_title.to_owned(),
-        file_name: output_file
-            .path
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string(),
+        part: output_file.part,
+        file_name,
         size: output_file.size,
+        build_settings: output_file.build_settings.clone(),
+        delivery: Delivery::default(),
+        sha256: manifest::file_sha256(&output_file.path).ok(),
     };
 
-    // check if the data is already in the que, and if there are no duplicates, add the file data
-    let duplicate_index = data
-        .files
-        .iter()
-        .position(|x| x.file_name.eq(&file_data.file_name));
-    if duplicate_index.is_none() {
-        data.files.push(file_data);
-
-        let serialized = serde_json::to_string(&data).unwrap();
+    data.files.retain(|x| x.file_name != file_data.file_name);
+    data.files.push(file_data.clone());
 
-        fs::write(que::QUE_DB, serialized).unwrap();
-    }
+    let serialized = serde_json::to_string(&data).unwrap();
 
-    // ─────────────────────────────────────────────────────────────────────
+    // The book is copied before it is listed, so the queue never lists a
+    // book that is not there
     fs::copy(
         &output_file.path,
-        format!(
-            "{}\\{}",
-            que::QUE_FOLDER,
-            &output_file.path.file_name().unwrap().to_str().unwrap()
-        ),
-    )
-    .unwrap();
+        paths::que_folder().join(&file_data.file_name),
+    )?;
+
+    // ─────────────────────────────────────────────────────────────────────
+    fs::write(paths::que_db(), serialized)?;
+
+    Ok(file_data)
+}
+
+/// Records how `que_file` was delivered
+fn set_delivery(que_file: &QueFile, delivery: Delivery) {
+    let serialized = read_to_string(paths::que_db()).unwrap();
+
+    let mut data: QueFiles = serde_json::from_str(&serialized).unwrap();
+
+    if let Some(file) = data
+        .files
+        .iter_mut()
+        .find(|x| x.file_name == que_file.file_name)
+    {
+        file.delivery = delivery;
+    }
+
+    let serialized = serde_json::to_string(&data).unwrap();
+
+    fs::write(paths::que_db(), serialized).unwrap();
+}
+
+/// Emails `que_file` to the Kindle and records whether that worked. The book
+/// stays in the queue either way, so it can still be copied over USB.
+pub fn email_item(que_file: &QueFile, settings: &EmailSettings) -> Result<(), EmailError> {
+    let output_file = que_file.to_output_file();
+    let subject = output_file
+        .path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let result = email::send(settings, &output_file.path, &subject);
+
+    set_delivery(
+        que_file,
+        match &result {
+            Ok(()) => Delivery::Emailed { at },
+            Err(error) => Delivery::EmailFailed {
+                at,
+                error: error.to_string(),
+            },
+        },
+    );
+
+    result
 }
 
 pub fn remove(que_file: &QueFile) {
-    let serialized = read_to_string(que::QUE_DB).unwrap();
+    let serialized = read_to_string(paths::que_db()).unwrap();
 
     let mut data: QueFiles = serde_json::from_str(&serialized).unwrap();
 
@@ -175,20 +260,17 @@ pub fn remove(que_file: &QueFile) {
 
     let serialized = serde_json::to_string(&data).unwrap();
 
-    fs::write(que::QUE_DB, serialized).unwrap();
+    fs::write(paths::que_db(), serialized).unwrap();
 
-    fs::remove_file(format!("{}\\{}", QUE_FOLDER, que_file.file_name)).unwrap();
+    fs::remove_file(paths::que_folder().join(&que_file.file_name)).unwrap();
 }
 
-pub fn send_item_to_kindle(
-    que_file: &QueFile,
-    kindle: &Mount,
-) -> Result<(), KindleNoSpaceAvailableError> {
+pub fn send_item_to_kindle(que_file: &QueFile, kindle: &Mount) -> Result<(), KindleError> {
     if que_file.size > kindle.available_space {
-        return Err(KindleNoSpaceAvailableError);
+        return Err(KindleError::NoSpace);
     }
 
-    kindle.send_to_kindle(&que_file.to_output_file()).unwrap();
+    kindle.send_to_kindle(&que_file.to_output_file())?;
 
     remove(que_file);
 
@@ -0,0 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, read_to_string},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::manga::{Enhancement, ReadingDirection};
+use crate::paths;
+
+// ─── Serde Structs ───────────────────────────────────────────────────────────
+
+/// Per-series overrides of the device profile defaults.
+///
+/// Every field is optional; `None` means the series follows the profile.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SeriesSettings {
+    #[serde(default)]
+    pub enhancement: Option<Enhancement>,
+    /// Force long-strip slicing on or off instead of detecting it
+    #[serde(default)]
+    pub long_strip: Option<bool>,
+    /// Overrides the direction guessed from the original language
+    #[serde(default)]
+    pub reading_direction: Option<ReadingDirection>,
+    /// Build the series with Kindle Panel View
+    #[serde(default)]
+    pub panel_view: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SeriesSettingsDb {
+    /// Settings keyed by MangaDex manga id
+    series: BTreeMap<String, SeriesSettings>,
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+fn read_db() -> SeriesSettingsDb {
+    let path = paths::series_settings_db();
+    if !path.exists() {
+        return SeriesSettingsDb::default();
+    }
+
+    let serialized = read_to_string(&path).unwrap();
+
+    serde_json::from_str(&serialized).unwrap_or_else(|error| {
+        log::warn!("ignoring unreadable {}: {}", path.display(), error);
+        SeriesSettingsDb::default()
+    })
+}
+
+/// The settings of the series `manga_id`, or the defaults if it has none
+pub fn get(manga_id: &str) -> SeriesSettings {
+    read_db().series.remove(manga_id).unwrap_or_default()
+}
+
+/// Stores `settings` for the series `manga_id`
+pub fn set(manga_id: &str, settings: SeriesSettings) {
+    let mut data = read_db();
+
+    if settings == SeriesSettings::default() {
+        data.series.remove(manga_id);
+    } else {
+        data.series.insert(manga_id.to_string(), settings);
+    }
+
+    let serialized = serde_json::to_string_pretty(&data).unwrap();
+
+    fs::write(paths::series_settings_db(), serialized).unwrap();
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use sysinfo::{Pid, PidExt, System, SystemExt};
+use uuid::Uuid;
+
+use crate::paths;
+
+const JOB_PREFIX: &str = "job-";
+
+const LOCK_FILE: &str = ".lock";
+
+// ─── Workspace ───────────────────────────────────────────────────────────────
+
+/// A private working directory for a single build job.
+///
+/// Every job gets its own `job-<uuid>` folder in the temp folder, so downloaded pages, the
+/// intermediate epub and the converted mobi of one build can never collide with
+/// those of another. The folder is removed when the `Workspace` is dropped,
+/// which also happens while unwinding from a panic.
+#[derive(Debug)]
+pub struct Workspace {
+    path: PathBuf,
+}
+
+impl Workspace {
+    pub fn new() -> io::Result<Workspace> {
+        let path = paths::temp_folder().join(format!("{}{}", JOB_PREFIX, Uuid::new_v4()));
+
+        fs::create_dir_all(&path)?;
+
+        // The lock file records the owning process, so that `sweep` can tell
+        // a live job apart from one left behind by a crashed run
+        fs::write(path.join(LOCK_FILE), process::id().to_string())?;
+
+        Ok(Workspace {
+            path: fs::canonicalize(path)?,
+        })
+    }
+
+    /// Absolute path of the workspace folder
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Absolute path of `file_name` inside the workspace
+    pub fn file<P: AsRef<Path>>(&self, file_name: P) -> PathBuf {
+        self.path.join(file_name)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_dir_all(&self.path) {
+            log::warn!(
+                "could not remove workspace {}: {}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+}
+
+// ─── Functions ───────────────────────────────────────────────────────────────
+
+/// Removes the workspaces of jobs whose process is no longer running.
+///
+/// Meant to be called once at startup, to reclaim the folders left behind by
+/// runs that were killed before their `Workspace` could be dropped.
+pub fn sweep() {
+    let entries = match fs::read_dir(paths::temp_folder()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let mut sys = System::new();
+    sys.refresh_processes();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        let is_job = path.is_dir()
+            && entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(JOB_PREFIX));
+
+        if !is_job {
+            continue;
+        }
+
+        let owner = fs::read_to_string(path.join(LOCK_FILE))
+            .ok()
+            .and_then(|pid| pid.trim().parse::<u32>().ok());
+
+        let is_alive = match owner {
+            // Nothing of ours exists yet when sweeping, so a folder carrying our
+            // own pid was left by an earlier process that happened to share it
+            Some(pid) => pid != process::id() && sys.process(Pid::from_u32(pid)).is_some(),
+            None => false,
+        };
+
+        if !is_alive {
+            log::info!("removing stale workspace {}", path.display());
+
+            fs::remove_dir_all(&path).ok();
+        }
+    }
+}
+
+#[test]
+fn workspace_is_removed_on_drop() {
+    let workspace = Workspace::new().unwrap();
+    let path = workspace.path().to_path_buf();
+    fs::write(workspace.file("page.png"), b"").unwrap();
+
+    assert!(path.join("page.png").is_file());
+
+    drop(workspace);
+
+    assert!(!path.exists());
+}